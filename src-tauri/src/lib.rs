@@ -3,22 +3,26 @@ use std::{
     env,
     fs,
     io::{BufRead, BufReader, Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Child, Command, Stdio},
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
     thread,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::OnceLock;
 use serde::{Deserialize, Serialize};
 use tauri::{async_runtime, Emitter, Manager, State};
 use tauri::path::BaseDirectory;
 use tokio::net::UdpSocket;
 use webrtc::api::media_engine::MediaEngine;
+use webrtc::api::setting_engine::SettingEngine;
 use webrtc::api::APIBuilder;
+use webrtc::ice::mdns::MulticastDnsMode;
+use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
 use webrtc::peer_connection::RTCPeerConnection;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::rtp::packet::Packet;
@@ -33,6 +37,8 @@ use std::os::windows::process::CommandExt;
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 static FFMPEG_PATH: OnceLock<PathBuf> = OnceLock::new();
+// Bundled mockup frame images; resolved once the app is set up.
+static MOCKUP_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 #[cfg(target_os = "windows")]
 fn new_cmd(bin: &str) -> Command {
@@ -45,7 +51,17 @@ fn new_cmd(bin: &str) -> Command {
     Command::new(bin)
 }
 
+// The ffmpeg_path setting, when it points at a file.
+fn ffmpeg_override() -> Option<String> {
+    load_settings()
+        .ffmpeg_path
+        .filter(|path| PathBuf::from(path).is_file())
+}
+
 fn ffmpeg_binary() -> String {
+    if let Some(path) = ffmpeg_override() {
+        return path;
+    }
     let bin_name = if cfg!(target_os = "windows") { "ffmpeg.exe" } else { "ffmpeg" };
     if let Some(p) = FFMPEG_PATH.get() {
         if p.exists() {
@@ -98,6 +114,9 @@ fn ffmpeg_binary() -> String {
 }
 
 fn ffmpeg_binary_with_app_handle(app: &tauri::AppHandle) -> String {
+    if let Some(path) = ffmpeg_override() {
+        return path;
+    }
     let bin_name = if cfg!(target_os = "windows") { "ffmpeg.exe" } else { "ffmpeg" };
     if let Ok(resource_path) =
         app.path().resolve(format!("ffmpeg/{bin_name}"), BaseDirectory::Resource)
@@ -120,7 +139,168 @@ fn ffmpeg_binary_with_app_handle(app: &tauri::AppHandle) -> String {
     ffmpeg_binary()
 }
 
-#[derive(Deserialize)]
+// Error every command returns to the frontend: a stable code to localize on,
+// the values a message needs, and an English fallback. Helpers still produce
+// "code" or "code: detail" strings; From<String> maps those onto the variants,
+// so `?` converts at the command boundary.
+#[derive(Debug, Clone)]
+enum AppError {
+    FfmpegNotFound { bin: String, detail: String },
+    // The last lines of ffmpeg's stderr, when there were any.
+    ExportFailed { log_tail: Option<String> },
+    WindowTitleRequired,
+    RecordingAlreadyRunning,
+    NoActiveRecording,
+    SessionNotFound,
+    InvalidDirectory { path: String },
+    UnknownExportPreset { name: String },
+    Other { code: String, detail: Option<String> },
+}
+
+impl AppError {
+    fn code(&self) -> &str {
+        match self {
+            AppError::FfmpegNotFound { .. } => "ffmpeg_not_found",
+            AppError::ExportFailed { .. } => "export_failed",
+            AppError::WindowTitleRequired => "window_title_required",
+            AppError::RecordingAlreadyRunning => "recording_already_running",
+            AppError::NoActiveRecording => "no_active_recording",
+            AppError::SessionNotFound => "session_not_found",
+            AppError::InvalidDirectory { .. } => "invalid_directory",
+            AppError::UnknownExportPreset { .. } => "unknown_export_preset",
+            AppError::Other { code, .. } => code,
+        }
+    }
+
+    fn params(&self) -> serde_json::Value {
+        match self {
+            AppError::FfmpegNotFound { bin, detail } => {
+                serde_json::json!({ "bin": bin, "detail": detail })
+            }
+            AppError::ExportFailed { log_tail } => serde_json::json!({ "log_tail": log_tail }),
+            AppError::InvalidDirectory { path } => serde_json::json!({ "path": path }),
+            AppError::UnknownExportPreset { name } => serde_json::json!({ "name": name }),
+            AppError::Other {
+                detail: Some(detail),
+                ..
+            } => serde_json::json!({ "detail": detail }),
+            _ => serde_json::json!({}),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            AppError::FfmpegNotFound { .. } => {
+                "FFmpeg could not be started. Reinstall the app or set an FFmpeg path in settings."
+                    .to_string()
+            }
+            AppError::ExportFailed { .. } => {
+                "The export failed. Check the export log for details.".to_string()
+            }
+            AppError::WindowTitleRequired => "Choose a window to record.".to_string(),
+            AppError::RecordingAlreadyRunning => "A recording is already running.".to_string(),
+            AppError::NoActiveRecording => "No recording is running.".to_string(),
+            AppError::SessionNotFound => "The recording could not be found.".to_string(),
+            AppError::InvalidDirectory { path } => format!("The folder {path} cannot be used."),
+            AppError::UnknownExportPreset { name } => format!("Unknown export preset {name}."),
+            AppError::Other { code, detail } => match detail {
+                Some(detail) => format!("{} ({detail})", code.replace('_', " ")),
+                None => code.replace('_', " "),
+            },
+        }
+    }
+}
+
+impl From<String> for AppError {
+    fn from(error: String) -> Self {
+        let (code, detail) = match error.split_once(':') {
+            Some((code, detail))
+                if !code.is_empty()
+                    && code.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') =>
+            {
+                (code.to_string(), Some(detail.trim().to_string()).filter(|d| !d.is_empty()))
+            }
+            _ => (error.trim().to_string(), None),
+        };
+        match (code.as_str(), detail) {
+            ("ffmpeg_not_found", detail) => {
+                let detail = detail.unwrap_or_default();
+                // Built as "ffmpeg_not_found: {err} (bin={bin})".
+                match detail.rsplit_once(" (bin=") {
+                    Some((err, bin)) => AppError::FfmpegNotFound {
+                        bin: bin.trim_end_matches(')').to_string(),
+                        detail: err.to_string(),
+                    },
+                    None => AppError::FfmpegNotFound {
+                        bin: String::new(),
+                        detail,
+                    },
+                }
+            }
+            ("export_failed", log_tail) => AppError::ExportFailed { log_tail },
+            ("window_title_required", _) => AppError::WindowTitleRequired,
+            ("recording_already_running", _) => AppError::RecordingAlreadyRunning,
+            ("no_active_recording", _) => AppError::NoActiveRecording,
+            ("session_not_found", _) => AppError::SessionNotFound,
+            ("invalid_directory", Some(path)) => AppError::InvalidDirectory { path },
+            ("unknown_export_preset", Some(name)) => AppError::UnknownExportPreset { name },
+            (_, detail) => AppError::Other {
+                code: code.clone(),
+                detail,
+            },
+        }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(error: &str) -> Self {
+        AppError::from(error.to_string())
+    }
+}
+
+// Back to the plain "code: detail" form, for helpers that call a command and
+// report strings themselves.
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        match error {
+            AppError::FfmpegNotFound { bin, detail } => format!("ffmpeg_not_found: {detail} (bin={bin})"),
+            AppError::ExportFailed { log_tail: Some(tail) } => format!("export_failed:\n{tail}"),
+            AppError::InvalidDirectory { path } => format!("invalid_directory: {path}"),
+            AppError::UnknownExportPreset { name } => format!("unknown_export_preset: {name}"),
+            AppError::Other {
+                code,
+                detail: Some(detail),
+            } => format!("{code}: {detail}"),
+            error => error.code().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("params", &self.params())?;
+        error.serialize_field("message", &self.message())?;
+        error.end()
+    }
+}
+
+// Structured form of an error string from a command or event that still
+// reports plain strings, such as ExportStatus.error.
+#[tauri::command]
+fn describe_error(error: String) -> AppError {
+    AppError::from(error)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 struct StartRecordingRequest {
     resolution: String,
     fps: u32,
@@ -130,9 +310,13 @@ struct StartRecordingRequest {
     capture_mode: Option<String>,
     window_title: Option<String>,
     region: Option<CaptureRegion>,
+    #[serde(default)]
+    speed_factor: Option<f32>,
+    #[serde(default)]
+    hide_cursor: Option<bool>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 struct CaptureRegion {
     x: i32,
     y: i32,
@@ -140,13 +324,23 @@ struct CaptureRegion {
     height: i32,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct StartRecordingResponse {
     session_id: String,
     output_path: String,
     log_path: String,
     preview_url: Option<String>,
+    preview_rtp_port: Option<u16>,
+    preview_audio_rtp_port: Option<u16>,
     camera_path: Option<String>,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct CameraBlackFramesEvent {
+    session_id: String,
+    camera: String,
+    average_luma: f32,
 }
 
 #[derive(Serialize)]
@@ -155,6 +349,20 @@ struct StopRecordingResponse {
     duration_ms: u64,
 }
 
+#[derive(Serialize)]
+struct ImportMediaResponse {
+    session_id: String,
+    output_path: String,
+    probe: MediaProbe,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct RecordingPreset {
+    name: String,
+    #[serde(flatten)]
+    request: StartRecordingRequest,
+}
+
 struct RecordingState {
     inner: Mutex<Option<RecordingSession>>,
 }
@@ -172,6 +380,80 @@ struct RecordingSession {
     started_at: Instant,
     child: Child,
     cursor_stop: Arc<AtomicBool>,
+    clock: Arc<RecordingClock>,
+    plan: RecordingPlan,
+    segment_count: usize,
+    // Bitmask of SUSPEND_* reasons; capture only restarts once all are cleared.
+    suspend_reasons: u8,
+    speed_factor: f32,
+    // Set by continue_recording: length of the session before this take.
+    append_after_ms: Option<u64>,
+}
+
+const SUSPEND_POWER: u8 = 1;
+const SUSPEND_LOCK: u8 = 2;
+const SUSPEND_USER: u8 = 4;
+
+// Everything needed to respawn the capture ffmpeg for a new segment of the
+// same session (after a system resume, for example).
+#[derive(Clone)]
+struct RecordingPlan {
+    bin: String,
+    output_dir: PathBuf,
+    input_args: Vec<String>,
+    camera_output_args: Option<Vec<String>>,
+    preview_output_args: Option<Vec<String>>,
+}
+
+// Tracks time spent suspended so cursor offsets and durations line up with
+// the concatenated video rather than wall-clock time.
+struct RecordingClock {
+    paused: AtomicBool,
+    paused_ms: AtomicU64,
+    paused_at: Mutex<Option<Instant>>,
+}
+
+impl RecordingClock {
+    fn new() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            paused_ms: AtomicU64::new(0),
+            paused_at: Mutex::new(None),
+        }
+    }
+
+    fn pause(&self) {
+        if let Ok(mut guard) = self.paused_at.lock() {
+            if guard.is_none() {
+                *guard = Some(Instant::now());
+            }
+        }
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    fn resume(&self) {
+        if let Ok(mut guard) = self.paused_at.lock() {
+            if let Some(at) = guard.take() {
+                self.paused_ms
+                    .fetch_add(at.elapsed().as_millis() as u64, Ordering::Relaxed);
+            }
+        }
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    fn active_ms(&self, started: Instant) -> u64 {
+        let mut paused = self.paused_ms.load(Ordering::Relaxed);
+        if let Ok(guard) = self.paused_at.lock() {
+            if let Some(at) = guard.as_ref() {
+                paused += at.elapsed().as_millis() as u64;
+            }
+        }
+        (started.elapsed().as_millis() as u64).saturating_sub(paused)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -187,6 +469,14 @@ struct EditState {
     camera_blur: bool,
     background_type: String,
     background_preset: u32,
+    // Used by the "image" and "video" background types.
+    #[serde(default)]
+    background_path: Option<String>,
+    #[serde(default)]
+    background_blur: u32,
+    // Used by the "custom" background type.
+    #[serde(default)]
+    background_colors: Option<BackgroundColors>,
     camera_position: String,
     #[serde(default)]
     shrink_16_9: f32,
@@ -224,6 +514,64 @@ struct EditState {
     safe_w: f32,
     #[serde(default)]
     safe_h: f32,
+    #[serde(default)]
+    theme: Option<String>,
+    #[serde(default)]
+    click_effect: bool,
+    #[serde(default)]
+    click_effect_color: String,
+    #[serde(default)]
+    click_effect_size: u32,
+    #[serde(default)]
+    click_effect_duration_ms: u32,
+    #[serde(default)]
+    synthetic_cursor: bool,
+    #[serde(default)]
+    cursor_image: Option<String>,
+    #[serde(default)]
+    cursor_size: u32,
+    #[serde(default)]
+    cursor_smoothing: f32,
+    #[serde(default)]
+    keystrokes: bool,
+    #[serde(default)]
+    keystroke_position: String,
+    #[serde(default)]
+    keystroke_scale: f32,
+    // Overrides the theme watermark for this export when set.
+    #[serde(default)]
+    watermark: Option<ThemeWatermark>,
+    // Green-screen key color for the camera ("#rrggbb"); None disables it.
+    #[serde(default)]
+    camera_chroma_key: Option<String>,
+    #[serde(default)]
+    camera_chroma_similarity: f32,
+    #[serde(default)]
+    camera_chroma_blend: f32,
+    // eq values (0 / 1 / 1 are neutral) and white balance in kelvin.
+    #[serde(default)]
+    camera_brightness: f32,
+    #[serde(default = "one")]
+    camera_contrast: f32,
+    #[serde(default = "one")]
+    camera_saturation: f32,
+    #[serde(default)]
+    camera_temperature: u32,
+    // Applied to the screen capture; 0 / 1 / 1 / 1 are neutral.
+    #[serde(default)]
+    screen_brightness: f32,
+    #[serde(default = "one")]
+    screen_contrast: f32,
+    #[serde(default = "one")]
+    screen_saturation: f32,
+    #[serde(default = "one")]
+    screen_gamma: f32,
+    // .cube LUT applied after the eq adjustments.
+    #[serde(default)]
+    screen_lut: Option<String>,
+    // Mockup drawn around the recording: "browser", "macos" or "phone".
+    #[serde(default)]
+    frame: Option<String>,
 }
 
 impl Default for EditState {
@@ -240,6 +588,9 @@ impl Default for EditState {
             camera_blur: false,
             background_type: "gradient".to_string(),
             background_preset: 0,
+            background_path: None,
+            background_blur: 0,
+            background_colors: None,
             camera_position: "bottom_left".to_string(),
             shrink_16_9: 0.94,
             shrink_1_1: 0.94,
@@ -259,49 +610,354 @@ impl Default for EditState {
             safe_y: 0.0,
             safe_w: 1.0,
             safe_h: 1.0,
+            theme: None,
+            click_effect: false,
+            click_effect_color: "#ffffff".to_string(),
+            click_effect_size: 48,
+            click_effect_duration_ms: 450,
+            synthetic_cursor: false,
+            cursor_image: None,
+            cursor_size: 32,
+            cursor_smoothing: 0.5,
+            keystrokes: false,
+            keystroke_position: "bottom".to_string(),
+            keystroke_scale: 1.0,
+            watermark: None,
+            camera_chroma_key: None,
+            camera_chroma_similarity: 0.3,
+            camera_chroma_blend: 0.1,
+            camera_brightness: 0.0,
+            camera_contrast: 1.0,
+            camera_saturation: 1.0,
+            camera_temperature: 6500,
+            screen_brightness: 0.0,
+            screen_contrast: 1.0,
+            screen_saturation: 1.0,
+            screen_gamma: 1.0,
+            screen_lut: None,
+            frame: None,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+// Neutral eq value for sessions saved before the color fields existed.
+fn one() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct ExportProfile {
     format: String,
     width: u32,
     height: u32,
     fps: u32,
     bitrate_kbps: u32,
+    #[serde(default)]
+    encoder_preset: Option<String>,
+    // "software" (default) always uses the preset's encoder; "auto" uses a
+    // detected hardware encoder for H.264/H.265 and "nvenc", "qsv" or "amf"
+    // pick a vendor. Hardware encoders ignore the preset's tuning options.
+    #[serde(default)]
+    encoder: Option<String>,
+    // Defaults to bitrate_kbps when unset.
+    #[serde(default)]
+    rate_control: Option<RateControl>,
+    // Integrated loudness for this destination; ExportRequest.loudness_lufs
+    // overrides it.
+    #[serde(default)]
+    loudness_lufs: Option<f32>,
+    // True-peak ceiling in dBTP, -1.5 when unset.
+    #[serde(default)]
+    true_peak_db: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+enum RateControl {
+    Bitrate,
+    // Constant quality; lower is better, 18-28 is the useful range for x264.
+    Crf { crf: u32 },
+    // Converted to an average bitrate from the output duration before export.
+    TargetSize { size_mb: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct EncoderPreset {
+    name: String,
+    codec: String,
+    #[serde(default)]
+    preset: Option<String>,
+    #[serde(default)]
+    tune: Option<String>,
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    level: Option<String>,
+    #[serde(default)]
+    pix_fmt: Option<String>,
+    #[serde(default)]
+    extra_args: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct EncoderPresetInfo {
+    preset: EncoderPreset,
+    builtin: bool,
+    overridden: bool,
+    args: Vec<String>,
 }
 
-#[derive(Deserialize, Clone)]
+static ENCODER_PRESETS: OnceLock<Vec<EncoderPreset>> = OnceLock::new();
+// Hardware encoders that passed a test encode at startup, in preference order.
+static HARDWARE_ENCODERS: OnceLock<Vec<String>> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone)]
 struct ExportRequest {
     input_path: String,
     output_path: String,
     edit_state: EditState,
+    // Filled from the named export preset when one is given.
+    #[serde(default)]
     profile: ExportProfile,
+    // Name from list_export_presets, e.g. "youtube_1080p".
+    #[serde(default)]
+    preset: Option<String>,
     camera_path: Option<String>,
+    #[serde(default)]
+    music: Option<MusicOptions>,
+    // Burns the session's SRT into the video when set.
+    #[serde(default)]
+    subtitles: Option<SubtitleStyle>,
+    // "embed" muxes the session subtitles as a soft track, "sidecar" writes
+    // an .srt next to the output.
+    #[serde(default)]
+    soft_subtitles: Option<String>,
+    // "fft" (afftdn) or "rnn" (arnndn); rnn needs a model, either
+    // denoise_model or the bundled ffmpeg/models/std.rnnn.
+    #[serde(default)]
+    denoise: Option<String>,
+    #[serde(default)]
+    denoise_model: Option<String>,
+    // Integrated loudness target, e.g. -14 for YouTube or -16 for podcasts.
+    #[serde(default)]
+    loudness_lufs: Option<f32>,
+    // Exports only this part of the edited timeline (after the clip track).
+    #[serde(default)]
+    start_s: Option<f64>,
+    #[serde(default)]
+    end_s: Option<f64>,
+    #[serde(default)]
+    poster: Option<PosterOptions>,
+    // Raw ffmpeg flags placed before the recording's -i and before the output
+    // path; checked by validate_extra_args.
+    #[serde(default)]
+    extra_input_args: Vec<String>,
+    #[serde(default)]
+    extra_output_args: Vec<String>,
+    #[serde(default)]
+    metadata: Option<ExportMetadata>,
+    // Further encodes of the same render, e.g. an H.264 and a ProRes copy.
+    // They must share the profile's size and fps.
+    #[serde(default)]
+    extra_outputs: Vec<ExportOutput>,
+    // Writes numbered frames instead of a video; output_path then names the
+    // frames directory.
+    #[serde(default)]
+    image_sequence: Option<ImageSequenceOptions>,
+    // One output per clip segment instead of a single joined file.
+    #[serde(default)]
+    split_segments: bool,
+    #[serde(default)]
+    timecode: Option<TimecodeOptions>,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
+struct ExportOutput {
+    profile: ExportProfile,
+    output_path: String,
+}
+
+// Container tags for media libraries. creation_time is ISO 8601
+// ("2024-05-01T10:00:00Z") or "now".
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct ExportMetadata {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    comment: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    creation_time: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TimecodeOptions {
+    // "timecode" (default): HH:MM:SS:FF of the edited timeline; "wall_clock":
+    // the local date and time the frame was captured.
+    #[serde(default)]
+    mode: Option<String>,
+    // "top_left" (default), "top_right", "bottom_left" or "bottom_right".
+    #[serde(default)]
+    position: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ImageSequenceOptions {
+    // "png" (default) or "webp".
+    #[serde(default)]
+    format: Option<String>,
+    // Frames per second of output; the profile fps when unset.
+    #[serde(default)]
+    fps: Option<f64>,
+    // One frame per timeline marker instead of a fixed rate.
+    #[serde(default)]
+    at_markers: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct PosterOptions {
+    // "jpg" (default) or "png".
+    #[serde(default)]
+    format: Option<String>,
+    // Output time of the frame; the first non-black frame when unset.
+    #[serde(default)]
+    time_s: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SubtitleStyle {
+    #[serde(default)]
+    font: Option<String>,
+    #[serde(default)]
+    size: Option<u32>,
+    #[serde(default)]
+    color: Option<String>,
+    // Box behind the text; None draws an outline instead.
+    #[serde(default)]
+    background: Option<String>,
+    #[serde(default)]
+    background_opacity: Option<f32>,
+}
+
+// Background music mixed under the recording. It always stops with the
+// video; loop_music decides whether a short track repeats until then.
+#[derive(Serialize, Deserialize, Clone)]
+struct MusicOptions {
+    path: String,
+    #[serde(default)]
+    volume: Option<f32>,
+    #[serde(default)]
+    loop_music: Option<bool>,
+    #[serde(default)]
+    duck: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 struct ExportStatus {
     job_id: String,
     state: String,
     progress: f32,
     error: Option<String>,
     output_path: Option<String>,
+    // Shared by the jobs of one multi-profile start_export call.
+    #[serde(default)]
+    group_id: Option<String>,
+    // Encoding rate from ffmpeg's -progress output while running.
+    #[serde(default)]
+    fps: Option<f32>,
+    #[serde(default)]
+    speed: Option<f32>,
+    #[serde(default)]
+    eta_s: Option<f32>,
+    // Poster image written next to the output, when requested.
+    #[serde(default)]
+    poster_path: Option<String>,
+}
+
+// One block of ffmpeg's -progress output.
+#[derive(Default, Clone, Copy)]
+struct FfmpegProgress {
+    out_time_us: u64,
+    fps: Option<f32>,
+    speed: Option<f32>,
+}
+
+impl FfmpegProgress {
+    // Folds in one key=value line; true at the progress= line that closes a
+    // block.
+    fn update(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+        let value = value.trim();
+        match key {
+            // Despite the name, out_time_ms is in microseconds too.
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<u64>() {
+                    self.out_time_us = us;
+                }
+            }
+            "fps" => self.fps = value.parse::<f32>().ok().filter(|fps| *fps > 0.0),
+            "speed" => {
+                self.speed = value
+                    .trim_end_matches('x')
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|speed| speed.is_finite() && *speed > 0.0)
+            }
+            "progress" => return true,
+            _ => {}
+        }
+        false
+    }
+
+    fn out_ms(&self) -> u64 {
+        self.out_time_us / 1000
+    }
+
+    fn fraction(&self, duration_ms: u64) -> f32 {
+        if duration_ms == 0 {
+            0.0
+        } else {
+            (self.out_ms() as f64 / duration_ms as f64).min(1.0) as f32
+        }
+    }
+
+    // Seconds left at the current speed.
+    fn eta_s(&self, duration_ms: u64) -> Option<f32> {
+        let left_s = duration_ms.saturating_sub(self.out_ms()) as f32 / 1000.0;
+        self.speed.map(|speed| left_s / speed)
+    }
 }
 
 #[derive(Serialize)]
 struct ExportStartResponse {
     job_id: String,
+    group_id: Option<String>,
+    job_ids: Vec<String>,
+    // Every file the jobs will write, including fanned-out encodes.
+    output_paths: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
 struct ExportJob {
     job_id: String,
+    group_id: Option<String>,
     request: ExportRequest,
+    // Higher runs first; equal priorities keep their queue order.
+    #[serde(default)]
+    priority: i32,
 }
 
 struct ExportManager {
     queue: VecDeque<ExportJob>,
+    // The job the worker is on; persisted with the queue so it re-runs.
+    current: Option<ExportJob>,
+    // Live ffmpeg processes per job, killed directly by cancel_export.
+    children: HashMap<String, Vec<Arc<Mutex<Child>>>>,
+    // When each running job left the queue, for the history log.
+    started_at_ms: HashMap<String, u64>,
     running: bool,
     statuses: HashMap<String, ExportStatus>,
     cancellations: HashMap<String, bool>,
@@ -316,6 +972,9 @@ impl ExportState {
         Self {
             inner: Arc::new(Mutex::new(ExportManager {
                 queue: VecDeque::new(),
+                current: None,
+                children: HashMap::new(),
+                started_at_ms: HashMap::new(),
                 running: false,
                 statuses: HashMap::new(),
                 cancellations: HashMap::new(),
@@ -324,38 +983,244 @@ impl ExportState {
     }
 }
 
-const PREVIEW_RTP_PORT: u16 = 19000;
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct IceServerConfig {
+    urls: Vec<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    credential: Option<String>,
+}
 
-struct PreviewState {
-    inner: Mutex<Option<PreviewSession>>,
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct PreviewSettings {
+    #[serde(default)]
+    ice_servers: Vec<IceServerConfig>,
+    // Skip STUN/TURN and mDNS entirely and offer plain host (incl. loopback)
+    // candidates, for machines where outbound UDP or multicast is blocked.
+    #[serde(default)]
+    host_candidates_only: bool,
+    #[serde(default)]
+    relay_only: bool,
+    #[serde(default)]
+    quality: PreviewQuality,
 }
 
-impl PreviewState {
-    fn new() -> Self {
+#[derive(Serialize, Deserialize, Clone)]
+struct PreviewQuality {
+    bitrate_kbps: u32,
+    fps: u32,
+    size: u32,
+}
+
+impl Default for PreviewQuality {
+    fn default() -> Self {
         Self {
-            inner: Mutex::new(None),
+            bitrate_kbps: 600,
+            fps: 20,
+            size: 240,
         }
     }
 }
 
-struct PreviewSession {
-    peer: Arc<RTCPeerConnection>,
-    udp_task: async_runtime::JoinHandle<()>,
+// Where the preview ffmpeg reads its frames from: the recorder's lightly
+// compressed camera feed on a local port, or the camera itself when no
+// recording is running yet.
+#[derive(Clone)]
+enum PreviewSource {
+    Recorder(u16),
+    Camera(String),
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct Rect {
-    x: i32,
-    y: i32,
-    width: i32,
+// A separate ffmpeg re-encodes the preview for WebRTC so it can be restarted
+// with other quality settings without touching the recording.
+struct PreviewRelay {
+    bin: String,
+    source: PreviewSource,
+    child: Child,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct AppSettings {
+    #[serde(default)]
+    preview: PreviewSettings,
+    #[serde(default)]
+    retention: RetentionPolicy,
+    // The request of the last recording that started, offered as the default.
+    #[serde(default)]
+    last_recording: Option<StartRecordingRequest>,
+    #[serde(default)]
+    directories: DirectorySettings,
+    #[serde(default)]
+    defaults: DefaultSettings,
+    #[serde(default)]
+    hotkeys: HotkeySettings,
+    #[serde(default)]
+    features: FeatureSettings,
+    // An ffmpeg binary to use instead of the bundled one.
+    #[serde(default)]
+    ffmpeg_path: Option<String>,
+    #[serde(default)]
+    crash_reports: CrashReportSettings,
+    #[serde(default)]
+    updates: UpdateSettings,
+}
+
+// The endpoint serves the updater's latest.json; unset uses the release feed.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct UpdateSettings {
+    endpoint: Option<String>,
+    background_check: bool,
+    check_interval_hours: u32,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            background_check: true,
+            check_interval_hours: 24,
+        }
+    }
+}
+
+// Crash reports are always written locally; they only leave the machine when
+// upload is switched on and an endpoint is set.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct CrashReportSettings {
+    #[serde(default)]
+    upload: bool,
+    #[serde(default)]
+    endpoint: Option<String>,
+}
+
+// Unset folders use the built-in locations under the app data root.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct DirectorySettings {
+    #[serde(default)]
+    work_dir: Option<String>,
+    #[serde(default)]
+    export_dir: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct DefaultSettings {
+    // Export preset applied when a request has neither preset nor profile.
+    export_preset: Option<String>,
+    export_history_max: usize,
+    // Recordings with a longer edge get a proxy after they stop.
+    auto_proxy_long_edge: u32,
+}
+
+impl Default for DefaultSettings {
+    fn default() -> Self {
+        Self {
+            export_preset: None,
+            export_history_max: EXPORT_HISTORY_MAX,
+            auto_proxy_long_edge: PROXY_AUTO_LONG_EDGE,
+        }
+    }
+}
+
+// Accelerator strings as the frontend registers them.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct HotkeySettings {
+    toggle_recording: String,
+    pause_recording: String,
+    add_marker: String,
+}
+
+impl Default for HotkeySettings {
+    fn default() -> Self {
+        Self {
+            toggle_recording: "CmdOrCtrl+Shift+R".to_string(),
+            pause_recording: "CmdOrCtrl+Shift+P".to_string(),
+            add_marker: "CmdOrCtrl+Shift+M".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct FeatureSettings {
+    auto_proxy: bool,
+    verify_exports: bool,
+    keep_export_logs: bool,
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        Self {
+            auto_proxy: true,
+            verify_exports: true,
+            keep_export_logs: true,
+        }
+    }
+}
+
+// Applied to the work folder at startup. Unset limits are not enforced, and
+// favorite sessions are never removed.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct RetentionPolicy {
+    #[serde(default)]
+    max_age_days: Option<u32>,
+    #[serde(default)]
+    max_total_gb: Option<f64>,
+    // Trashed sessions are purged after this many days, 30 when unset.
+    #[serde(default)]
+    trash_purge_days: Option<u32>,
+}
+
+struct PreviewState {
+    inner: Mutex<Option<PreviewSession>>,
+}
+
+impl PreviewState {
+    fn new() -> Self {
+        Self {
+            inner: Mutex::new(None),
+        }
+    }
+}
+
+struct PreviewSession {
+    peer: Arc<RTCPeerConnection>,
+    udp_task: async_runtime::JoinHandle<()>,
+    rtp_port: u16,
+    audio_task: Option<async_runtime::JoinHandle<()>>,
+    audio_rtp_port: Option<u16>,
+    audio_muted: Arc<AtomicBool>,
+    relay: Option<PreviewRelay>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: i32,
     height: i32,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct CaptureMeta {
     mode: String,
     rect: Rect,
     started_at_ms: u64,
+    #[serde(default)]
+    speed_factor: Option<f32>,
+    #[serde(default)]
+    cursor_hidden: bool,
+    // Display name set by rename_session; the folder keeps its id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    // Exempt from the retention policy.
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -366,6 +1231,12 @@ struct CursorEventRecord {
     ayn: f32,
 }
 
+#[derive(Serialize, Deserialize)]
+struct KeyEventRecord {
+    offset_ms: u64,
+    keys: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 struct ClipSegment {
     start_s: f64,
@@ -402,6 +1273,236 @@ struct CameraTrack {
     segments: Vec<CameraSegment>,
 }
 
+// Normalized (0..1) point the zoom centers on at time_s.
+#[derive(Serialize, Deserialize, Clone)]
+struct ZoomAnchor {
+    time_s: f64,
+    x: f32,
+    y: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ZoomWindow {
+    id: String,
+    start_s: f64,
+    end_s: f64,
+    scale: f32,
+    #[serde(default)]
+    manual: bool,
+    #[serde(default)]
+    anchors: Vec<ZoomAnchor>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ZoomFrame {
+    time_s: f64,
+    scale: f32,
+    x: f32,
+    y: f32,
+}
+
+// Normalized (0..1) crop of the source. Keyframes only move the rect: the
+// crop filter's output size is fixed, so the size always comes from rect.
+#[derive(Serialize, Deserialize, Clone)]
+struct CropRect {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CropKeyframe {
+    time_s: f64,
+    x: f32,
+    y: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CropTrack {
+    rect: CropRect,
+    #[serde(default)]
+    keyframes: Vec<CropKeyframe>,
+}
+
+// time_s is on the recording timeline, like the other tracks.
+#[derive(Serialize, Deserialize, Clone)]
+struct Chapter {
+    name: String,
+    time_s: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct ChapterTrack {
+    #[serde(default)]
+    chapters: Vec<Chapter>,
+}
+
+// Skin-region position in camera.mp4, normalized to the frame; size is the
+// region height over the frame height.
+#[derive(Serialize, Deserialize, Clone)]
+struct CameraFramingSample {
+    time_s: f64,
+    cx: f32,
+    cy: f32,
+    size: f32,
+}
+
+// samples are kept for the editor; export crops to the steady frame, the
+// median over all detections.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct CameraFramingTrack {
+    #[serde(default)]
+    samples: Vec<CameraFramingSample>,
+    #[serde(default)]
+    frame: Option<CameraFramingSample>,
+}
+
+// Editor bookmarks such as "retake here"; unlike click markers they are placed
+// by the user and never derived from cursor.jsonl.
+#[derive(Serialize, Deserialize, Clone)]
+struct TimelineMarker {
+    #[serde(default)]
+    id: String,
+    time_s: f64,
+    label: String,
+    #[serde(default)]
+    color: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct MarkerTrack {
+    #[serde(default)]
+    markers: Vec<TimelineMarker>,
+}
+
+// Linear gain (1.0 = unchanged) at time_s on the recording timeline.
+#[derive(Serialize, Deserialize, Clone)]
+struct VolumeKeyframe {
+    time_s: f64,
+    gain: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct AudioTrack {
+    #[serde(default)]
+    keyframes: Vec<VolumeKeyframe>,
+    #[serde(default)]
+    fade_in_s: f64,
+    #[serde(default)]
+    fade_out_s: f64,
+}
+
+// windows are the source of truth; frames are a sampled copy for the editor
+// and are rebuilt whenever the track is written.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct ZoomTrack {
+    #[serde(default)]
+    settings: ZoomSettings,
+    windows: Vec<ZoomWindow>,
+    #[serde(default)]
+    frames: Vec<ZoomFrame>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct ZoomSettings {
+    max_zoom: f32,
+    ramp_in_s: f64,
+    ramp_out_s: f64,
+    follow_threshold_px: f32,
+    sample_ms: u64,
+    // 0 pans straight between anchors, 1 is the heaviest smoothing.
+    pan_smoothing: f32,
+    // "fast" (default) zooms at source size; "high" and "max" supersample
+    // 2x / 3x, up to twice the output size, so the pan and zoom move in
+    // sub-pixel steps.
+    quality: String,
+}
+
+impl Default for ZoomSettings {
+    fn default() -> Self {
+        Self {
+            max_zoom: 2.0,
+            ramp_in_s: 0.5,
+            ramp_out_s: 0.5,
+            follow_threshold_px: 160.0,
+            sample_ms: 100,
+            pan_smoothing: 0.5,
+            quality: "fast".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ThemeGradient {
+    start: String,
+    mid: String,
+    end: String,
+    #[serde(default = "default_gradient_mid_pos")]
+    mid_pos: f32,
+}
+
+fn default_gradient_mid_pos() -> f32 {
+    0.5
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ThemeWallpaper {
+    #[serde(default)]
+    start: Option<String>,
+    #[serde(default)]
+    end: Option<String>,
+    #[serde(default)]
+    image: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ThemeCameraRing {
+    color: String,
+    width: u32,
+}
+
+// A user gradient; mid defaults to halfway between start and end, and
+// angle_deg 45 runs from the top-left to the bottom-right like the presets.
+#[derive(Serialize, Deserialize, Clone)]
+struct BackgroundColors {
+    start: String,
+    #[serde(default)]
+    mid: Option<String>,
+    end: String,
+    #[serde(default)]
+    mid_pos: Option<f32>,
+    #[serde(default)]
+    angle_deg: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ThemeWatermark {
+    image: String,
+    #[serde(default)]
+    corner: Option<String>,
+    #[serde(default)]
+    opacity: Option<f32>,
+    #[serde(default)]
+    margin: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ThemePack {
+    #[serde(default)]
+    id: String,
+    name: String,
+    #[serde(default)]
+    gradients: Vec<ThemeGradient>,
+    #[serde(default)]
+    wallpapers: Vec<ThemeWallpaper>,
+    #[serde(default)]
+    camera_ring: Option<ThemeCameraRing>,
+    #[serde(default)]
+    watermark: Option<ThemeWatermark>,
+}
+
 fn write_error_log(output_dir: &PathBuf, message: &str) {
     if let Ok(mut file) = fs::OpenOptions::new()
         .create(true)
@@ -421,6 +1522,22 @@ fn edit_state_path(output_path: &str) -> PathBuf {
     }
 }
 
+const EDIT_ASPECTS: &[(&str, &str)] = &[
+    ("16:9", "edit_state_16x9.json"),
+    ("1:1", "edit_state_1x1.json"),
+    ("9:16", "edit_state_9x16.json"),
+];
+
+// Full layout for one target aspect, next to the shared edit_state.json.
+fn aspect_edit_state_path(output_path: &str, aspect: &str) -> Result<PathBuf, String> {
+    let name = EDIT_ASPECTS
+        .iter()
+        .find(|(value, _)| *value == aspect)
+        .map(|(_, name)| *name)
+        .ok_or("unsupported_aspect")?;
+    Ok(edit_state_path(output_path).with_file_name(name))
+}
+
 fn preview_path(output_path: &str) -> PathBuf {
     let path = PathBuf::from(output_path);
     let session = path
@@ -445,8 +1562,26 @@ fn app_data_root() -> PathBuf {
     app_install_dir()
 }
 
+// Per-user settings folder; the install folder may be read-only or shared.
+fn user_config_dir() -> PathBuf {
+    if let Ok(dir) = env::var("APPDATA") {
+        return PathBuf::from(dir).join("Flash Recorder");
+    }
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        return PathBuf::from(dir).join("flash-recorder");
+    }
+    if let Ok(home) = env::var("HOME") {
+        return PathBuf::from(home).join(".config").join("flash-recorder");
+    }
+    app_data_root()
+}
+
+fn configured_dir(dir: Option<String>) -> Option<PathBuf> {
+    dir.filter(|dir| !dir.trim().is_empty()).map(PathBuf::from)
+}
+
 fn work_base_dir() -> PathBuf {
-    app_data_root().join("work")
+    configured_dir(load_settings().directories.work_dir).unwrap_or_else(|| app_data_root().join("work"))
 }
 
 fn user_videos_dir() -> PathBuf {
@@ -457,7 +1592,8 @@ fn user_videos_dir() -> PathBuf {
 }
 
 fn export_dir_with_fallback() -> PathBuf {
-    let preferred = app_data_root().join("recordings");
+    let preferred = configured_dir(load_settings().directories.export_dir)
+        .unwrap_or_else(|| app_data_root().join("recordings"));
     if fs::create_dir_all(&preferred).is_ok() {
         return preferred;
     }
@@ -466,9 +1602,34 @@ fn export_dir_with_fallback() -> PathBuf {
     fallback
 }
 
+// Image sequences go to <stem>_frames beside where the video would be.
 fn normalize_export_output_path(req: &ExportRequest) -> String {
+    let path = normalize_video_output_path(req);
+    if req.image_sequence.is_none() {
+        return path;
+    }
+    let path = PathBuf::from(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("export")
+        .to_string();
+    path.with_file_name(format!("{stem}_frames"))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn normalize_video_output_path(req: &ExportRequest) -> String {
     let raw = PathBuf::from(&req.output_path);
+    let mezzanine = is_mezzanine_profile(&req.profile);
     if raw.is_absolute() && raw.parent().is_some() {
+        let is_mov = raw
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("mov"));
+        if mezzanine && !is_mov {
+            return raw.with_extension("mov").to_string_lossy().to_string();
+        }
         return raw.to_string_lossy().to_string();
     }
     let input = PathBuf::from(&req.input_path);
@@ -477,7 +1638,7 @@ fn normalize_export_output_path(req: &ExportRequest) -> String {
         .and_then(|p| p.file_name())
         .and_then(|n| n.to_str())
         .unwrap_or("export");
-    let name = format!("{session}.mp4");
+    let name = format!("{session}.{}", if mezzanine { "mov" } else { "mp4" });
     export_dir_with_fallback()
         .join(name)
         .to_string_lossy()
@@ -505,27 +1666,91 @@ fn copy_dir(src: &PathBuf, dst: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
-fn maybe_migrate_old_recordings() {
-    let candidates = [PathBuf::from(r"D:\recordings"), PathBuf::from(r"D:\Recordings")];
-    let target = work_base_dir();
-    let _ = fs::create_dir_all(&target);
-    for base in candidates {
-        if !base.exists() {
-            continue;
-        }
-        if let Ok(entries) = fs::read_dir(&base) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                    let dst = target.join(entry.file_name());
-                    if fs::rename(&path, &dst).is_err() {
-                        let _ = copy_dir(&path, &dst);
-                        let _ = fs::remove_dir_all(&path);
-                    }
+#[derive(Serialize)]
+struct LegacyImportItem {
+    session_id: String,
+    bytes: u64,
+    // "import", "exists" (an id already in the work folder) or
+    // "not_a_session" (no recording.mp4).
+    action: String,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LegacyImportReport {
+    source: String,
+    dry_run: bool,
+    items: Vec<LegacyImportItem>,
+}
+
+#[derive(Serialize, Clone)]
+struct LegacyImportProgress {
+    index: usize,
+    total: usize,
+    session_id: String,
+}
+
+// Moves session folders from an old recordings folder (e.g. D:\recordings)
+// into the work folder. With dry_run nothing is touched and the report shows
+// what would happen. Emits legacy_import_progress before each move.
+#[tauri::command]
+async fn import_legacy_recordings(
+    app: tauri::AppHandle,
+    path: String,
+    dry_run: Option<bool>,
+) -> Result<LegacyImportReport, AppError> {
+    let source = PathBuf::from(&path);
+    if !source.is_dir() {
+        return Err("legacy_dir_not_found".into());
+    }
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let target = work_base_dir();
+        fs::create_dir_all(&target).map_err(|_| "session_create_failed".to_string())?;
+        let mut items: Vec<LegacyImportItem> = fs::read_dir(&source)
+            .map_err(|_| "legacy_dir_not_found".to_string())?
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .map(|entry| {
+                let session_id = entry.file_name().to_string_lossy().to_string();
+                let action = if !entry.path().join("recording.mp4").is_file() {
+                    "not_a_session"
+                } else if target.join(&session_id).exists() {
+                    "exists"
+                } else {
+                    "import"
+                };
+                LegacyImportItem {
+                    bytes: dir_size(&entry.path()),
+                    session_id,
+                    action: action.to_string(),
+                    error: None,
                 }
+            })
+            .collect();
+        items.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        if !dry_run {
+            let total = items.iter().filter(|item| item.action == "import").count();
+            let mut index = 0;
+            for item in items.iter_mut().filter(|item| item.action == "import") {
+                let _ = app.emit(
+                    "legacy_import_progress",
+                    LegacyImportProgress { index, total, session_id: item.session_id.clone() },
+                );
+                index += 1;
+                let from = source.join(&item.session_id);
+                item.error = move_dir(&from, &target.join(&item.session_id)).err();
             }
         }
-    }
+        Ok::<_, String>(LegacyImportReport {
+            source: source.to_string_lossy().to_string(),
+            dry_run,
+            items,
+        })
+    })
+    .await
+    .map_err(|_| "legacy_import_join_failed".to_string())?
+    .map_err(AppError::from)
 }
 
 fn parse_duration_ms(text: &str) -> Option<u64> {
@@ -559,7 +1784,11 @@ fn bitrate_for_resolution(value: u32) -> u32 {
 }
 
 fn get_media_duration_ms(app: &tauri::AppHandle, input_path: &str) -> Option<u64> {
-    let output = new_cmd(&ffmpeg_binary_with_app_handle(app))
+    media_duration_ms_with_bin(&ffmpeg_binary_with_app_handle(app), input_path)
+}
+
+fn media_duration_ms_with_bin(bin: &str, input_path: &str) -> Option<u64> {
+    let output = new_cmd(bin)
         .args(["-i", input_path, "-hide_banner"])
         .output()
         .ok()?;
@@ -567,20 +1796,94 @@ fn get_media_duration_ms(app: &tauri::AppHandle, input_path: &str) -> Option<u64
     parse_duration_ms(&stderr)
 }
 
-fn aspect_ratio(aspect: &str) -> f32 {
-    match aspect {
-        "1:1" => 1.0,
-        "9:16" => 9.0 / 16.0,
-        _ => 16.0 / 9.0,
-    }
+#[derive(Serialize, Clone, Default)]
+struct MediaProbe {
+    duration_ms: Option<u64>,
+    has_video: bool,
+    has_audio: bool,
+    width: Option<u32>,
+    height: Option<u32>,
+    fps: Option<f32>,
+    video_codec: Option<String>,
+    audio_codec: Option<String>,
 }
 
-fn evenize(value: i32) -> i32 {
-    if value % 2 == 0 {
-        value
-    } else {
-        value - 1
-    }
+fn parse_media_probe(text: &str) -> MediaProbe {
+    let mut probe = MediaProbe {
+        duration_ms: parse_duration_ms(text),
+        ..MediaProbe::default()
+    };
+    let codec_of = |desc: &str| {
+        desc.split([' ', ','])
+            .next()
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_string())
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if !line.starts_with("Stream #") {
+            continue;
+        }
+        if let Some(index) = line.find("Video: ") {
+            if probe.has_video {
+                continue;
+            }
+            probe.has_video = true;
+            let desc = &line[index + "Video: ".len()..];
+            probe.video_codec = codec_of(desc);
+            for part in desc.split(", ") {
+                let part = part.trim();
+                if probe.width.is_none() {
+                    let dims = part.split(' ').next().unwrap_or("");
+                    if let Some((w, h)) = dims.split_once('x') {
+                        if let (Ok(w), Ok(h)) = (w.parse::<u32>(), h.parse::<u32>()) {
+                            probe.width = Some(w);
+                            probe.height = Some(h);
+                        }
+                    }
+                }
+                if let Some(value) = part.strip_suffix(" fps") {
+                    probe.fps = value.trim().parse().ok();
+                }
+            }
+        } else if let Some(index) = line.find("Audio: ") {
+            if probe.has_audio {
+                continue;
+            }
+            probe.has_audio = true;
+            probe.audio_codec = codec_of(&line[index + "Audio: ".len()..]);
+        }
+    }
+    probe
+}
+
+fn probe_media(app: &tauri::AppHandle, input_path: &str) -> Option<MediaProbe> {
+    if !PathBuf::from(input_path).exists() {
+        return None;
+    }
+    let output = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args(["-hide_banner", "-i", input_path])
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    Some(parse_media_probe(&stderr))
+}
+
+fn aspect_ratio(aspect: &str) -> f32 {
+    match aspect {
+        "1:1" => 1.0,
+        "9:16" => 9.0 / 16.0,
+        _ => 16.0 / 9.0,
+    }
+}
+
+fn evenize(value: i32) -> i32 {
+    if value % 2 == 0 {
+        value
+    } else {
+        value - 1
+    }
 }
 
 fn parse_hex_color(value: &str) -> (i32, i32, i32) {
@@ -594,50 +1897,704 @@ fn parse_hex_color(value: &str) -> (i32, i32, i32) {
     (r, g, b)
 }
 
-fn background_source(edit_state: &EditState, width: i32, height: i32, fps: u32) -> String {
-    let gradients = [
-        ("#6ee7ff", "#a855f7", "#f97316", 0.5),
-        ("#0f172a", "#1e40af", "#38bdf8", 0.55),
-        ("#111827", "#7c3aed", "#ec4899", 0.6),
-        ("#0b1020", "#0f766e", "#22d3ee", 0.6),
-    ];
-    let wallpapers = [
-        ("#0f172a", "#1f2937"),
-        ("#0b1020", "#1f1b3a"),
-        ("#1f2937", "#0f172a"),
-        ("#0a0f1f", "#0b1020"),
-    ];
+fn builtin_theme() -> ThemePack {
+    let gradient = |start: &str, mid: &str, end: &str, mid_pos: f32| ThemeGradient {
+        start: start.to_string(),
+        mid: mid.to_string(),
+        end: end.to_string(),
+        mid_pos,
+    };
+    let wallpaper = |start: &str, end: &str| ThemeWallpaper {
+        start: Some(start.to_string()),
+        end: Some(end.to_string()),
+        image: None,
+    };
+    ThemePack {
+        id: "default".to_string(),
+        name: "Default".to_string(),
+        gradients: vec![
+            gradient("#6ee7ff", "#a855f7", "#f97316", 0.5),
+            gradient("#0f172a", "#1e40af", "#38bdf8", 0.55),
+            gradient("#111827", "#7c3aed", "#ec4899", 0.6),
+            gradient("#0b1020", "#0f766e", "#22d3ee", 0.6),
+        ],
+        wallpapers: vec![
+            wallpaper("#0f172a", "#1f2937"),
+            wallpaper("#0b1020", "#1f1b3a"),
+            wallpaper("#1f2937", "#0f172a"),
+            wallpaper("#0a0f1f", "#0b1020"),
+        ],
+        camera_ring: None,
+        watermark: None,
+    }
+}
+
+fn themes_dir() -> PathBuf {
+    app_data_root().join("themes")
+}
+
+fn resolve_theme_asset(dir: &PathBuf, value: &str) -> String {
+    let path = PathBuf::from(value);
+    if path.is_absolute() {
+        value.to_string()
+    } else {
+        dir.join(path).to_string_lossy().to_string()
+    }
+}
+
+fn read_theme_pack(dir: &PathBuf) -> Option<ThemePack> {
+    let data = fs::read_to_string(dir.join("theme.json")).ok()?;
+    let mut theme: ThemePack = serde_json::from_str(&data).ok()?;
+    theme.id = dir.file_name()?.to_str()?.to_string();
+    let fallback = builtin_theme();
+    if theme.gradients.is_empty() {
+        theme.gradients = fallback.gradients;
+    }
+    if theme.wallpapers.is_empty() {
+        theme.wallpapers = fallback.wallpapers;
+    }
+    for wallpaper in theme.wallpapers.iter_mut() {
+        if let Some(image) = wallpaper.image.as_mut() {
+            *image = resolve_theme_asset(dir, image);
+        }
+    }
+    if let Some(watermark) = theme.watermark.as_mut() {
+        watermark.image = resolve_theme_asset(dir, &watermark.image);
+    }
+    theme.camera_ring = theme.camera_ring.filter(|ring| is_hex_color(&ring.color));
+    Some(theme)
+}
+
+// Theme ids are folder names under themes_dir, checked like session ids.
+fn valid_theme_id(id: &str) -> bool {
+    !id.trim().is_empty() && !id.contains(['/', '\\']) && !id.contains("..")
+}
+
+// "#rrggbb" only: theme colors end up inside filter graphs.
+fn is_hex_color(value: &str) -> bool {
+    value.len() == 7
+        && value.starts_with('#')
+        && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn load_theme(theme_id: Option<&str>) -> ThemePack {
+    match theme_id {
+        Some(id) if !id.is_empty() && id != "default" && valid_theme_id(id) => {
+            read_theme_pack(&themes_dir().join(id)).unwrap_or_else(builtin_theme)
+        }
+        _ => builtin_theme(),
+    }
+}
+
+// Escapes a file path for use as a filter option value inside single quotes.
+// A quote closes the graph-level quoting, adds an option-level \' and reopens.
+fn filter_escape_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .replace(':', "\\:")
+        .replace('\'', "\\'\\''")
+}
+
+fn background_source(
+    edit_state: &EditState,
+    theme: &ThemePack,
+    width: i32,
+    height: i32,
+    fps: u32,
+) -> String {
+    let fallback = builtin_theme();
+    let gradients = if theme.gradients.is_empty() {
+        &fallback.gradients
+    } else {
+        &theme.gradients
+    };
+    let wallpapers = if theme.wallpapers.is_empty() {
+        &fallback.wallpapers
+    } else {
+        &theme.wallpapers
+    };
     let index = edit_state.background_preset as usize;
     let t = "((X/max(W-1,1))+(Y/max(H-1,1)))/2";
+    let user_media = edit_state
+        .background_path
+        .as_deref()
+        .filter(|path| PathBuf::from(path).is_file());
+    if let Some(path) = user_media.filter(|_| {
+        matches!(edit_state.background_type.as_str(), "image" | "video")
+    }) {
+        // Stills repeat their single frame; videos loop for the whole export,
+        // renumbered at their own rate so looping keeps timestamps rising
+        // without changing playback speed, then resampled to the export fps.
+        let input = if edit_state.background_type == "video" {
+            format!(
+                "movie='{}':loop=0,setpts=N/(FRAME_RATE*TB),fps={fps}",
+                filter_escape_path(path)
+            )
+        } else {
+            format!(
+                "movie='{}',loop=loop=-1:size=1:start=0,setpts=N/({fps}*TB)",
+                filter_escape_path(path)
+            )
+        };
+        let blur = match edit_state.background_blur.min(100) {
+            0 => String::new(),
+            sigma => format!(",gblur=sigma={sigma}"),
+        };
+        return format!(
+            "{input},scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height}{blur},format=rgba"
+        );
+    }
+    if let Some(colors) = edit_state
+        .background_colors
+        .as_ref()
+        .filter(|_| edit_state.background_type == "custom")
+    {
+        let angle = colors.angle_deg.unwrap_or(45.0).to_radians();
+        let (c, s) = (angle.cos(), angle.sin());
+        let t = format!(
+            "clip(((X-W/2)*{c}+(Y-H/2)*{s})/(W*{ac}+H*{as_})+0.5,0,1)",
+            ac = c.abs(),
+            as_ = s.abs()
+        );
+        let (sr, sg, sb) = parse_hex_color(&colors.start);
+        let (er, eg, eb) = parse_hex_color(&colors.end);
+        let (mr, mg, mb) = match colors.mid.as_deref() {
+            Some(mid) => parse_hex_color(mid),
+            None => ((sr + er) / 2, (sg + eg) / 2, (sb + eb) / 2),
+        };
+        let m = colors.mid_pos.unwrap_or(0.5).clamp(0.05, 0.95);
+        return three_stop_gradient_source(
+            &t,
+            [(sr, sg, sb), (mr, mg, mb), (er, eg, eb)],
+            m,
+            (width, height),
+            fps,
+        );
+    }
     if edit_state.background_type == "wallpaper" {
-        let (start, end) = wallpapers[index % wallpapers.len()];
+        let wallpaper = &wallpapers[index % wallpapers.len()];
+        if let Some(image) = wallpaper.image.as_ref() {
+            return format!(
+                "movie='{image}',loop=loop=-1:size=1:start=0,setpts=N/({fps}*TB),scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height},format=rgba",
+                image = filter_escape_path(image)
+            );
+        }
+        let start = wallpaper.start.as_deref().unwrap_or("#0f172a");
+        let end = wallpaper.end.as_deref().unwrap_or(start);
         let (sr, sg, sb) = parse_hex_color(start);
         let (er, eg, eb) = parse_hex_color(end);
         let r = format!("{sr}+({er}-{sr})*{t}");
         let g = format!("{sg}+({eg}-{sg})*{t}");
         let b = format!("{sb}+({eb}-{sb})*{t}");
+        static_geq_source((&r, &g, &b), (width, height), fps)
+    } else {
+        let gradient = &gradients[index % gradients.len()];
+        three_stop_gradient_source(
+            t,
+            [
+                parse_hex_color(&gradient.start),
+                parse_hex_color(&gradient.mid),
+                parse_hex_color(&gradient.end),
+            ],
+            gradient.mid_pos.clamp(0.05, 0.95),
+            (width, height),
+            fps,
+        )
+    }
+}
+
+// Source for a start/mid/end gradient along the 0..1 position expression t.
+fn three_stop_gradient_source(
+    t: &str,
+    stops: [(i32, i32, i32); 3],
+    m: f32,
+    (width, height): (i32, i32),
+    fps: u32,
+) -> String {
+    let [(sr, sg, sb), (mr, mg, mb), (er, eg, eb)] = stops;
+    let r = format!(
+        "if(lte({t},{m}),{sr}+({mr}-{sr})*{t}/{m},{mr}+({er}-{mr})*({t}-{m})/(1-{m}))"
+    );
+    let g = format!(
+        "if(lte({t},{m}),{sg}+({mg}-{sg})*{t}/{m},{mg}+({eg}-{mg})*({t}-{m})/(1-{m}))"
+    );
+    let b = format!(
+        "if(lte({t},{m}),{sb}+({mb}-{sb})*{t}/{m},{mb}+({eb}-{mb})*({t}-{m})/(1-{m}))"
+    );
+    static_geq_source((&r, &g, &b), (width, height), fps)
+}
+
+// Evaluates the geq expressions for a single frame and loops it, so a static
+// background costs one frame of geq instead of one per output frame.
+fn static_geq_source((r, g, b): (&str, &str, &str), (width, height): (i32, i32), fps: u32) -> String {
+    format!(
+        "nullsrc=s={width}x{height}:r={fps},trim=end_frame=1,format=rgba,geq=r='{r}':g='{g}':b='{b}':a='255',loop=loop=-1:size=1:start=0,setpts=N/({fps}*TB)"
+    )
+}
+
+fn append_watermark(
+    graph: String,
+    edit_state: &EditState,
+    theme: &ThemePack,
+    output_w: i32,
+    output_h: i32,
+) -> String {
+    let Some(watermark) = edit_state
+        .watermark
+        .as_ref()
+        .or(theme.watermark.as_ref())
+        .filter(|w| PathBuf::from(&w.image).is_file())
+    else {
+        return graph;
+    };
+    let Some(head) = graph.strip_suffix("[v]") else {
+        return graph;
+    };
+    let width = evenize(((output_w.min(output_h) as f32) * 0.18).round() as i32).max(2);
+    let margin = watermark.margin.unwrap_or(24) as i32;
+    let opacity = watermark.opacity.unwrap_or(0.8).clamp(0.0, 1.0);
+    let (x, y) = match watermark.corner.as_deref().unwrap_or("bottom_right") {
+        "top_left" => (format!("{margin}"), format!("{margin}")),
+        "top_right" => (format!("W-w-{margin}"), format!("{margin}")),
+        "bottom_left" => (format!("{margin}"), format!("H-h-{margin}")),
+        _ => (format!("W-w-{margin}"), format!("H-h-{margin}")),
+    };
+    format!(
+        "{head}[vwm];movie='{image}',scale={width}:-2,format=rgba,colorchannelmixer=aa={opacity}[wm];[vwm][wm]overlay=x={x}:y={y}[v]",
+        image = filter_escape_path(&watermark.image)
+    )
+}
+
+const KEY_BADGE_S: f64 = 1.2;
+const KEY_BADGE_FADE_S: f64 = 0.25;
+
+fn read_key_events(dir: &PathBuf) -> Vec<KeyEventRecord> {
+    fs::read_to_string(dir.join("keys.jsonl"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<KeyEventRecord>(line).ok())
+        .filter(|event| !event.keys.is_empty())
+        .collect()
+}
+
+// Maps a recording time onto the output timeline of a window render, or None
+// when the clip track cuts it.
+fn output_time_for(segments: Option<&[ClipWindowSegment]>, window_s: f64) -> Option<f64> {
+    let Some(segments) = segments else {
+        return Some(window_s);
+    };
+    let mut out_s = 0.0;
+    for seg in segments.iter() {
+        if window_s >= seg.start_s && window_s <= seg.end_s {
+            return Some(out_s + (window_s - seg.start_s) / seg.speed);
+        }
+        out_s += (seg.end_s - seg.start_s) / seg.speed;
+    }
+    None
+}
+
+// Two escaping levels: drawtext's option parser, then the graph parser.
+fn drawtext_escape(text: &str) -> String {
+    let mut option = String::new();
+    for c in text.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | ',' | ';' | '[' | ']') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+fn keystroke_font() -> String {
+    if cfg!(target_os = "windows") {
+        format!("fontfile='{}'", filter_escape_path("C:/Windows/Fonts/segoeuib.ttf"))
+    } else {
+        "font='Sans'".to_string()
+    }
+}
+
+// Fading badges for pressed keys from keys.jsonl, centered horizontally and
+// kept inside the title (top) or subtitle (bottom) safe area of the aspect.
+fn append_keystrokes(
+    graph: String,
+    input_path: &str,
+    edit_state: &EditState,
+    profile: &ExportProfile,
+    (start_s, end_s): (f64, f64),
+) -> String {
+    if !edit_state.keystrokes {
+        return graph;
+    }
+    let Some(dir) = PathBuf::from(input_path).parent().map(|d| d.to_path_buf()) else {
+        return graph;
+    };
+    let segments = load_clip_track(input_path)
+        .filter(|track| !track.segments.is_empty())
+        .map(|track| clip_segments_window(&track, start_s, end_s));
+    let mut badges: Vec<(f64, f64, String)> = Vec::new();
+    for event in read_key_events(&dir).iter() {
+        let t = event.offset_ms as f64 / 1000.0;
+        if t < start_s || t > end_s {
+            continue;
+        }
+        let Some(out_s) = output_time_for(segments.as_deref(), t - start_s) else {
+            continue;
+        };
+        if let Some(last) = badges.last_mut() {
+            last.1 = last.1.min(out_s);
+        }
+        badges.push((out_s, out_s + KEY_BADGE_S, event.keys.join(" + ")));
+    }
+    badges.retain(|badge| badge.1 > badge.0);
+    if badges.is_empty() {
+        return graph;
+    }
+    let Some(head) = graph.strip_suffix("[v]") else {
+        return graph;
+    };
+    let output_h = profile.height as f32;
+    let (title_safe, subtitle_safe) = match edit_state.aspect.as_str() {
+        "1:1" => (edit_state.title_safe_1_1, edit_state.subtitle_safe_1_1),
+        "9:16" => (edit_state.title_safe_9_16, edit_state.subtitle_safe_9_16),
+        _ => (edit_state.title_safe_16_9, edit_state.subtitle_safe_16_9),
+    };
+    let scale = if edit_state.keystroke_scale > 0.0 {
+        edit_state.keystroke_scale.clamp(0.5, 3.0)
+    } else {
+        1.0
+    };
+    let font_size = ((output_h * 0.04 * scale).round() as i32).max(12);
+    let pad = (font_size / 2).max(4);
+    let y = if edit_state.keystroke_position == "top" {
+        format!("{}", (output_h * title_safe.clamp(0.0, 0.4)).round() as i32 + pad)
+    } else {
         format!(
-            "nullsrc=s={width}x{height}:r={fps},format=rgba,geq=r='{r}':g='{g}':b='{b}':a='255'"
+            "{}-text_h-{pad}",
+            (output_h * (1.0 - subtitle_safe.clamp(0.0, 0.4))).round() as i32
         )
+    };
+    let font = keystroke_font();
+    let chain = badges
+        .iter()
+        .map(|(s, e, label)| {
+            format!(
+                "drawtext={font}:expansion=none:text={text}:fontsize={font_size}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw={pad}:x=(w-text_w)/2:y={y}:alpha='clip(min((t-{s})/{KEY_BADGE_FADE_S},({e}-t)/{KEY_BADGE_FADE_S}),0,1)':enable='between(t,{s},{e})'",
+                text = drawtext_escape(label)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{head}[vks];[vks]{chain}[v]")
+}
+
+fn smpte_timecode(seconds: f64, fps: u32) -> String {
+    let fps = fps.max(1) as u64;
+    let frames = (seconds.max(0.0) * fps as f64).round() as u64;
+    let secs = frames / fps;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        frames % fps
+    )
+}
+
+// Burns a running timecode or the capture wall-clock time into a corner.
+// Wall-clock text is drawn per clip segment so cuts keep the real time; inside
+// a sped-up segment it advances at output speed.
+fn append_timecode(
+    graph: String,
+    input_path: &str,
+    options: Option<&TimecodeOptions>,
+    profile: &ExportProfile,
+    (start_s, end_s): (f64, f64),
+) -> String {
+    let Some(options) = options else {
+        return graph;
+    };
+    let Some(head) = graph.strip_suffix("[v]") else {
+        return graph;
+    };
+    let font_size = ((profile.height as f32 * 0.03).round() as i32).max(12);
+    let pad = (font_size / 2).max(4);
+    let (x, y) = match options.position.as_deref() {
+        Some("top_right") => (format!("w-text_w-{pad}"), format!("{pad}")),
+        Some("bottom_left") => (format!("{pad}"), format!("h-text_h-{pad}")),
+        Some("bottom_right") => (format!("w-text_w-{pad}"), format!("h-text_h-{pad}")),
+        _ => (format!("{pad}"), format!("{pad}")),
+    };
+    let font = keystroke_font();
+    let style = format!("{font}:fontsize={font_size}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw={pad}:x={x}:y={y}");
+    let track = load_clip_track(input_path).filter(|track| !track.segments.is_empty());
+    let chain = if options.mode.as_deref() == Some("wall_clock") {
+        let Some(started_at_ms) = PathBuf::from(input_path)
+            .parent()
+            .and_then(|dir| fs::read_to_string(dir.join("capture.json")).ok())
+            .and_then(|data| serde_json::from_str::<CaptureMeta>(&data).ok())
+            .map(|meta| meta.started_at_ms)
+        else {
+            return graph;
+        };
+        let started_s = started_at_ms as f64 / 1000.0;
+        let clock = |epoch: f64| {
+            let text = format!("%{{pts:localtime:{epoch:.3}:%Y-%m-%d %H\\:%M\\:%S}}");
+            format!("drawtext={style}:text={}", drawtext_escape(&text))
+        };
+        let Some(track) = track.as_ref() else {
+            return format!("{head}[vtc];[vtc]{}[v]", clock(started_s + start_s));
+        };
+        let mut out_s = 0.0;
+        let mut stages = Vec::new();
+        for seg in clip_segments_window(track, start_s, end_s).iter() {
+            let seg_out = (out_s, out_s + (seg.end_s - seg.start_s) / seg.speed);
+            out_s = seg_out.1;
+            stages.push(format!(
+                "{}:enable='between(t,{:.3},{:.3})'",
+                clock(started_s + start_s + seg.start_s - seg_out.0),
+                seg_out.0,
+                seg_out.1
+            ));
+        }
+        stages.join(",")
     } else {
-        let (start, mid, end, mid_pos) = gradients[index % gradients.len()];
-        let (sr, sg, sb) = parse_hex_color(start);
-        let (mr, mg, mb) = parse_hex_color(mid);
-        let (er, eg, eb) = parse_hex_color(end);
-        let m = mid_pos;
-        let r = format!(
-            "if(lte({t},{m}),{sr}+({mr}-{sr})*{t}/{m},{mr}+({er}-{mr})*({t}-{m})/(1-{m}))"
-        );
-        let g = format!(
-            "if(lte({t},{m}),{sg}+({mg}-{sg})*{t}/{m},{mg}+({eg}-{mg})*({t}-{m})/(1-{m}))"
-        );
-        let b = format!(
-            "if(lte({t},{m}),{sb}+({mb}-{sb})*{t}/{m},{mb}+({eb}-{mb})*({t}-{m})/(1-{m}))"
-        );
+        // The edited-timeline position of this render's first frame.
+        let offset_s = match track.as_ref() {
+            Some(track) => clip_segments_window(track, 0.0, start_s)
+                .iter()
+                .map(|seg| (seg.end_s - seg.start_s) / seg.speed)
+                .sum(),
+            None => start_s,
+        };
         format!(
-            "nullsrc=s={width}x{height}:r={fps},format=rgba,geq=r='{r}':g='{g}':b='{b}':a='255'"
+            "drawtext={style}:timecode={}:rate={}",
+            drawtext_escape(&smpte_timecode(offset_s, profile.fps)),
+            profile.fps
         )
+    };
+    if chain.is_empty() {
+        return graph;
+    }
+    format!("{head}[vtc];[vtc]{chain}[v]")
+}
+
+struct SubtitleCue {
+    start_s: f64,
+    end_s: f64,
+    text: String,
+}
+
+// Accepts SRT (00:00:01,000) and VTT (00:00:01.000 or 00:01.000) times.
+fn parse_srt_time(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    let mut total = 0.0;
+    for part in value.split(':') {
+        total = total * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    Some(total)
+}
+
+fn parse_srt(data: &str) -> Vec<SubtitleCue> {
+    let data = data.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in data.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+        let Some((start, end)) = timing.split_once("-->") else {
+            continue;
+        };
+        // VTT-style cue settings may follow the end time.
+        let end = end.split_whitespace().next().unwrap_or("");
+        let (Some(start_s), Some(end_s)) = (parse_srt_time(start), parse_srt_time(end)) else {
+            continue;
+        };
+        let text = lines.collect::<Vec<_>>().join("\n");
+        if end_s > start_s && !text.trim().is_empty() {
+            cues.push(SubtitleCue { start_s, end_s, text });
+        }
+    }
+    cues
+}
+
+fn format_srt_time(time_s: f64) -> String {
+    let ms = (time_s.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn write_vtt(cues: &[SubtitleCue]) -> String {
+    let body = cues
+        .iter()
+        .map(|cue| {
+            format!(
+                "{} --> {}\n{}\n",
+                format_srt_time(cue.start_s).replace(',', "."),
+                format_srt_time(cue.end_s).replace(',', "."),
+                cue.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("WEBVTT\n\n{body}")
+}
+
+fn write_srt(cues: &[SubtitleCue]) -> String {
+    cues.iter()
+        .enumerate()
+        .map(|(i, cue)| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                format_srt_time(cue.start_s),
+                format_srt_time(cue.end_s),
+                cue.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// subtitles.srt, or the first other .srt, in the session folder.
+fn session_subtitle_path(dir: &PathBuf) -> Option<PathBuf> {
+    let preferred = dir.join("subtitles.srt");
+    if preferred.is_file() {
+        return Some(preferred);
+    }
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("srt"))
+        .filter(|p| {
+            !p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("subtitles_export_"))
+        })
+        .collect();
+    found.sort();
+    found.into_iter().next()
+}
+
+// Recording-time span [a, b] (relative to the window) on the output
+// timeline, or None when it was cut completely.
+fn output_span_for(segments: Option<&[ClipWindowSegment]>, (a, b): (f64, f64)) -> Option<(f64, f64)> {
+    let Some(segments) = segments else {
+        return (b > 0.0).then_some((a.max(0.0), b));
+    };
+    let mut out_s = 0.0;
+    let mut span: Option<(f64, f64)> = None;
+    for seg in segments.iter() {
+        let start = a.max(seg.start_s);
+        let end = b.min(seg.end_s);
+        if end > start {
+            let mapped = (
+                out_s + (start - seg.start_s) / seg.speed,
+                out_s + (end - seg.start_s) / seg.speed,
+            );
+            span = Some(match span {
+                Some((s, _)) => (s, mapped.1),
+                None => mapped,
+            });
+        }
+        out_s += (seg.end_s - seg.start_s) / seg.speed;
+    }
+    span
+}
+
+fn ass_color(hex: &str, alpha: f32) -> String {
+    let (r, g, b) = parse_hex_color(hex);
+    let a = ((1.0 - alpha.clamp(0.0, 1.0)) * 255.0).round() as i32;
+    format!("&H{a:02X}{b:02X}{g:02X}{r:02X}")
+}
+
+// The session subtitles that fall in [start_s, end_s), re-timed through the
+// clip track onto the output timeline of that window.
+fn output_subtitle_cues(input_path: &str, (start_s, end_s): (f64, f64)) -> Vec<SubtitleCue> {
+    let Some(dir) = PathBuf::from(input_path).parent().map(|d| d.to_path_buf()) else {
+        return Vec::new();
+    };
+    let Some(source) = session_subtitle_path(&dir) else {
+        return Vec::new();
+    };
+    let segments = load_clip_track(input_path)
+        .filter(|track| !track.segments.is_empty())
+        .map(|track| clip_segments_window(&track, start_s, end_s));
+    parse_srt(&fs::read_to_string(&source).unwrap_or_default())
+        .into_iter()
+        .filter(|cue| cue.end_s > start_s && cue.start_s < end_s)
+        .filter_map(|cue| {
+            let span = (cue.start_s - start_s, cue.end_s.min(end_s) - start_s);
+            let (a, b) = output_span_for(segments.as_deref(), span)?;
+            Some(SubtitleCue { start_s: a, end_s: b, text: cue.text })
+        })
+        .collect()
+}
+
+// Burns the session SRT into [v]. Cues are re-timed into a copy next to the
+// recording, and the bottom margin follows the subtitle safe area of the
+// aspect.
+fn append_subtitles(
+    graph: String,
+    input_path: &str,
+    style: Option<&SubtitleStyle>,
+    edit_state: &EditState,
+    profile: &ExportProfile,
+    (start_s, end_s): (f64, f64),
+) -> String {
+    let Some(style) = style else {
+        return graph;
+    };
+    let Some(dir) = PathBuf::from(input_path).parent().map(|d| d.to_path_buf()) else {
+        return graph;
+    };
+    let cues = output_subtitle_cues(input_path, (start_s, end_s));
+    if cues.is_empty() {
+        return graph;
     }
+    let Some(head) = graph.strip_suffix("[v]") else {
+        return graph;
+    };
+    let retimed = dir.join(format!("subtitles_export_{}.srt", (start_s * 1000.0) as u64));
+    if fs::write(&retimed, write_srt(&cues)).is_err() {
+        return graph;
+    }
+    let (width, height) = (profile.width as i32, profile.height as i32);
+    let subtitle_safe = match edit_state.aspect.as_str() {
+        "1:1" => edit_state.subtitle_safe_1_1,
+        "9:16" => edit_state.subtitle_safe_9_16,
+        _ => edit_state.subtitle_safe_16_9,
+    };
+    let margin_v = (height as f32 * subtitle_safe.clamp(0.0, 0.4)).round() as i32;
+    let font_size = style
+        .size
+        .map(|size| size.clamp(8, 200) as i32)
+        .unwrap_or_else(|| ((height as f32) * 0.045).round() as i32);
+    let font = style.font.as_deref().unwrap_or("Arial").replace([',', '\''], "");
+    let primary = ass_color(style.color.as_deref().unwrap_or("#ffffff"), 1.0);
+    let border = match style.background.as_deref() {
+        Some(background) => format!(
+            "BorderStyle=3,Outline=0,Shadow=0,BackColour={}",
+            ass_color(background, style.background_opacity.unwrap_or(0.6))
+        ),
+        None => "BorderStyle=1,Outline=2,Shadow=0,OutlineColour=&H00000000".to_string(),
+    };
+    format!(
+        "{head}[vsub];[vsub]subtitles=filename='{path}':original_size={width}x{height}:force_style='FontName={font},FontSize={font_size},PrimaryColour={primary},{border},Alignment=2,MarginV={margin_v}'[v]",
+        path = filter_escape_path(&retimed.to_string_lossy())
+    )
 }
 
 fn rounded_alpha_expr(radius: i32) -> String {
@@ -649,7 +2606,144 @@ fn rounded_alpha_expr(radius: i32) -> String {
     )
 }
 
-fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_camera: bool, camera_enable: Option<String>, clip_select: Option<String>) -> String {
+// colorkey then despill on the camera, keyed on a dominant green or blue.
+fn camera_chroma_filter(edit_state: &EditState) -> Option<String> {
+    let key = edit_state.camera_chroma_key.as_deref()?;
+    let hex = key.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let (_, g, b) = parse_hex_color(key);
+    let despill = if b > g { "blue" } else { "green" };
+    Some(format!(
+        ",colorkey=0x{hex}:{similarity:.3}:{blend:.3},despill=type={despill}",
+        similarity = edit_state.camera_chroma_similarity.clamp(0.01, 1.0),
+        blend = edit_state.camera_chroma_blend.clamp(0.0, 1.0),
+    ))
+}
+
+// eq and colortemperature for the camera, each left out when neutral. Ends
+// with a comma so it can be prefixed to the camera chain.
+fn camera_color_filter(edit_state: &EditState) -> String {
+    let mut chain = String::new();
+    let brightness = edit_state.camera_brightness.clamp(-1.0, 1.0);
+    let contrast = edit_state.camera_contrast.clamp(0.0, 3.0);
+    let saturation = edit_state.camera_saturation.clamp(0.0, 3.0);
+    if brightness.abs() > 0.001 || (contrast - 1.0).abs() > 0.001 || (saturation - 1.0).abs() > 0.001 {
+        chain.push_str(&format!(
+            "eq=brightness={brightness:.3}:contrast={contrast:.3}:saturation={saturation:.3},"
+        ));
+    }
+    let temperature = edit_state.camera_temperature;
+    if temperature != 0 && temperature != 6500 {
+        chain.push_str(&format!("colortemperature=temperature={},", temperature.clamp(1000, 40000)));
+    }
+    chain
+}
+
+// Title bars sit above the content at full width; a bezel is fitted to the
+// inner rect with the content behind its screen opening (in asset pixels).
+struct MockupSpec {
+    name: &'static str,
+    asset: &'static str,
+    size: (i32, i32),
+    screen: Option<(i32, i32, i32, i32)>,
+}
+
+const MOCKUP_FRAMES: &[MockupSpec] = &[
+    MockupSpec { name: "browser", asset: "browser_bar.png", size: (1600, 96), screen: None },
+    MockupSpec { name: "macos", asset: "macos_bar.png", size: (1600, 64), screen: None },
+    MockupSpec {
+        name: "phone",
+        asset: "phone_bezel.png",
+        size: (1000, 2040),
+        screen: Some((40, 40, 920, 1960)),
+    },
+];
+
+struct MockupLayout {
+    asset: PathBuf,
+    asset_rect: (i32, i32, i32, i32),
+    content_rect: (i32, i32, i32, i32),
+    bezel: bool,
+}
+
+fn mockup_layout(edit_state: &EditState, inner_w: i32, inner_h: i32) -> Option<MockupLayout> {
+    let name = edit_state.frame.as_deref()?;
+    let spec = MOCKUP_FRAMES.iter().find(|spec| spec.name == name)?;
+    let asset = MOCKUP_DIR.get()?.join(spec.asset);
+    if !asset.is_file() {
+        return None;
+    }
+    let (aw, ah) = (spec.size.0 as f32, spec.size.1 as f32);
+    match spec.screen {
+        None => {
+            let bar_h = evenize((inner_w as f32 * ah / aw).round() as i32).clamp(2, inner_h / 4);
+            Some(MockupLayout {
+                asset,
+                asset_rect: (0, 0, inner_w, bar_h),
+                content_rect: (0, bar_h, inner_w, evenize(inner_h - bar_h).max(2)),
+                bezel: false,
+            })
+        }
+        Some((sx, sy, sw, sh)) => {
+            let scale = (inner_w as f32 / aw).min(inner_h as f32 / ah);
+            let w = evenize((aw * scale).round() as i32).max(2);
+            let h = evenize((ah * scale).round() as i32).max(2);
+            let x = evenize((inner_w - w) / 2);
+            let y = evenize((inner_h - h) / 2);
+            // Content slightly overlaps the opening so no seam shows.
+            let cx = x + (sx as f32 * scale).floor() as i32 - 1;
+            let cy = y + (sy as f32 * scale).floor() as i32 - 1;
+            Some(MockupLayout {
+                asset,
+                asset_rect: (x, y, w, h),
+                content_rect: (
+                    evenize(cx.max(0)),
+                    evenize(cy.max(0)),
+                    evenize((sw as f32 * scale).ceil() as i32 + 4).max(2),
+                    evenize((sh as f32 * scale).ceil() as i32 + 4).max(2),
+                ),
+                bezel: true,
+            })
+        }
+    }
+}
+
+// Single-frame gray mask source evaluated once; alphamerge and blend repeat
+// the last mask frame for the rest of the stream.
+fn rounded_mask_source(w: i32, h: i32, radius: i32, fps: u32) -> String {
+    format!(
+        "color=c=black:s={w}x{h}:r={fps},trim=end_frame=1,format=gray,geq=lum='{}'",
+        rounded_alpha_expr(radius)
+    )
+}
+
+// Cuts rounded corners into an rgba chain of size w x h. With keep_alpha the
+// existing alpha is intersected with the mask instead of replaced.
+fn apply_rounded_mask(chain: &str, label: &str, (w, h): (i32, i32), radius: i32, fps: u32, keep_alpha: bool) -> String {
+    let mask = rounded_mask_source(w, h, radius, fps);
+    if keep_alpha {
+        format!(
+            "{chain},split=2[{label}rgb][{label}src];[{label}src]alphaextract[{label}a];{mask}[{label}mask];[{label}a][{label}mask]blend=all_mode=darken[{label}alpha];[{label}rgb][{label}alpha]alphamerge"
+        )
+    } else {
+        format!("{chain}[{label}rgb];{mask}[{label}mask];[{label}rgb][{label}mask]alphamerge")
+    }
+}
+
+// mode_9_16 / mode_1_1 = "blur": the recording sits centered over a blurred,
+// frame-filling copy of itself instead of the background.
+fn blur_fill_layout(edit_state: &EditState) -> bool {
+    match edit_state.aspect.as_str() {
+        "9:16" => edit_state.mode_9_16 == "blur",
+        "1:1" => edit_state.mode_1_1 == "blur",
+        _ => false,
+    }
+}
+
+fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_camera: bool, camera_enable: Option<String>, clip_select: Option<ClipSelect>, source_fx: Option<String>) -> String {
+    let theme = load_theme(edit_state.theme.as_deref());
     let output_w = profile.width as i32;
     let output_h = profile.height as i32;
     let aspect = aspect_ratio(&edit_state.aspect);
@@ -673,8 +2767,20 @@ fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_came
     let shadow_blur = (shadow / 4).max(1);
     let shadow_alpha = ((shadow as f32) / 120.0).clamp(0.0, 0.6);
     let shadow_offset = (shadow / 6).max(0);
-    let bg_source = background_source(edit_state, output_w, output_h, profile.fps);
-    let bg_comp_source = background_source(edit_state, inner_w, inner_h, profile.fps);
+    let bg_source = background_source(edit_state, &theme, output_w, output_h, profile.fps);
+    let mockup = mockup_layout(edit_state, inner_w, inner_h);
+    // The recording fills the mockup's content area instead of the inner rect.
+    let (content_w, content_h) = mockup
+        .as_ref()
+        .map(|m| (m.content_rect.2, m.content_rect.3))
+        .unwrap_or((inner_w, inner_h));
+    let blur_fill = blur_fill_layout(edit_state);
+    let bg_comp_source = if blur_fill {
+        // Letterbox bands stay see-through so the blurred fill shows.
+        format!("color=c=black@0:s={content_w}x{content_h}:r={},format=rgba", profile.fps)
+    } else {
+        background_source(edit_state, &theme, content_w, content_h, profile.fps)
+    };
     let is_portrait_split = false;
     let margin_lr_169 = 0.06f32;
     let margin_tb_916 = 0.36f32;
@@ -697,40 +2803,78 @@ fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_came
     let safe_y = edit_state.safe_y.clamp(0.0, 1.0);
     let safe_w = edit_state.safe_w.clamp(0.0, 1.0);
     let safe_h = edit_state.safe_h.clamp(0.0, 1.0);
-    let safe_w_px = evenize(((safe_w * inner_w as f32).round() as i32).max(2));
-    let safe_h_px = evenize(((safe_h * inner_h as f32).round() as i32).max(2));
-    let mut safe_x_px = evenize((safe_x * inner_w as f32).round() as i32);
-    let mut safe_y_px = evenize((safe_y * inner_h as f32).round() as i32);
-    if inner_w > safe_w_px {
-        safe_x_px = safe_x_px.max(0).min(inner_w - safe_w_px);
+    let safe_w_px = evenize(((safe_w * content_w as f32).round() as i32).max(2));
+    let safe_h_px = evenize(((safe_h * content_h as f32).round() as i32).max(2));
+    let mut safe_x_px = evenize((safe_x * content_w as f32).round() as i32);
+    let mut safe_y_px = evenize((safe_y * content_h as f32).round() as i32);
+    if content_w > safe_w_px {
+        safe_x_px = safe_x_px.max(0).min(content_w - safe_w_px);
     } else {
         safe_x_px = 0;
     }
-    if inner_h > safe_h_px {
-        safe_y_px = safe_y_px.max(0).min(inner_h - safe_h_px);
+    if content_h > safe_h_px {
+        safe_y_px = safe_y_px.max(0).min(content_h - safe_h_px);
     } else {
         safe_y_px = 0;
     }
+    let clip_stage = clip_select
+        .as_ref()
+        .map(|clip| match clip.retime.as_ref() {
+            // Speed ramps: map each kept frame onto the output timeline,
+            // then let fps duplicate or drop frames to a constant rate.
+            Some(retime) => format!(",select='{}',setpts='{}',fps={}", clip.expr, retime, profile.fps),
+            None => format!(",select='{}',setpts=N/({}*TB)", clip.expr, profile.fps),
+        })
+        .unwrap_or_default();
+    let source = source_fx
+        .map(|fx| format!("{fx};[src]"))
+        .unwrap_or_else(|| "[0:v]".to_string());
+    // Blur fill: a scaled-up, blurred copy of the recording goes behind the
+    // sharp one, cut and retimed the same way so both stay in sync.
+    let (bg_source, source) = if blur_fill {
+        let sigma = match edit_state.background_blur.min(100) {
+            0 => 30,
+            sigma => sigma,
+        };
+        (
+            format!(
+                "{source}split=2[fillsrc][fgsrc];[fillsrc]scale={output_w}:{output_h}:force_original_aspect_ratio=increase,crop={output_w}:{output_h},gblur=sigma={sigma},eq=brightness=-0.08,format=rgba,fps={fps}{clip_stage}",
+                fps = profile.fps
+            ),
+            "[fgsrc]".to_string(),
+        )
+    } else {
+        (bg_source, source)
+    };
+    let pad_color = if blur_fill { ":color=black@0" } else { "" };
     let base = if is_portrait_split {
         unreachable!()
     } else {
-        let mut s = format!(
-            "{bg_source}[bg];{bg_comp}[bgc];[0:v]scale={safe_w}:{safe_h}:force_original_aspect_ratio=decrease,pad={safe_w}:{safe_h}:(ow-iw)/2:(oh-ih)/2,format=rgba[vid];[bgc][vid]overlay=x={safe_x}:y={safe_y}:shortest=1,format=rgba,fps={fps}",
+        format!(
+            "{bg_source}[bg];{bg_comp}[bgc];{source}scale={safe_w}:{safe_h}:force_original_aspect_ratio=decrease,pad={safe_w}:{safe_h}:(ow-iw)/2:(oh-ih)/2{pad_color},format=rgba[vid];[bgc][vid]overlay=x={safe_x}:y={safe_y}:shortest=1,format=rgba,fps={fps}{clip_stage}",
             bg_comp = bg_comp_source,
             safe_w = safe_w_px,
             safe_h = safe_h_px,
             safe_x = safe_x_px,
             safe_y = safe_y_px,
-            fps = profile.fps
-        );
-        if let Some(expr) = clip_select.as_ref() {
-            s = format!("{},select='{}',setpts=N/({}*TB)", s, expr, profile.fps);
+            fps = profile.fps,
+        )
+    };
+    let base = match mockup.as_ref() {
+        Some(mockup) => {
+            let (cx, cy, _, _) = mockup.content_rect;
+            let (ax, ay, aw, ah) = mockup.asset_rect;
+            format!(
+                "{base},pad={inner_w}:{inner_h}:{cx}:{cy}:color=black@0[mockcontent];movie='{asset}',scale={aw}:{ah},format=rgba[mockframe];[mockcontent][mockframe]overlay=x={ax}:y={ay},format=rgba",
+                asset = filter_escape_path(&mockup.asset.to_string_lossy())
+            )
         }
-        s
+        None => base,
     };
+    // A bezel brings its own outline, so the corners are not rounded again.
+    let radius = if mockup.as_ref().is_some_and(|m| m.bezel) { 0 } else { radius };
     let rounded = if radius > 0 {
-        let alpha_expr = rounded_alpha_expr(radius);
-        format!("{base},geq=r='r(X,Y)':g='g(X,Y)':b='b(X,Y)':a='{alpha_expr}'")
+        apply_rounded_mask(&base, "scr", (inner_w, inner_h), radius, profile.fps, false)
     } else {
         base
     };
@@ -759,7 +2903,7 @@ fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_came
         )
     };
     if !has_camera {
-        return base;
+        return append_watermark(base, edit_state, &theme, output_w, output_h);
     }
     let camera_size = if edit_state.aspect.as_str() == "9:16" {
         let base = (edit_state.camera_size as f32).max(2.0);
@@ -767,8 +2911,14 @@ fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_came
     } else {
         evenize(((inner_w as f32) * 0.10).round() as i32).max(2)
     };
+    let ring_width = theme
+        .camera_ring
+        .as_ref()
+        .map(|ring| evenize(ring.width as i32).max(0))
+        .unwrap_or(0);
+    let bubble_size = camera_size + ring_width * 2;
     let camera_scale_expr = "1".to_string();
-    let camera_size_expr = format!("round({}*({}))", camera_size, camera_scale_expr);
+    let camera_size_expr = format!("round({}*({}))", bubble_size, camera_scale_expr);
     let offset = if edit_state.aspect.as_str() == "9:16" { 16 } else { 12 };
     let (camera_x_expr, camera_y_expr) = match edit_state.camera_position.as_str() {
         "top_left" => (format!("{}", offset), format!("{}", offset)),
@@ -798,20 +2948,47 @@ fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_came
     let camera_shadow_alpha = ((camera_shadow as f32) / 120.0).clamp(0.0, 0.6);
     let camera_shadow_offset = (camera_shadow / 6).max(0);
     let mirror = if edit_state.camera_mirror { "hflip," } else { "" };
+    let chroma = camera_chroma_filter(edit_state);
     let camera_base = format!(
-        "[1:v]{mirror}scale={camera_size}:{camera_size}:force_original_aspect_ratio=increase,crop={camera_size}:{camera_size},format=rgba"
+        "[1:v]{mirror}{}scale={camera_size}:{camera_size}:force_original_aspect_ratio=increase,crop={camera_size}:{camera_size},format=rgba{}",
+        camera_color_filter(edit_state),
+        chroma.as_deref().unwrap_or("")
     );
     let camera_rounded = if camera_radius > 0 {
-        let alpha_expr = rounded_alpha_expr(camera_radius);
-        format!("{camera_base},geq=r='r(X,Y)':g='g(X,Y)':b='b(X,Y)':a='{alpha_expr}'")
+        // Keep the keyed alpha inside the rounded mask.
+        apply_rounded_mask(
+            &camera_base,
+            "cam",
+            (camera_size, camera_size),
+            camera_radius,
+            profile.fps,
+            chroma.is_some(),
+        )
     } else {
         camera_base
     };
+    let camera_rounded = match theme.camera_ring.as_ref() {
+        Some(ring) if ring_width > 0 => {
+            let ring_color = format!("0x{}", ring.color.trim_start_matches('#'));
+            let ring_radius = if camera_radius > 0 { camera_radius + ring_width } else { 0 };
+            let ring_alpha = if ring_radius > 0 {
+                rounded_alpha_expr(ring_radius)
+            } else {
+                "255".to_string()
+            };
+            // The ring is drawn once and looped under the camera.
+            format!(
+                "{camera_rounded}[camcore];color=c={ring_color}:s={bubble_size}x{bubble_size}:r={fps},trim=end_frame=1,format=rgba,geq=r='r(X,Y)':g='g(X,Y)':b='b(X,Y)':a='{ring_alpha}',loop=loop=-1:size=1[camring];[camring][camcore]overlay=x={ring_width}:y={ring_width}:shortest=1",
+                fps = profile.fps
+            )
+        }
+        _ => camera_rounded,
+    };
     let camera_scaled = format!(
         "{camera_rounded},scale=w='round(iw*({scale}))':h='round(ih*({scale}))':eval=frame",
         scale = camera_scale_expr
     );
-    if camera_shadow > 0 {
+    let graph = if camera_shadow > 0 {
         let shadow_x_expr = format!("'({})+{}'", camera_x_expr, camera_shadow_offset);
         let shadow_y_expr = format!("'({})+{}'", camera_y_expr, camera_shadow_offset);
         let enable_expr = camera_enable
@@ -838,48 +3015,198 @@ fn build_export_filter(edit_state: &EditState, profile: &ExportProfile, has_came
             camera_y = camera_y_value,
             enable = enable_expr
         )
+    };
+    append_watermark(graph, edit_state, &theme, output_w, output_h)
+}
+
+// Schema migrations for session files. Entry N upgrades a document from
+// version N to N + 1; the current version is the length of the list.
+type JsonMigration = fn(&mut serde_json::Value) -> Vec<String>;
+
+const EDIT_STATE_MIGRATIONS: &[JsonMigration] = &[migrate_edit_state_v1];
+const CLIP_TRACK_MIGRATIONS: &[JsonMigration] = &[migrate_track_segments_v1];
+const CAMERA_TRACK_MIGRATIONS: &[JsonMigration] = &[migrate_track_segments_v1];
+const PROJECT_MIGRATIONS: &[JsonMigration] = &[migrate_project_v1];
+
+fn schema_version(migrations: &[JsonMigration]) -> u32 {
+    migrations.len() as u32
+}
+
+fn json_version(value: &serde_json::Value) -> u32 {
+    value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32
+}
+
+fn migrate_edit_state_v1(value: &mut serde_json::Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    let defaults = serde_json::to_value(EditState::default()).unwrap_or_default();
+    let (Some(obj), Some(defaults)) = (value.as_object_mut(), defaults.as_object()) else {
+        return changes;
+    };
+    for (key, default) in defaults.iter() {
+        if !obj.contains_key(key) {
+            obj.insert(key.clone(), default.clone());
+            changes.push(format!("added {key}"));
+        }
+    }
+    let position = obj
+        .get("camera_position")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    if !["top_left", "top_right", "bottom_left", "bottom_right"].contains(&position.as_str()) {
+        obj.insert("camera_position".to_string(), "bottom_left".into());
+        changes.push(format!("camera_position {position:?} -> \"bottom_left\""));
     }
+    changes
 }
 
-fn derive_camera_enable(input_path: &str) -> Option<String> {
-    let binding = PathBuf::from(input_path);
-    let dir = binding.parent()?;
-    let path = dir.join("camera_track.json");
-    let data = fs::read_to_string(&path).ok()?;
-    let track: CameraTrack = serde_json::from_str(&data).ok()?;
-    if track.segments.is_empty() {
-        return None;
+fn migrate_track_segments_v1(value: &mut serde_json::Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    let Some(obj) = value.as_object_mut() else {
+        return changes;
+    };
+    let segments = obj
+        .entry("segments")
+        .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+    if !segments.is_array() {
+        *segments = serde_json::Value::Array(Vec::new());
+        changes.push("replaced malformed segments".to_string());
+        return changes;
     }
-    let mut expr = String::new();
-    for seg in track.segments.iter() {
-        if !seg.visible {
-            continue;
+    let Some(segments) = segments.as_array_mut() else {
+        return changes;
+    };
+    let bounds = |seg: &serde_json::Value| {
+        (
+            seg.get("start_s").and_then(|v| v.as_f64()),
+            seg.get("end_s").and_then(|v| v.as_f64()),
+        )
+    };
+    let before = segments.len();
+    segments.retain(|seg| matches!(bounds(seg), (Some(start), Some(end)) if start >= 0.0 && end > start));
+    if segments.len() != before {
+        changes.push(format!("dropped {} invalid segments", before - segments.len()));
+    }
+    let starts: Vec<f64> = segments.iter().filter_map(|seg| bounds(seg).0).collect();
+    if starts.windows(2).any(|w| w[1] < w[0]) {
+        segments.sort_by(|a, b| {
+            let a = bounds(a).0.unwrap_or(0.0);
+            let b = bounds(b).0.unwrap_or(0.0);
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        changes.push("sorted segments".to_string());
+    }
+    changes
+}
+
+// Brings the documents embedded in a project up to their own schemas.
+fn migrate_project_v1(value: &mut serde_json::Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    let Some(obj) = value.as_object_mut() else {
+        return changes;
+    };
+    for (key, migrations) in project_member_migrations() {
+        if let Some(member) = obj.get_mut(key).filter(|m| m.is_object()) {
+            let (from, member_changes) = apply_migrations(member, migrations);
+            changes.extend(member_changes.into_iter().map(|c| format!("{key}: {c}")));
+            if from < schema_version(migrations) {
+                changes.push(format!("{key}: v{from} -> v{}", schema_version(migrations)));
+            }
         }
-        let part = format!("between(t,{},{})", seg.start_s, seg.end_s);
-        if expr.is_empty() {
-            expr = part;
-        } else {
-            expr = format!("({})+({})", expr, part);
+    }
+    changes
+}
+
+fn project_member_migrations() -> [(&'static str, &'static [JsonMigration]); 3] {
+    [
+        ("edit_state", EDIT_STATE_MIGRATIONS),
+        ("clip_track", CLIP_TRACK_MIGRATIONS),
+        ("camera_track", CAMERA_TRACK_MIGRATIONS),
+    ]
+}
+
+fn apply_migrations(value: &mut serde_json::Value, migrations: &[JsonMigration]) -> (u32, Vec<String>) {
+    let from = json_version(value);
+    let mut changes = Vec::new();
+    for (index, migration) in migrations.iter().enumerate().skip(from as usize) {
+        changes.extend(migration(value));
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("version".to_string(), (index as u64 + 1).into());
         }
     }
-    if expr.is_empty() {
-        None
-    } else {
-        Some(expr)
+    (from, changes)
+}
+
+fn read_migrated<T: serde::de::DeserializeOwned>(
+    path: &PathBuf,
+    migrations: &[JsonMigration],
+) -> Option<T> {
+    let data = fs::read_to_string(path).ok()?;
+    let mut value: serde_json::Value = serde_json::from_str(&data).ok()?;
+    apply_migrations(&mut value, migrations);
+    serde_json::from_value(value).ok()
+}
+
+fn to_versioned_value<T: Serialize>(
+    data: &T,
+    migrations: &[JsonMigration],
+) -> Result<serde_json::Value, String> {
+    let mut value = serde_json::to_value(data).map_err(|e| e.to_string())?;
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("version".to_string(), schema_version(migrations).into());
+    }
+    Ok(value)
+}
+
+// Stamps the current schema version into track JSON coming from the editor;
+// unparseable input is passed through untouched.
+fn stamp_track_json(track_json: String, migrations: &[JsonMigration]) -> String {
+    match serde_json::from_str::<serde_json::Value>(&track_json) {
+        Ok(mut value) if value.is_object() => {
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert("version".to_string(), schema_version(migrations).into());
+            }
+            serde_json::to_string(&value).unwrap_or(track_json)
+        }
+        _ => track_json,
+    }
+}
+
+fn session_dir(session_id: &str) -> Result<PathBuf, String> {
+    if session_id.trim().is_empty()
+        || session_id.contains(['/', '\\'])
+        || session_id.contains("..")
+    {
+        return Err("invalid_session_id".to_string());
     }
+    // Sessions moved off the work folder by move_session.
+    if let Some(dir) = read_session_locations()
+        .get(session_id)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+    {
+        return Ok(dir);
+    }
+    let dir = work_base_dir().join(session_id);
+    if !dir.is_dir() {
+        return Err("session_not_found".to_string());
+    }
+    Ok(dir)
 }
 
-fn derive_clip_select(input_path: &str) -> Option<String> {
+fn derive_camera_enable(input_path: &str) -> Option<String> {
     let binding = PathBuf::from(input_path);
     let dir = binding.parent()?;
-    let path = dir.join("clip_track.json");
-    let data = fs::read_to_string(&path).ok()?;
-    let track: ClipTrack = serde_json::from_str(&data).ok()?;
+    let path = dir.join("camera_track.json");
+    let track: CameraTrack = read_migrated(&path, CAMERA_TRACK_MIGRATIONS)?;
     if track.segments.is_empty() {
         return None;
     }
     let mut expr = String::new();
     for seg in track.segments.iter() {
+        if !seg.visible {
+            continue;
+        }
         let part = format!("between(t,{},{})", seg.start_s, seg.end_s);
         if expr.is_empty() {
             expr = part;
@@ -894,35 +3221,70 @@ fn derive_clip_select(input_path: &str) -> Option<String> {
     }
 }
 
+fn derive_clip_select(input_path: &str) -> Option<ClipSelect> {
+    let track = load_clip_track(input_path)?;
+    clip_select_for(&clip_segments_window(&track, 0.0, f64::MAX))
+}
+
 fn load_clip_track(input_path: &str) -> Option<ClipTrack> {
     let binding = PathBuf::from(input_path);
     let dir = binding.parent()?;
     let path = dir.join("clip_track.json");
-    let data = fs::read_to_string(&path).ok()?;
-    serde_json::from_str(&data).ok()
+    read_migrated(&path, CLIP_TRACK_MIGRATIONS)
 }
 
 fn load_camera_track(input_path: &str) -> Option<CameraTrack> {
     let binding = PathBuf::from(input_path);
     let dir = binding.parent()?;
     let path = dir.join("camera_track.json");
-    let data = fs::read_to_string(&path).ok()?;
-    serde_json::from_str(&data).ok()
+    read_migrated(&path, CAMERA_TRACK_MIGRATIONS)
+}
+
+// A clip segment clipped to a source window, with times relative to the
+// window start.
+struct ClipWindowSegment {
+    start_s: f64,
+    end_s: f64,
+    speed: f64,
+}
+
+struct ClipSelect {
+    expr: String,
+    // setpts expression for speed ramps; None means plain frame renumbering.
+    retime: Option<String>,
+}
+
+fn clip_segment_speed(seg: &ClipSegment) -> f64 {
+    seg.speed
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .map(|speed| speed.clamp(0.1, 16.0) as f64)
+        .unwrap_or(1.0)
+}
+
+fn clip_segments_window(track: &ClipTrack, start_s: f64, end_s: f64) -> Vec<ClipWindowSegment> {
+    track
+        .segments
+        .iter()
+        .filter_map(|seg| {
+            let seg_start = seg.start_s.max(start_s);
+            let seg_end = seg.end_s.min(end_s);
+            (seg_end > seg_start).then(|| ClipWindowSegment {
+                start_s: seg_start - start_s,
+                end_s: seg_end - start_s,
+                speed: clip_segment_speed(seg),
+            })
+        })
+        .collect()
 }
 
-fn build_clip_select_window(track: &ClipTrack, start_s: f64, end_s: f64) -> Option<String> {
+fn clip_has_speed_ramp(segments: &[ClipWindowSegment]) -> bool {
+    segments.iter().any(|seg| (seg.speed - 1.0).abs() > 1e-6)
+}
+
+fn clip_select_for(segments: &[ClipWindowSegment]) -> Option<ClipSelect> {
     let mut expr = String::new();
-    for seg in track.segments.iter() {
-        let seg_start = seg.start_s.max(start_s);
-        let seg_end = seg.end_s.min(end_s);
-        if seg_end <= seg_start {
-            continue;
-        }
-        let part = format!(
-            "between(t,{},{})",
-            seg_start - start_s,
-            seg_end - start_s
-        );
+    for seg in segments.iter() {
+        let part = format!("between(t,{},{})", seg.start_s, seg.end_s);
         if expr.is_empty() {
             expr = part;
         } else {
@@ -930,127 +3292,1052 @@ fn build_clip_select_window(track: &ClipTrack, start_s: f64, end_s: f64) -> Opti
         }
     }
     if expr.is_empty() {
+        return None;
+    }
+    let retime = if clip_has_speed_ramp(segments) {
+        let mut retime = "0".to_string();
+        let mut offsets = Vec::with_capacity(segments.len());
+        let mut out_s = 0.0;
+        for seg in segments.iter() {
+            offsets.push(out_s);
+            out_s += (seg.end_s - seg.start_s) / seg.speed;
+        }
+        for (seg, offset) in segments.iter().zip(offsets).rev() {
+            retime = format!(
+                "if(between(T,{start},{end}),({offset}+(T-{start})/{speed})/TB,{retime})",
+                start = seg.start_s,
+                end = seg.end_s,
+                speed = seg.speed
+            );
+        }
+        Some(retime)
+    } else {
         None
+    };
+    Some(ClipSelect { expr, retime })
+}
+
+// atempo only accepts 0.5..2.0 per instance, so larger factors are chained.
+fn atempo_chain(speed: f64) -> String {
+    let mut remaining = speed;
+    let mut parts = Vec::new();
+    while remaining > 2.0 {
+        parts.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        parts.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    if (remaining - 1.0).abs() > 1e-6 {
+        parts.push(format!("atempo={remaining:.6}"));
+    }
+    parts.join(",")
+}
+
+// Audio counterpart of the clip select: trims each segment out of [0:a],
+// applies its tempo (pitch preserved) and concatenates the pieces into [a].
+fn build_clip_audio_filter(segments: &[ClipWindowSegment], input: &str) -> Option<String> {
+    if segments.is_empty() {
+        return None;
+    }
+    let count = segments.len();
+    let mut graph = if count > 1 {
+        let outs = (0..count).map(|i| format!("[as{i}]")).collect::<String>();
+        format!("{input}asplit={count}{outs};")
     } else {
-        Some(expr)
+        String::new()
+    };
+    for (i, seg) in segments.iter().enumerate() {
+        let source = if count > 1 { format!("[as{i}]") } else { input.to_string() };
+        let tempo = atempo_chain(seg.speed);
+        let tempo = if tempo.is_empty() { String::new() } else { format!(",{tempo}") };
+        graph.push_str(&format!(
+            "{source}atrim=start={}:end={},asetpts=PTS-STARTPTS{tempo}[ac{i}];",
+            seg.start_s, seg.end_s
+        ));
     }
+    let inputs = (0..count).map(|i| format!("[ac{i}]")).collect::<String>();
+    graph.push_str(&format!("{inputs}concat=n={count}:v=0:a=1[a]"));
+    Some(graph)
 }
 
-fn build_camera_enable_window(track: &CameraTrack, start_s: f64, end_s: f64) -> Option<String> {
-    let mut expr = String::new();
-    for seg in track.segments.iter() {
-        if !seg.visible {
+fn audio_track_path(input_path: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join("audio_track.json"))
+}
+
+fn chapter_track_path(input_path: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join("chapters_track.json"))
+}
+
+fn load_chapter_track(input_path: &str) -> Option<ChapterTrack> {
+    let path = chapter_track_path(input_path).ok()?;
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn marker_track_path(input_path: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join("marker_track.json"))
+}
+
+fn marker_color_for(label: &str) -> &'static str {
+    let label = label.to_ascii_lowercase();
+    if label.contains("retake") {
+        "#ef4444"
+    } else if label.contains("cut") {
+        "#f97316"
+    } else if label.contains("todo") || label.contains("fix") {
+        "#eab308"
+    } else {
+        "#38bdf8"
+    }
+}
+
+fn camera_framing_path(input_path: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join("camera_framing_track.json"))
+}
+
+fn load_camera_framing(input_path: &str) -> Option<CameraFramingTrack> {
+    let path = camera_framing_path(input_path).ok()?;
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+// How much of the frame around the skin region the bubble shows, in region
+// heights.
+const CAMERA_FRAMING_HEADROOM: f32 = 2.4;
+
+// Crops the camera input to a square around the framed skin region before the
+// bubble's own scale/crop, which otherwise centers blindly.
+fn append_camera_framing(graph: String, input_path: &str) -> String {
+    let Some(frame) = load_camera_framing(input_path).and_then(|track| track.frame) else {
+        return graph;
+    };
+    let side = (frame.size * CAMERA_FRAMING_HEADROOM).clamp(0.2, 1.0);
+    let crop = format!(
+        "crop=w='min(min(iw,ih),{side:.4}*ih)':h='min(min(iw,ih),{side:.4}*ih)':x='clip({cx:.4}*iw-ow/2,0,iw-ow)':y='clip({cy:.4}*ih-oh/2,0,ih-oh)',",
+        cx = frame.cx,
+        cy = frame.cy,
+    );
+    graph.replacen("[1:v]", &format!("[1:v]{crop}"), 1)
+}
+
+fn median_f32(values: &mut [f32]) -> f32 {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    values.get(values.len() / 2).copied().unwrap_or(0.0)
+}
+
+// Skin chroma bounds in Cb/Cr (Chai & Ngan). Luma is ignored, which keeps
+// them working across skin tones and lighting.
+const SKIN_CB: std::ops::RangeInclusive<u8> = 77..=127;
+const SKIN_CR: std::ops::RangeInclusive<u8> = 133..=173;
+// Frames are scanned at this width; a presenter in a camera bubble stays well
+// above a handful of pixels at it.
+const SKIN_SCAN_WIDTH: usize = 160;
+
+// Center and height of the largest connected skin region in one planar YUV
+// 4:4:4 frame, normalized. This is a chroma heuristic, not a face detector:
+// the region is cut to head proportions from its top since it often runs on
+// into the neck and shoulders, and hands or skin-toned backgrounds can win.
+fn skin_region_in_frame(frame: &[u8], (w, h): (usize, usize)) -> Option<(f32, f32, f32)> {
+    let plane = w * h;
+    let (cb, cr) = (&frame[plane..2 * plane], &frame[2 * plane..3 * plane]);
+    let mut mask: Vec<bool> = (0..plane)
+        .map(|i| SKIN_CB.contains(&cb[i]) && SKIN_CR.contains(&cr[i]))
+        .collect();
+    let mut best: Option<(usize, (usize, usize, usize, usize))> = None;
+    let mut stack = Vec::new();
+    for start in 0..plane {
+        if !mask[start] {
             continue;
         }
-        let seg_start = seg.start_s.max(start_s);
-        let seg_end = seg.end_s.min(end_s);
-        if seg_end <= seg_start {
-            continue;
+        mask[start] = false;
+        stack.push(start);
+        let (mut count, mut x0, mut y0, mut x1, mut y1) = (0, w, h, 0, 0);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            count += 1;
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < w).then(|| i + 1),
+                (y > 0).then(|| i - w),
+                (y + 1 < h).then(|| i + w),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if mask[n] {
+                    mask[n] = false;
+                    stack.push(n);
+                }
+            }
         }
-        let part = format!(
-            "between(t,{},{})",
-            seg_start - start_s,
-            seg_end - start_s
-        );
-        if expr.is_empty() {
-            expr = part;
-        } else {
-            expr = format!("({})+({})", expr, part);
+        if !best.is_some_and(|(most, _)| most >= count) {
+            best = Some((count, (x0, y0, x1, y1)));
         }
     }
-    if expr.is_empty() {
-        None
-    } else {
-        Some(expr)
+    let (count, (x0, y0, x1, y1)) = best?;
+    // Less than 1% of the frame is noise, not a presenter.
+    if count * 100 < plane {
+        return None;
     }
+    let box_w = (x1 - x0 + 1) as f32;
+    let box_h = ((y1 - y0 + 1) as f32).min(box_w * 1.3);
+    Some((
+        (x0 as f32 + box_w / 2.0) / w as f32,
+        (y0 as f32 + box_h / 2.0) / h as f32,
+        box_h / h as f32,
+    ))
 }
 
-fn emit_export_status(app: &tauri::AppHandle, status: &ExportStatus) {
-    let _ = app.emit("export_progress", status);
+// Decodes the camera at 2 fps into small YUV frames with the bundled ffmpeg
+// and finds the skin region in each; frames without one are skipped.
+fn scan_skin_regions(app: &tauri::AppHandle, camera_path: &str) -> Result<Vec<CameraFramingSample>, String> {
+    let probe = probe_media(app, camera_path).ok_or("camera_not_found")?;
+    let (Some(width), Some(height)) = (probe.width, probe.height) else {
+        return Err("camera_probe_failed".to_string());
+    };
+    let w = SKIN_SCAN_WIDTH;
+    let h = ((w * height.max(1) as usize / width.max(1) as usize) & !1).max(2);
+    let filter = format!("fps=2,scale={w}:{h},format=yuv444p");
+    let mut child = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args(["-hide_banner", "-i", camera_path, "-an", "-vf", filter.as_str(), "-f", "rawvideo", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("ffmpeg_spawn_failed: {e}"))?;
+    let mut stdout = child.stdout.take().ok_or("skin_scan_failed")?;
+    let mut frame = vec![0u8; w * h * 3];
+    let mut samples: Vec<CameraFramingSample> = Vec::new();
+    let mut index = 0usize;
+    while stdout.read_exact(&mut frame).is_ok() {
+        if let Some((cx, cy, size)) = skin_region_in_frame(&frame, (w, h)) {
+            samples.push(CameraFramingSample {
+                time_s: index as f64 / 2.0,
+                cx: cx.clamp(0.0, 1.0),
+                cy: cy.clamp(0.0, 1.0),
+                size: size.clamp(0.0, 1.0),
+            });
+        }
+        index += 1;
+    }
+    let status = child.wait().map_err(|_| "skin_scan_failed")?;
+    if !status.success() && index == 0 {
+        return Err("skin_scan_failed".to_string());
+    }
+    Ok(samples)
 }
 
-fn ensure_export_worker(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
-    let should_spawn = {
-        let mut guard = state.lock().ok();
-        if let Some(manager) = guard.as_mut() {
-            if manager.running {
-                false
-            } else {
-                manager.running = true;
-                true
+fn load_audio_track(input_path: &str) -> Option<AudioTrack> {
+    let path = audio_track_path(input_path).ok()?;
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn audio_track_is_neutral(track: &AudioTrack) -> bool {
+    track.fade_in_s <= 0.0
+        && track.fade_out_s <= 0.0
+        && track.keyframes.iter().all(|k| (k.gain - 1.0).abs() < 1e-4)
+}
+
+// Gain expression for the volume filter on the recording timeline. Fades run
+// from the first kept clip and into the end of the last one, so they follow
+// the edit rather than the raw recording.
+fn audio_envelope_expr(input_path: &str, duration_s: f64, offset_s: f64) -> Option<String> {
+    let track = load_audio_track(input_path).filter(|t| !audio_track_is_neutral(t))?;
+    let t = format!("(t+{offset_s})");
+    let keyframes = &track.keyframes;
+    let mut expr = match keyframes.first() {
+        None => "1".to_string(),
+        Some(first) => {
+            let last = keyframes.last().unwrap_or(first);
+            let mut expr = format!("{}", last.gain);
+            for pair in keyframes.windows(2).rev() {
+                let (a, b) = (&pair[0], &pair[1]);
+                let span = (b.time_s - a.time_s).max(1e-6);
+                expr = format!(
+                    "if(lt({t},{bt}),{av}+({bv}-{av})*({t}-{at})/{span},{expr})",
+                    bt = b.time_s,
+                    at = a.time_s,
+                    av = a.gain,
+                    bv = b.gain
+                );
             }
-        } else {
-            false
+            format!("if(lt({t},{}),{},{expr})", first.time_s, first.gain)
         }
     };
-    if should_spawn {
-        tauri::async_runtime::spawn(export_worker_async(app, state));
+    let (content_start, content_end) = load_clip_track(input_path)
+        .and_then(|clip| {
+            let start = clip.segments.iter().map(|s| s.start_s).reduce(f64::min)?;
+            let end = clip.segments.iter().map(|s| s.end_s).reduce(f64::max)?;
+            Some((start, end))
+        })
+        .unwrap_or((0.0, duration_s));
+    if track.fade_in_s > 0.0 {
+        expr = format!(
+            "({expr})*clip(({t}-{content_start})/{},0,1)",
+            track.fade_in_s
+        );
     }
+    if track.fade_out_s > 0.0 && content_end > 0.0 {
+        expr = format!(
+            "({expr})*clip(({content_end}-{t})/{},0,1)",
+            track.fade_out_s
+        );
+    }
+    Some(expr)
 }
 
-async fn export_worker_async(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
-    loop {
-        let job = {
-            let mut guard = match state.lock() {
-                Ok(guard) => guard,
-                Err(_) => return,
-            };
-            guard.queue.pop_front()
-        };
-        let Some(job) = job else {
-            if let Ok(mut guard) = state.lock() {
-                guard.running = false;
-            }
-            return;
-        };
-        let mut status = ExportStatus {
-            job_id: job.job_id.clone(),
-            state: "running".to_string(),
-            progress: 0.0,
-            error: None,
-            output_path: Some(job.request.output_path.clone()),
-        };
-        if let Ok(mut guard) = state.lock() {
-            guard.statuses.insert(job.job_id.clone(), status.clone());
-        }
-        emit_export_status(&app, &status);
-        let app_cloned = app.clone();
-        let state_cloned = state.clone();
-        let job_cloned = ExportJob {
-            job_id: job.job_id.clone(),
-            request: job.request.clone(),
-        };
-        let result = tauri::async_runtime::spawn_blocking(move || run_export_job(&app_cloned, &state_cloned, &job_cloned)).await;
-        let ok = match result {
-            Ok(ref r) => r.is_ok(),
-            Err(_) => false,
-        };
-        status.state = if ok { "completed".to_string() } else { "failed".to_string() };
-        status.progress = if ok { 1.0 } else { status.progress };
-        status.error = if ok {
-            None
+fn rnn_denoise_model(app: &tauri::AppHandle, request: &ExportRequest) -> Option<PathBuf> {
+    request
+        .denoise_model
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(|| {
+            app.path()
+                .resolve("ffmpeg/models/std.rnnn", BaseDirectory::Resource)
+                .ok()
+        })
+        .filter(|path| path.is_file())
+}
+
+// "rnn" without a model is refused up front rather than quietly swapped for
+// another denoiser.
+fn check_denoise_model(app: &tauri::AppHandle, request: &ExportRequest) -> Result<(), String> {
+    if request.denoise.as_deref() == Some("rnn") && rnn_denoise_model(app, request).is_none() {
+        return Err("denoise_model_missing".to_string());
+    }
+    Ok(())
+}
+
+fn denoise_filter(app: &tauri::AppHandle, request: &ExportRequest) -> Option<String> {
+    match request.denoise.as_deref()? {
+        "rnn" => rnn_denoise_model(app, request)
+            .map(|model| format!("arnndn=m='{}'", filter_escape_path(&model.to_string_lossy()))),
+        "fft" => Some("afftdn=nr=12:nf=-40:tn=1".to_string()),
+        _ => None,
+    }
+}
+
+// Export audio graph ending in [a]: pre (denoise) and the volume envelope
+// first, on recording time, then the clip cuts. None means the audio can be
+// mapped untouched.
+fn build_export_audio_filter(
+    input_path: &str,
+    segments: Option<&[ClipWindowSegment]>,
+    offset_s: f64,
+    duration_s: f64,
+    pre: Option<&str>,
+) -> Option<String> {
+    let envelope = audio_envelope_expr(input_path, duration_s, offset_s)
+        .map(|expr| format!("volume=volume='{expr}':eval=frame"));
+    let chain = [pre.map(|p| p.to_string()), envelope]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(",");
+    let input = if chain.is_empty() { "[0:a]" } else { "[aenv]" };
+    let cut = segments.and_then(|segments| build_clip_audio_filter(segments, input));
+    match (chain.is_empty(), cut) {
+        (true, cut) => cut,
+        (false, None) => Some(format!("[0:a]{chain}[a]")),
+        (false, Some(cut)) => Some(format!("[0:a]{chain}[aenv];{cut}")),
+    }
+}
+
+// Output time at which recording time time_s lands once the clip track is
+// applied, counting only kept footage before it.
+fn clip_output_offset(track: Option<&ClipTrack>, time_s: f64) -> f64 {
+    let Some(track) = track.filter(|t| !t.segments.is_empty()) else {
+        return time_s;
+    };
+    track
+        .segments
+        .iter()
+        .filter(|seg| seg.start_s < time_s)
+        .map(|seg| (seg.end_s.min(time_s) - seg.start_s).max(0.0) / clip_segment_speed(seg))
+        .sum()
+}
+
+fn music_input_args(music: &MusicOptions, offset_s: f64, music_ms: Option<u64>) -> Vec<String> {
+    let looped = music.loop_music.unwrap_or(true);
+    let mut args = Vec::new();
+    let mut offset_s = offset_s;
+    if looped {
+        args.extend(["-stream_loop".to_string(), "-1".to_string()]);
+        if let Some(ms) = music_ms.filter(|ms| *ms > 0) {
+            offset_s %= ms as f64 / 1000.0;
+        }
+    }
+    if offset_s > 0.0 {
+        args.extend(["-ss".to_string(), format!("{:.3}", offset_s)]);
+    }
+    args.extend(["-i".to_string(), music.path.clone()]);
+    args
+}
+
+// Mixes music input music_input under the recording audio (voice) into
+// [amix]. With ducking the music is compressed whenever the voice is active.
+fn build_music_mix(music: &MusicOptions, music_input: usize, voice: Option<&str>) -> String {
+    let volume = music.volume.unwrap_or(0.25).clamp(0.0, 2.0);
+    let bed = format!(
+        "[{music_input}:a]aformat=sample_rates=48000:channel_layouts=stereo,volume={volume}[music]"
+    );
+    let Some(voice) = voice else {
+        return format!("{bed};[music]anull[amix]");
+    };
+    let voice_fmt = format!("{voice}aformat=sample_rates=48000:channel_layouts=stereo");
+    if music.duck.unwrap_or(true) {
+        format!(
+            "{bed};{voice_fmt},asplit=2[voice][duckkey];[music][duckkey]sidechaincompress=threshold=0.03:ratio=8:attack=20:release=350[ducked];[voice][ducked]amix=inputs=2:duration=first:dropout_transition=0:normalize=0[amix]"
+        )
+    } else {
+        format!(
+            "{bed};{voice_fmt}[voice];[voice][music]amix=inputs=2:duration=first:dropout_transition=0:normalize=0[amix]"
+        )
+    }
+}
+
+// The audio half of an export graph: extra filter_complex stages and the
+// label (or stream specifier) to map.
+struct ExportAudio {
+    graph: Option<String>,
+    map: String,
+}
+
+// Joins the recording audio stages, the music bed and an optional final
+// filter (loudnorm) into one stage list.
+fn export_audio_for(
+    request: &ExportRequest,
+    audio_filter: Option<String>,
+    has_audio: bool,
+    has_camera: bool,
+    finish: Option<&str>,
+) -> ExportAudio {
+    let music = request
+        .music
+        .as_ref()
+        .filter(|music| PathBuf::from(&music.path).is_file());
+    let voice = match audio_filter {
+        Some(_) => Some("[a]"),
+        None if has_audio => Some("[0:a]"),
+        None => None,
+    };
+    let music_mix = music.map(|music| build_music_mix(music, 1 + usize::from(has_camera), voice));
+    let label = match (&music_mix, voice) {
+        (Some(_), _) => "[amix]",
+        (None, Some(voice)) => voice,
+        (None, None) => {
+            return ExportAudio { graph: None, map: "0:a?".to_string() };
+        }
+    };
+    let finish = finish.map(|finish| format!("{label}{finish},aresample=48000[aout]"));
+    let map = if finish.is_some() { "[aout]" } else if label == "[0:a]" { "0:a?" } else { label };
+    let stages = [audio_filter, music_mix, finish]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    ExportAudio {
+        graph: (!stages.is_empty()).then(|| stages.join(";")),
+        map: map.to_string(),
+    }
+}
+
+// Recording audio stages for the whole timeline, as a single-pass export
+// builds them.
+fn full_export_audio_filter(
+    app: &tauri::AppHandle,
+    request: &ExportRequest,
+    has_audio: bool,
+    total_ms: u64,
+) -> Option<String> {
+    if !has_audio {
+        return None;
+    }
+    // Audio is cut (and re-timed) with the same segments as the video so the
+    // two stay in sync instead of the full-length track running on.
+    let segments = load_clip_track(&request.input_path)
+        .map(|track| clip_segments_window(&track, 0.0, f64::MAX));
+    build_export_audio_filter(
+        &request.input_path,
+        segments.as_deref(),
+        0.0,
+        total_ms as f64 / 1000.0,
+        denoise_filter(app, request).as_deref(),
+    )
+}
+
+// Loudness target and true-peak ceiling of the export, if normalizing.
+fn export_loudness(request: &ExportRequest) -> Option<(f32, f32)> {
+    let target = request.loudness_lufs.or(request.profile.loudness_lufs)?;
+    let true_peak = request.profile.true_peak_db.unwrap_or(-1.5);
+    Some((target.clamp(-36.0, -5.0), true_peak.clamp(-9.0, 0.0)))
+}
+
+// First loudnorm pass over the finished audio mix. Returns the second-pass
+// filter with the measured values in linear mode, so every part of a
+// segmented export gets the same gain; falls back to one-pass loudnorm when
+// the measurement fails.
+fn loudnorm_filter(
+    app: &tauri::AppHandle,
+    request: &ExportRequest,
+    full_audio_filter: Option<String>,
+    has_audio: bool,
+    has_camera: bool,
+) -> Option<String> {
+    let (target, true_peak) = export_loudness(request)?;
+    let base = format!("loudnorm=I={target}:TP={true_peak}:LRA=11");
+    let measure = format!("{base}:print_format=json");
+    let audio = export_audio_for(request, full_audio_filter, has_audio, has_camera, Some(&measure));
+    let graph = audio.graph?;
+    let mut args = vec!["-hide_banner".to_string(), "-i".to_string(), request.input_path.clone()];
+    if has_camera {
+        if let Some(path) = request.camera_path.as_ref() {
+            args.extend(["-i".to_string(), path.clone()]);
+        }
+    }
+    if let Some(music) = request.music.as_ref().filter(|m| PathBuf::from(&m.path).is_file()) {
+        args.extend(music_input_args(music, 0.0, None));
+        if !has_audio {
+            args.push("-shortest".to_string());
+        }
+    }
+    args.extend([
+        "-filter_complex".to_string(),
+        graph,
+        "-map".to_string(),
+        audio.map,
+        "-f".to_string(),
+        "null".to_string(),
+        "-".to_string(),
+    ]);
+    let measured = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .ok()
+        .and_then(|output| {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let json = &stderr[stderr.rfind('{')?..=stderr.rfind('}')?];
+            serde_json::from_str::<serde_json::Value>(json).ok()
+        });
+    let field = |key: &str| -> Option<String> {
+        let value = measured.as_ref()?.get(key)?.as_str()?.trim().to_string();
+        value.parse::<f64>().ok().filter(|v| v.is_finite())?;
+        Some(value)
+    };
+    match (
+        field("input_i"),
+        field("input_tp"),
+        field("input_lra"),
+        field("input_thresh"),
+        field("target_offset"),
+    ) {
+        (Some(i), Some(tp), Some(lra), Some(thresh), Some(offset)) => Some(format!(
+            "{base}:measured_I={i}:measured_TP={tp}:measured_LRA={lra}:measured_thresh={thresh}:offset={offset}:linear=true"
+        )),
+        _ => Some(base),
+    }
+}
+
+fn build_clip_select_window(track: &ClipTrack, start_s: f64, end_s: f64) -> Option<ClipSelect> {
+    clip_select_for(&clip_segments_window(track, start_s, end_s))
+}
+
+fn build_camera_enable_window(track: &CameraTrack, start_s: f64, end_s: f64) -> Option<String> {
+    let mut expr = String::new();
+    for seg in track.segments.iter() {
+        if !seg.visible {
+            continue;
+        }
+        let seg_start = seg.start_s.max(start_s);
+        let seg_end = seg.end_s.min(end_s);
+        if seg_end <= seg_start {
+            continue;
+        }
+        let part = format!(
+            "between(t,{},{})",
+            seg_start - start_s,
+            seg_end - start_s
+        );
+        if expr.is_empty() {
+            expr = part;
         } else {
-            match result {
-                Ok(r) => r.err(),
-                Err(_) => Some("export_task_join_failed".to_string()),
+            expr = format!("({})+({})", expr, part);
+        }
+    }
+    if expr.is_empty() {
+        None
+    } else {
+        Some(expr)
+    }
+}
+
+fn builtin_encoder_presets() -> Vec<EncoderPreset> {
+    vec![
+        EncoderPreset {
+            name: "h264".to_string(),
+            codec: "libx264".to_string(),
+            preset: Some("fast".to_string()),
+            tune: None,
+            profile: None,
+            level: None,
+            pix_fmt: Some("yuv420p".to_string()),
+            extra_args: Vec::new(),
+        },
+        EncoderPreset {
+            name: "h265".to_string(),
+            codec: "libx265".to_string(),
+            preset: Some("fast".to_string()),
+            tune: None,
+            profile: None,
+            level: None,
+            pix_fmt: None,
+            extra_args: Vec::new(),
+        },
+        EncoderPreset {
+            name: "prores".to_string(),
+            codec: "prores_ks".to_string(),
+            preset: None,
+            tune: None,
+            // 3 = ProRes 422 HQ.
+            profile: Some("3".to_string()),
+            level: None,
+            pix_fmt: Some("yuv422p10le".to_string()),
+            extra_args: vec!["-vendor".to_string(), "apl0".to_string()],
+        },
+        EncoderPreset {
+            name: "dnxhr".to_string(),
+            codec: "dnxhd".to_string(),
+            preset: None,
+            tune: None,
+            profile: Some("dnxhr_hq".to_string()),
+            level: None,
+            pix_fmt: Some("yuv422p".to_string()),
+            extra_args: Vec::new(),
+        },
+    ]
+}
+
+// Intra-frame editing codecs: quality is set by the profile, not a bitrate,
+// and they go in .mov with uncompressed audio.
+fn is_mezzanine_codec(codec: &str) -> bool {
+    matches!(codec, "prores_ks" | "prores" | "prores_aw" | "dnxhd")
+}
+
+fn is_mezzanine_profile(profile: &ExportProfile) -> bool {
+    is_mezzanine_codec(&resolve_encoder_preset(profile).codec)
+}
+
+fn export_audio_args(profile: &ExportProfile) -> Vec<String> {
+    if is_mezzanine_profile(profile) {
+        vec!["-c:a".to_string(), "pcm_s16le".to_string()]
+    } else {
+        vec!["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "160k".to_string()]
+    }
+}
+
+fn encoder_presets_path() -> PathBuf {
+    app_data_root().join("encoder_presets.json")
+}
+
+fn read_user_encoder_presets() -> Vec<EncoderPreset> {
+    fs::read_to_string(encoder_presets_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+// Builtins first, replaced by name with entries from encoder_presets.json.
+// The file is seeded with the builtins on first start so they can be inspected
+// and edited.
+fn init_encoder_presets() {
+    let path = encoder_presets_path();
+    if !path.exists() {
+        if let Ok(serialized) = serde_json::to_string_pretty(&builtin_encoder_presets()) {
+            let _ = fs::write(&path, serialized);
+        }
+    }
+    let mut presets = builtin_encoder_presets();
+    for user in read_user_encoder_presets() {
+        if let Some(existing) = presets.iter_mut().find(|p| p.name == user.name) {
+            *existing = user;
+        } else {
+            presets.push(user);
+        }
+    }
+    let _ = ENCODER_PRESETS.set(presets);
+}
+
+fn encoder_presets() -> Vec<EncoderPreset> {
+    ENCODER_PRESETS
+        .get()
+        .cloned()
+        .unwrap_or_else(builtin_encoder_presets)
+}
+
+fn resolve_encoder_preset(profile: &ExportProfile) -> EncoderPreset {
+    let name = match profile.encoder_preset.as_deref() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => match profile.format.as_str() {
+            "h265" | "hevc" => "h265".to_string(),
+            "prores" => "prores".to_string(),
+            "dnxhr" | "dnxhd" => "dnxhr".to_string(),
+            _ => "h264".to_string(),
+        },
+    };
+    let presets = encoder_presets();
+    presets
+        .iter()
+        .find(|p| p.name == name)
+        .or_else(|| presets.iter().find(|p| p.name == "h264"))
+        .cloned()
+        .unwrap_or_else(|| builtin_encoder_presets().remove(0))
+}
+
+fn bitrate_args(bitrate_kbps: u32) -> Vec<String> {
+    vec!["-b:v".to_string(), format!("{}k", bitrate_kbps.max(1))]
+}
+
+// Encoder flags for the profile's rate control on the given codec.
+fn rate_control_args(codec: &str, profile: &ExportProfile) -> Vec<String> {
+    let Some(RateControl::Crf { crf }) = profile.rate_control.as_ref() else {
+        return bitrate_args(profile.bitrate_kbps);
+    };
+    let crf = (*crf).min(51).to_string();
+    let args: Vec<&str> = if codec.ends_with("_nvenc") {
+        vec!["-rc", "vbr", "-cq", &crf, "-b:v", "0"]
+    } else if codec.ends_with("_qsv") {
+        vec!["-global_quality", &crf]
+    } else if codec.ends_with("_amf") {
+        vec!["-rc", "cqp", "-qp_i", &crf, "-qp_p", &crf]
+    } else {
+        vec!["-crf", &crf]
+    };
+    args.into_iter().map(|s| s.to_string()).collect()
+}
+
+fn encoder_preset_args(preset: &EncoderPreset, rate_args: Vec<String>) -> Vec<String> {
+    let mut args = vec!["-c:v".to_string(), preset.codec.clone()];
+    let options = [
+        ("-preset", &preset.preset),
+        ("-tune", &preset.tune),
+        ("-profile:v", &preset.profile),
+        ("-level", &preset.level),
+        ("-pix_fmt", &preset.pix_fmt),
+    ];
+    for (flag, value) in options {
+        if let Some(value) = value.as_ref().filter(|v| !v.is_empty()) {
+            args.push(flag.to_string());
+            args.push(value.clone());
+        }
+    }
+    if !is_mezzanine_codec(&preset.codec) {
+        args.extend(rate_args);
+    }
+    args.extend(preset.extra_args.iter().cloned());
+    args
+}
+
+fn encoder_video_args(profile: &ExportProfile) -> Vec<String> {
+    let preset = resolve_encoder_preset(profile);
+    match hardware_encoder_for(profile, &preset) {
+        Some(codec) => {
+            let rate_args = rate_control_args(&codec, profile);
+            hardware_encoder_args(&codec, rate_args)
+        }
+        None => {
+            let rate_args = rate_control_args(&preset.codec, profile);
+            encoder_preset_args(&preset, rate_args)
+        }
+    }
+}
+
+const HARDWARE_ENCODER_VENDORS: &[&str] = &["nvenc", "qsv", "amf"];
+
+// A listed encoder may still have no device behind it, so each candidate
+// encodes a few frames before it is offered.
+fn detect_hardware_encoders(app: &tauri::AppHandle) -> Vec<String> {
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let listed = probe_capabilities(app).encoders;
+    let mut found = Vec::new();
+    for vendor in HARDWARE_ENCODER_VENDORS {
+        for codec in ["h264", "hevc"] {
+            let name = format!("{codec}_{vendor}");
+            if !listed.contains(&name) {
+                continue;
             }
-        };
-        if let Ok(mut guard) = state.lock() {
+            let ok = new_cmd(&bin)
+                .args([
+                    "-hide_banner",
+                    "-f",
+                    "lavfi",
+                    "-i",
+                    "color=c=black:s=256x256:r=30:d=0.2",
+                    "-c:v",
+                    name.as_str(),
+                    "-f",
+                    "null",
+                    "-",
+                ])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false);
+            if ok {
+                found.push(name);
+            }
+        }
+    }
+    found
+}
+
+fn hardware_encoder_for(profile: &ExportProfile, preset: &EncoderPreset) -> Option<String> {
+    let codec = match preset.codec.as_str() {
+        "libx264" => "h264",
+        "libx265" => "hevc",
+        _ => return None,
+    };
+    let preference = profile.encoder.as_deref().unwrap_or("software");
+    if preference == "software" {
+        return None;
+    }
+    HARDWARE_ENCODERS
+        .get()?
+        .iter()
+        .filter(|name| name.starts_with(&format!("{codec}_")))
+        .find(|name| preference == "auto" || name.ends_with(&format!("_{preference}")))
+        .cloned()
+}
+
+fn hardware_encoder_args(codec: &str, rate_args: Vec<String>) -> Vec<String> {
+    let mut args = vec!["-c:v".to_string(), codec.to_string()];
+    let quality: &[&str] = if codec.ends_with("_nvenc") {
+        &["-preset", "p5"]
+    } else if codec.ends_with("_qsv") {
+        &["-preset", "medium"]
+    } else {
+        &["-quality", "balanced"]
+    };
+    args.extend(quality.iter().map(|s| s.to_string()));
+    args.extend(["-pix_fmt".to_string(), "yuv420p".to_string()]);
+    args.extend(rate_args);
+    args
+}
+
+#[tauri::command]
+fn get_hardware_encoders() -> Vec<String> {
+    HARDWARE_ENCODERS.get().cloned().unwrap_or_default()
+}
+
+const EXPORT_AUDIO_KBPS: u64 = 160;
+
+// Turns a target-size rate control into the bitrate that fills it over the
+// edited duration, less audio and ~3% container overhead.
+fn target_size_profile(profile: &ExportProfile, duration_ms: u64) -> Option<ExportProfile> {
+    let Some(RateControl::TargetSize { size_mb }) = profile.rate_control.as_ref() else {
+        return None;
+    };
+    let total_kbps = (size_mb * 8_000.0 * 1000.0 / duration_ms.max(1) as f64 / 1.03) as u64;
+    Some(ExportProfile {
+        bitrate_kbps: total_kbps.saturating_sub(EXPORT_AUDIO_KBPS).max(100) as u32,
+        rate_control: Some(RateControl::Bitrate),
+        ..profile.clone()
+    })
+}
+
+// The request with every target-size profile, including those of merged
+// outputs, resolved to a bitrate. None when there is nothing to resolve.
+fn resolve_target_sizes(app: &tauri::AppHandle, request: &ExportRequest) -> Option<ExportRequest> {
+    let targets_size = |profile: &ExportProfile| {
+        matches!(profile.rate_control, Some(RateControl::TargetSize { .. }))
+    };
+    if !targets_size(&request.profile) && !request.extra_outputs.iter().any(|o| targets_size(&o.profile)) {
+        return None;
+    }
+    let duration_ms = export_duration_ms(app, request);
+    let mut resolved = request.clone();
+    if let Some(profile) = target_size_profile(&request.profile, duration_ms) {
+        resolved.profile = profile;
+    }
+    for output in resolved.extra_outputs.iter_mut() {
+        if let Some(profile) = target_size_profile(&output.profile, duration_ms) {
+            output.profile = profile;
+        }
+    }
+    Some(resolved)
+}
+
+// Retries a failed hardware export once with the software encoder, since
+// drivers can fail mid-job (session limits, device loss).
+fn run_export_job_with_fallback(
+    app: &tauri::AppHandle,
+    state: &Arc<Mutex<ExportManager>>,
+    job: &ExportJob,
+) -> Result<(), String> {
+    let resolved;
+    let job = match resolve_target_sizes(app, &job.request) {
+        Some(request) => {
+            resolved = ExportJob {
+                request,
+                ..job.clone()
+            };
+            &resolved
+        }
+        None => job,
+    };
+    let result = run_export_job(app, state, job);
+    let hardware = export_outputs(&job.request).iter().any(|request| {
+        hardware_encoder_for(&request.profile, &resolve_encoder_preset(&request.profile)).is_some()
+    });
+    match result {
+        Err(err) if hardware && err != "export_cancelled" => {
+            let mut request = job.request.clone();
+            request.profile.encoder = Some("software".to_string());
+            for output in request.extra_outputs.iter_mut() {
+                output.profile.encoder = Some("software".to_string());
+            }
+            run_export_job(
+                app,
+                state,
+                &ExportJob {
+                    request,
+                    ..job.clone()
+                },
+            )
+        }
+        other => other,
+    }
+}
+
+fn emit_export_status(app: &tauri::AppHandle, status: &ExportStatus) {
+    let _ = app.emit("export_progress", status);
+}
+
+fn ensure_export_worker(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
+    let should_spawn = {
+        let mut guard = state.lock().ok();
+        if let Some(manager) = guard.as_mut() {
+            if manager.running {
+                false
+            } else {
+                manager.running = true;
+                true
+            }
+        } else {
+            false
+        }
+    };
+    if should_spawn {
+        tauri::async_runtime::spawn(export_worker_async(app, state));
+    }
+}
+
+// Queues the job behind everything of equal or higher priority.
+fn enqueue_export_job(manager: &mut ExportManager, job: ExportJob) {
+    let index = manager
+        .queue
+        .iter()
+        .position(|queued| queued.priority < job.priority)
+        .unwrap_or(manager.queue.len());
+    manager.queue.insert(index, job);
+}
+
+fn export_queue_path() -> PathBuf {
+    app_data_root().join("export_queue.json")
+}
+
+// Writes the running and queued jobs to export_queue.json so they survive a
+// restart. Cancelled jobs are left out.
+fn persist_export_queue(manager: &ExportManager) {
+    let path = export_queue_path();
+    let jobs: Vec<&ExportJob> = manager
+        .current
+        .iter()
+        .chain(manager.queue.iter())
+        .filter(|job| !manager.cancellations.contains_key(&job.job_id))
+        .collect();
+    if jobs.is_empty() {
+        let _ = fs::remove_file(path);
+        return;
+    }
+    if let Ok(serialized) = serde_json::to_string_pretty(&jobs) {
+        let _ = fs::write(path, serialized);
+    }
+}
+
+// Re-queues jobs left over from the previous run. A job that was running is
+// started again from the beginning.
+fn restore_export_queue(app: &tauri::AppHandle, state: &Arc<Mutex<ExportManager>>) {
+    let Some(jobs) = fs::read_to_string(export_queue_path())
+        .ok()
+        .and_then(|data| serde_json::from_str::<Vec<ExportJob>>(&data).ok())
+    else {
+        return;
+    };
+    let jobs: Vec<ExportJob> = jobs
+        .into_iter()
+        .filter(|job| PathBuf::from(&job.request.input_path).is_file())
+        .collect();
+    let mut statuses = Vec::new();
+    if let Ok(mut guard) = state.lock() {
+        for job in jobs {
+            let status = ExportStatus {
+                job_id: job.job_id.clone(),
+                state: "queued".to_string(),
+                progress: 0.0,
+                error: None,
+                output_path: Some(job.request.output_path.clone()),
+                group_id: job.group_id.clone(),
+                fps: None,
+                speed: None,
+                eta_s: None,
+                poster_path: None,
+            };
             guard.statuses.insert(job.job_id.clone(), status.clone());
-            guard.cancellations.remove(&job.job_id);
+            guard.queue.push_back(job);
+            statuses.push(status);
         }
-        emit_export_status(&app, &status);
+        persist_export_queue(&guard);
+    }
+    if statuses.is_empty() {
+        return;
+    }
+    for status in statuses.iter() {
+        emit_export_status(app, status);
     }
+    ensure_export_worker(app.clone(), Arc::clone(state));
 }
 
-fn export_worker(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
+async fn export_worker_async(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
     loop {
         let job = {
             let mut guard = match state.lock() {
                 Ok(guard) => guard,
                 Err(_) => return,
             };
-            guard.queue.pop_front()
+            let job = guard.queue.pop_front();
+            guard.current = job.clone();
+            persist_export_queue(&guard);
+            job
         };
         let Some(job) = job else {
             if let Ok(mut guard) = state.lock() {
@@ -1064,8 +4351,543 @@ fn export_worker(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
             progress: 0.0,
             error: None,
             output_path: Some(job.request.output_path.clone()),
+            group_id: job.group_id.clone(),
+            fps: None,
+            speed: None,
+            eta_s: None,
+            poster_path: None,
         };
-        if let Ok(mut guard) = state.lock() {
+        if export_cancelled(&state, &job.job_id) {
+            status.state = "cancelled".to_string();
+            finish_export_job(&app, &state, &job, status);
+            continue;
+        }
+        if let Ok(mut guard) = state.lock() {
+            guard.statuses.insert(job.job_id.clone(), status.clone());
+            guard.started_at_ms.insert(job.job_id.clone(), unix_now_ms());
+        }
+        emit_export_status(&app, &status);
+        let app_cloned = app.clone();
+        let state_cloned = state.clone();
+        let job_cloned = job.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            run_export_job_with_fallback(&app_cloned, &state_cloned, &job_cloned)
+                .and_then(|_| {
+                    if job_cloned.request.image_sequence.is_some() {
+                        return Ok(());
+                    }
+                    for request in export_outputs(&job_cloned.request) {
+                        if load_settings().features.verify_exports {
+                            verify_export_output(&app_cloned, &request)?;
+                        }
+                        write_soft_subtitles(&app_cloned, &request)?;
+                        write_export_metadata(&app_cloned, &request)?;
+                    }
+                    Ok(())
+                })
+                .map(|_| {
+                    job_cloned
+                        .request
+                        .image_sequence
+                        .is_none()
+                        .then(|| write_export_poster(&app_cloned, &job_cloned.request))
+                        .flatten()
+                })
+        })
+        .await;
+        let ok = match result {
+            Ok(ref r) => r.is_ok(),
+            Err(_) => false,
+        };
+        if let Ok(Ok(poster_path)) = result.as_ref() {
+            status.poster_path = poster_path.clone();
+        }
+        status.state = if ok { "completed".to_string() } else { "failed".to_string() };
+        status.progress = if ok { 1.0 } else { status.progress };
+        status.error = if ok {
+            None
+        } else {
+            match result {
+                Ok(r) => r.err(),
+                Err(_) => Some("export_task_join_failed".to_string()),
+            }
+        };
+        if status.error.as_deref() == Some("export_cancelled") {
+            // The partial files are unplayable; don't leave them for the user.
+            for request in export_outputs(&job.request) {
+                let _ = fs::remove_file(&request.output_path);
+            }
+            if job.request.image_sequence.is_some() {
+                let dir = PathBuf::from(&job.request.output_path);
+                clear_sequence_frames(&dir);
+                let _ = fs::remove_dir(&dir);
+            }
+            status.state = "cancelled".to_string();
+            status.error = None;
+        }
+        finish_export_job(&app, &state, &job, status);
+    }
+}
+
+fn export_cancelled(state: &Arc<Mutex<ExportManager>>, job_id: &str) -> bool {
+    state
+        .lock()
+        .map(|guard| guard.cancellations.get(job_id).copied().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+// Records the final status of a job, drops its bookkeeping and appends it to
+// the history log.
+fn finish_export_job(
+    app: &tauri::AppHandle,
+    state: &Arc<Mutex<ExportManager>>,
+    job: &ExportJob,
+    status: ExportStatus,
+) {
+    let mut started_at_ms = None;
+    if let Ok(mut guard) = state.lock() {
+        guard.statuses.insert(job.job_id.clone(), status.clone());
+        guard.cancellations.remove(&job.job_id);
+        guard.children.remove(&job.job_id);
+        started_at_ms = guard.started_at_ms.remove(&job.job_id);
+        guard.current = None;
+        persist_export_queue(&guard);
+    }
+    emit_export_status(app, &status);
+    let finished_at_ms = unix_now_ms();
+    let duration_ms = (status.state == "completed")
+        .then(|| get_media_duration_ms(app, &job.request.output_path))
+        .flatten();
+    append_export_history(ExportHistoryEntry {
+        job_id: job.job_id.clone(),
+        group_id: job.group_id.clone(),
+        input_path: job.request.input_path.clone(),
+        output_path: job.request.output_path.clone(),
+        state: status.state,
+        error: status.error,
+        started_at_ms,
+        finished_at_ms: Some(finished_at_ms),
+        elapsed_ms: started_at_ms.map(|start| finished_at_ms.saturating_sub(start)),
+        duration_ms,
+    });
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ExportHistoryEntry {
+    job_id: String,
+    #[serde(default)]
+    group_id: Option<String>,
+    input_path: String,
+    output_path: String,
+    state: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    started_at_ms: Option<u64>,
+    #[serde(default)]
+    finished_at_ms: Option<u64>,
+    // Wall-clock encoding time.
+    #[serde(default)]
+    elapsed_ms: Option<u64>,
+    // Length of the exported video.
+    #[serde(default)]
+    duration_ms: Option<u64>,
+}
+
+const EXPORT_HISTORY_MAX: usize = 500;
+
+// export_logs/<job_id>.log; None for ids that aren't plain file names.
+fn export_log_path(job_id: &str) -> Option<PathBuf> {
+    let plain = !job_id.is_empty()
+        && job_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    plain.then(|| app_data_root().join("export_logs").join(format!("{job_id}.log")))
+}
+
+// Appends one ffmpeg run (command line and full stderr) to the job's log.
+fn append_export_log(job_id: &str, command_line: &str, stderr: &str) {
+    let Some(path) = export_log_path(job_id).filter(|_| load_settings().features.keep_export_logs) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "$ ffmpeg {command_line}\n{stderr}");
+    }
+}
+
+fn export_history_path() -> PathBuf {
+    app_data_root().join("export_history.jsonl")
+}
+
+fn read_export_history() -> Vec<ExportHistoryEntry> {
+    fs::read_to_string(export_history_path())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+// One JSON line per finished job, oldest first, capped at the
+// export_history_max setting.
+fn append_export_history(entry: ExportHistoryEntry) {
+    let mut entries = read_export_history();
+    entries.push(entry);
+    let skip = entries.len().saturating_sub(load_settings().defaults.export_history_max);
+    // Logs go with the history entries that fall off the end.
+    for dropped in entries.iter().take(skip) {
+        if let Some(path) = export_log_path(&dropped.job_id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+    let content: String = entries
+        .iter()
+        .skip(skip)
+        .filter_map(|entry| serde_json::to_string(entry).ok())
+        .map(|line| line + "\n")
+        .collect();
+    let _ = fs::write(export_history_path(), content);
+}
+
+// Registers a job's ffmpeg process so cancel_export can kill it right away.
+fn track_export_child(
+    state: &Arc<Mutex<ExportManager>>,
+    job_id: &str,
+    child: Child,
+) -> Arc<Mutex<Child>> {
+    let child = Arc::new(Mutex::new(child));
+    if let Ok(mut guard) = state.lock() {
+        guard.children.entry(job_id.to_string()).or_default().push(Arc::clone(&child));
+    }
+    child
+}
+
+// ffmpeg can exit 0 on a truncated file (full disk, a dying encoder), so the
+// output is probed before the job counts as done: non-empty, has video (and
+// audio when the source had it), and about as long as the edit.
+fn verify_export_output(app: &tauri::AppHandle, request: &ExportRequest) -> Result<(), String> {
+    let size = fs::metadata(&request.output_path).map(|m| m.len()).unwrap_or(0);
+    if size == 0 {
+        return Err("export_verify_failed: output is empty".to_string());
+    }
+    let probe = probe_media(app, &request.output_path)
+        .ok_or("export_verify_failed: output can't be read")?;
+    if !probe.has_video {
+        return Err("export_verify_failed: no video stream".to_string());
+    }
+    let source_has_audio = probe_media(app, &request.input_path).is_some_and(|p| p.has_audio);
+    if source_has_audio && !probe.has_audio {
+        return Err("export_verify_failed: no audio stream".to_string());
+    }
+    let expected_ms = export_duration_ms(app, request);
+    let (Some(actual_ms), true) = (probe.duration_ms, expected_ms > 0) else {
+        return Ok(());
+    };
+    // Stream copies start on the previous keyframe, so allow extra length.
+    let tolerance_ms = (expected_ms / 50).max(1500);
+    if actual_ms + tolerance_ms < expected_ms || actual_ms > expected_ms + tolerance_ms.max(10_000) {
+        return Err(format!(
+            "export_verify_failed: duration {:.1}s, expected {:.1}s",
+            actual_ms as f64 / 1000.0,
+            expected_ms as f64 / 1000.0
+        ));
+    }
+    Ok(())
+}
+
+// Renders the poster frame from the finished output, next to it with the same
+// stem. Best effort: a failure leaves the export itself successful.
+fn write_export_poster(app: &tauri::AppHandle, request: &ExportRequest) -> Option<String> {
+    let options = request.poster.as_ref()?;
+    let ext = match options.format.as_deref() {
+        Some("png") => "png",
+        _ => "jpg",
+    };
+    let poster = PathBuf::from(&request.output_path).with_extension(ext);
+    let _ = fs::remove_file(&poster);
+    let quality: &[&str] = if ext == "jpg" { &["-q:v", "2"] } else { &[] };
+    let mut args = vec!["-y".to_string()];
+    match options.time_s.filter(|t| t.is_finite() && *t > 0.0) {
+        Some(time_s) => {
+            args.extend(["-ss".to_string(), format!("{time_s:.3}")]);
+            args.extend(["-i".to_string(), request.output_path.clone()]);
+        }
+        None => {
+            // blackframe at amount=0 tags every frame with its black
+            // percentage; keep the first that is mostly not black.
+            args.extend(["-t".to_string(), "30".to_string()]);
+            args.extend(["-i".to_string(), request.output_path.clone()]);
+            args.extend([
+                "-vf".to_string(),
+                "blackframe=amount=0:threshold=32,metadata=mode=select:key=lavfi.blackframe.pblack:value=90:function=less"
+                    .to_string(),
+            ]);
+        }
+    }
+    args.extend(["-frames:v".to_string(), "1".to_string()]);
+    args.extend(quality.iter().map(|s| s.to_string()));
+    args.push(poster.to_string_lossy().to_string());
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let rendered = new_cmd(&bin)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+        && poster.exists();
+    if !rendered && options.time_s.is_none() {
+        // All black in the first 30s: fall back to the first frame.
+        let _ = new_cmd(&bin)
+            .args(["-y", "-i", &request.output_path, "-frames:v", "1"])
+            .args(quality)
+            .arg(&poster)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    poster.exists().then(|| poster.to_string_lossy().to_string())
+}
+
+// Runs after the video is written: adds the session subtitles as a soft
+// track (remuxed in place) or as an .srt beside the output.
+fn write_soft_subtitles(app: &tauri::AppHandle, request: &ExportRequest) -> Result<(), String> {
+    let Some(mode) = request.soft_subtitles.as_deref() else {
+        return Ok(());
+    };
+    let (range_start, range_end) = export_output_range(request);
+    let cues: Vec<SubtitleCue> = output_subtitle_cues(&request.input_path, (0.0, f64::MAX))
+        .into_iter()
+        .filter(|cue| cue.end_s > range_start && cue.start_s < range_end)
+        .map(|cue| SubtitleCue {
+            start_s: (cue.start_s - range_start).max(0.0),
+            end_s: cue.end_s.min(range_end) - range_start,
+            text: cue.text,
+        })
+        .collect();
+    if cues.is_empty() {
+        return Ok(());
+    }
+    let output = PathBuf::from(&request.output_path);
+    let sidecar = output.with_extension("srt");
+    fs::write(&sidecar, write_srt(&cues)).map_err(|_| "subtitle_write_failed")?;
+    if mode != "embed" {
+        return Ok(());
+    }
+    let ext = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("mp4")
+        .to_ascii_lowercase();
+    let codec = match ext.as_str() {
+        "webm" => "webvtt",
+        "mkv" => "srt",
+        _ => "mov_text",
+    };
+    let muxed = output.with_extension(format!("subs.{ext}"));
+    let status = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args(["-y", "-hide_banner", "-i"])
+        .arg(&output)
+        .arg("-i")
+        .arg(&sidecar)
+        .args(["-map", "0", "-map", "1:0", "-c", "copy", "-c:s", codec])
+        .arg(&muxed)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("ffmpeg_spawn_failed: {e}"))?;
+    let _ = fs::remove_file(&sidecar);
+    if !status.success() {
+        let _ = fs::remove_file(&muxed);
+        return Err("subtitle_mux_failed".to_string());
+    }
+    fs::rename(&muxed, &output).map_err(|_| "subtitle_mux_failed".to_string())
+}
+
+fn ffmetadata_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Chapters on the output timeline. A chapter inside a cut moves to the next
+// kept frame, and the first one starts at 0 as YouTube requires.
+fn output_chapters(input_path: &str, (offset_s, total_s): (f64, f64)) -> Vec<(f64, String)> {
+    let Some(track) = load_chapter_track(input_path) else {
+        return Vec::new();
+    };
+    let segments = load_clip_track(input_path)
+        .filter(|track| !track.segments.is_empty())
+        .map(|track| clip_segments_window(&track, 0.0, f64::MAX));
+    let mut chapters: Vec<(f64, String)> = track
+        .chapters
+        .into_iter()
+        .filter_map(|chapter| {
+            let (start, _) = output_span_for(segments.as_deref(), (chapter.time_s, f64::MAX))?;
+            let start = start - offset_s;
+            (start < total_s).then_some((start.max(0.0), chapter.name))
+        })
+        .collect();
+    chapters.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    chapters.dedup_by(|b, a| (b.0 - a.0).abs() < 0.001);
+    if let Some(first) = chapters.first_mut() {
+        first.0 = 0.0;
+    }
+    chapters
+}
+
+fn export_metadata_tags(metadata: Option<&ExportMetadata>) -> Vec<(&'static str, String)> {
+    let Some(metadata) = metadata else {
+        return Vec::new();
+    };
+    let creation_time = metadata.creation_time.as_deref().map(|value| {
+        if value == "now" {
+            let secs = unix_now_ms() / 1000;
+            format_utc_timestamp(secs)
+        } else {
+            value.to_string()
+        }
+    });
+    [
+        ("title", metadata.title.clone()),
+        ("comment", metadata.comment.clone()),
+        // mp4 reads artist, mov reads author.
+        ("artist", metadata.author.clone()),
+        ("author", metadata.author.clone()),
+        ("creation_time", creation_time),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.filter(|v| !v.trim().is_empty()).map(|v| (key, v)))
+    .collect()
+}
+
+// Seconds since the epoch as "YYYY-MM-DDTHH:MM:SSZ" (civil-from-days).
+fn format_utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+// Runs after the video is written: remuxes chapters from chapters_track.json
+// and the request's metadata tags into the output in place.
+fn write_export_metadata(app: &tauri::AppHandle, request: &ExportRequest) -> Result<(), String> {
+    let tags = export_metadata_tags(request.metadata.as_ref());
+    let has_chapters =
+        load_chapter_track(&request.input_path).is_some_and(|track| !track.chapters.is_empty());
+    if tags.is_empty() && !has_chapters {
+        return Ok(());
+    }
+    let output = PathBuf::from(&request.output_path);
+    let total_ms = get_media_duration_ms(app, &request.output_path).unwrap_or(0);
+    let offset_s = export_output_range(request).0;
+    let chapters = if has_chapters {
+        output_chapters(&request.input_path, (offset_s, total_ms as f64 / 1000.0))
+    } else {
+        Vec::new()
+    };
+    if tags.is_empty() && chapters.is_empty() {
+        return Ok(());
+    }
+    let mut metadata = String::from(";FFMETADATA1\n");
+    for (key, value) in tags.iter() {
+        metadata.push_str(&format!("{key}={}\n", ffmetadata_escape(value)));
+    }
+    for (idx, (start_s, name)) in chapters.iter().enumerate() {
+        let end_ms = chapters
+            .get(idx + 1)
+            .map(|next| (next.0 * 1000.0) as u64)
+            .unwrap_or(total_ms);
+        metadata.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            (start_s * 1000.0) as u64,
+            end_ms,
+            ffmetadata_escape(name)
+        ));
+    }
+    let metadata_path = output.with_extension("metadata.txt");
+    fs::write(&metadata_path, metadata).map_err(|_| "metadata_write_failed")?;
+    let ext = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("mp4")
+        .to_ascii_lowercase();
+    let muxed = output.with_extension(format!("meta.{ext}"));
+    let status = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args(["-y", "-hide_banner", "-i"])
+        .arg(&output)
+        .args(["-f", "ffmetadata", "-i"])
+        .arg(&metadata_path)
+        .args(["-map", "0", "-map_metadata", "1", "-map_chapters", "1", "-c", "copy"])
+        .arg(&muxed)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("ffmpeg_spawn_failed: {e}"))?;
+    let _ = fs::remove_file(&metadata_path);
+    if !status.success() {
+        let _ = fs::remove_file(&muxed);
+        return Err("metadata_mux_failed".to_string());
+    }
+    fs::rename(&muxed, &output).map_err(|_| "metadata_mux_failed".to_string())
+}
+
+fn export_worker(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
+    loop {
+        let job = {
+            let mut guard = match state.lock() {
+                Ok(guard) => guard,
+                Err(_) => return,
+            };
+            guard.queue.pop_front()
+        };
+        let Some(job) = job else {
+            if let Ok(mut guard) = state.lock() {
+                guard.running = false;
+            }
+            return;
+        };
+        let mut status = ExportStatus {
+            job_id: job.job_id.clone(),
+            state: "running".to_string(),
+            progress: 0.0,
+            error: None,
+            output_path: Some(job.request.output_path.clone()),
+            group_id: job.group_id.clone(),
+            fps: None,
+            speed: None,
+            eta_s: None,
+            poster_path: None,
+        };
+        if let Ok(mut guard) = state.lock() {
             guard.statuses.insert(job.job_id.clone(), status.clone());
         }
         emit_export_status(&app, &status);
@@ -1087,16 +4909,18 @@ fn export_worker(app: tauri::AppHandle, state: Arc<Mutex<ExportManager>>) {
 
 fn run_ffmpeg_with_progress<F, G>(
     app: &tauri::AppHandle,
+    state: &Arc<Mutex<ExportManager>>,
+    job_id: &str,
     args: Vec<String>,
-    duration_ms: u64,
     progress_cb: F,
     cancel_check: G,
 ) -> Result<(), String>
 where
-    F: Fn(f32) + Send + Sync,
+    F: Fn(&FfmpegProgress) + Send + Sync,
     G: Fn() -> bool + Send + Sync,
 {
     let bin = ffmpeg_binary_with_app_handle(app);
+    let command_line = args.join(" ");
     let mut child = new_cmd(&bin)
         .args(args)
         .stdout(Stdio::piped())
@@ -1111,6 +4935,7 @@ where
         .stderr
         .take()
         .ok_or("export_stderr_unavailable".to_string())?;
+    let child = track_export_child(state, job_id, child);
     let stderr_handle = thread::spawn(move || {
         let mut reader = BufReader::new(stderr);
         let mut buffer = String::new();
@@ -1119,12 +4944,13 @@ where
     });
     let mut reader = BufReader::new(stdout);
     let mut line = String::new();
+    let mut progress = FfmpegProgress::default();
     loop {
         if cancel_check() {
-            let _ = child.kill();
-            let _ = child.wait();
-            let _ = stderr_handle.join();
-            return Err("export_cancelled".to_string());
+            if let Ok(mut child) = child.lock() {
+                let _ = child.kill();
+            }
+            break;
         }
         line.clear();
         let bytes = match reader.read_line(&mut line) {
@@ -1134,23 +4960,24 @@ where
         if bytes == 0 {
             break;
         }
-        let trimmed = line.trim();
-        if let Some(value) = trimmed.strip_prefix("out_time_ms=") {
-            if let Ok(out_time_ms) = value.parse::<u64>() {
-                let progress = if duration_ms == 0 {
-                    0.0
-                } else {
-                    (out_time_ms as f64 / duration_ms as f64).min(1.0) as f32
-                };
-                progress_cb(progress);
-            }
+        if progress.update(&line) {
+            progress_cb(&progress);
         }
-        if trimmed == "progress=end" {
+        if line.trim() == "progress=end" {
             break;
         }
     }
-    let status = child.wait().map_err(|_| "export_wait_failed".to_string())?;
+    // A kill from cancel_export ends stdout, so this doesn't block long.
+    let status = child
+        .lock()
+        .map_err(|_| "export_wait_failed".to_string())?
+        .wait()
+        .map_err(|_| "export_wait_failed".to_string())?;
     let stderr_output = stderr_handle.join().unwrap_or_default();
+    append_export_log(job_id, &command_line, &stderr_output);
+    if cancel_check() {
+        return Err("export_cancelled".to_string());
+    }
     if status.success() {
         Ok(())
     } else if stderr_output.trim().is_empty() {
@@ -1169,15 +4996,50 @@ where
     }
 }
 
+// Completed parts of a segmented export, next to the output. key ties them to
+// the exact request and recording, so a changed edit starts over.
+#[derive(Serialize, Deserialize, Default)]
+struct SegmentManifest {
+    key: String,
+    done: Vec<usize>,
+}
+
+fn load_segment_manifest(path: &Path, request: &ExportRequest, segment_paths: &[PathBuf]) -> SegmentManifest {
+    let recording_len = fs::metadata(&request.input_path).map(|m| m.len()).unwrap_or(0);
+    let extra = format!(
+        "{}|{}|{}",
+        serde_json::to_string(request).unwrap_or_default(),
+        recording_len,
+        segment_paths.len()
+    );
+    let key = edit_state_hash(&request.edit_state, &request.input_path, &extra);
+    let mut manifest = fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<SegmentManifest>(&data).ok())
+        .filter(|manifest| manifest.key == key)
+        .unwrap_or(SegmentManifest { key, done: Vec::new() });
+    manifest
+        .done
+        .retain(|idx| segment_paths.get(*idx).is_some_and(|path| path.is_file()));
+    manifest
+}
+
+// Renders 5-minute parts (two at a time) and concatenates them. Parts are
+// resumable, see SegmentManifest.
 fn run_segmented_export(
     app: &tauri::AppHandle,
     state: &Arc<Mutex<ExportManager>>,
     job: &ExportJob,
     total_ms: u64,
+    (window_start_ms, window_end_ms): (u64, u64),
 ) -> Result<(), String> {
     let segment_ms = 300_000u64;
     let max_parallel = 2usize;
-    let segment_count = ((total_ms + segment_ms - 1) / segment_ms).max(1) as usize;
+    let window_ms = window_end_ms.saturating_sub(window_start_ms);
+    if window_ms == 0 {
+        return Err("export_empty".to_string());
+    }
+    let segment_count = window_ms.div_ceil(segment_ms).max(1) as usize;
     let output_path = PathBuf::from(&job.request.output_path);
     let output_dir = output_path
         .parent()
@@ -1204,7 +5066,33 @@ fn run_segmented_export(
     let has_camera = camera_path
         .map(|path| PathBuf::from(path).exists())
         .unwrap_or(false);
-    let progress_vec = Arc::new(Mutex::new(vec![0.0f32; segment_count]));
+    let probe = probe_media(app, &job.request.input_path).unwrap_or_default();
+    let has_audio = probe.has_audio;
+    let music = job
+        .request
+        .music
+        .clone()
+        .filter(|music| PathBuf::from(&music.path).is_file());
+    let music_ms = music
+        .as_ref()
+        .and_then(|music| get_media_duration_ms(app, &music.path));
+    let denoise = denoise_filter(app, &job.request);
+    let loudnorm = export_loudness(&job.request).and_then(|_| {
+        let full = full_export_audio_filter(app, &job.request, has_audio, total_ms);
+        loudnorm_filter(app, &job.request, full, has_audio, has_camera)
+    });
+    let manifest_path = output_dir.join(format!("{stem}_parts.json"));
+    let manifest = load_segment_manifest(&manifest_path, &job.request, &segment_paths);
+    let _ = fs::write(&manifest_path, serde_json::to_string(&manifest).unwrap_or_default());
+    let mut initial_progress = vec![0.0f32; segment_count];
+    for idx in manifest.done.iter() {
+        initial_progress[*idx] = 1.0;
+    }
+    let manifest = Arc::new(Mutex::new(manifest));
+    let progress_vec = Arc::new(Mutex::new(initial_progress));
+    // Latest -progress block per segment; parts encode in parallel, so the
+    // job's speed is their sum.
+    let rates_vec = Arc::new(Mutex::new(vec![None::<FfmpegProgress>; segment_count]));
     let next_index = Arc::new(AtomicUsize::new(0));
     let abort_flag = Arc::new(AtomicBool::new(false));
     let error_ref = Arc::new(Mutex::new(None::<String>));
@@ -1215,19 +5103,30 @@ fn run_segmented_export(
         let app_handle = app.clone();
         let state_handle = Arc::clone(state);
         let progress_handle = Arc::clone(&progress_vec);
+        let rates_handle = Arc::clone(&rates_vec);
+        let manifest_handle = Arc::clone(&manifest);
+        let manifest_path = manifest_path.clone();
         let next_handle = Arc::clone(&next_index);
         let abort_handle = Arc::clone(&abort_flag);
         let error_handle = Arc::clone(&error_ref);
         let clip_track = clip_track.clone();
         let camera_track = camera_track.clone();
+        let probe = probe.clone();
         let input_path = job.request.input_path.clone();
         let profile = job.request.profile.clone();
         let edit_state = job.request.edit_state.clone();
         let camera_path = camera_path.map(|p| p.to_string());
+        let music = music.clone();
+        let subtitles = job.request.subtitles.clone();
+        let timecode = job.request.timecode.clone();
+        let denoise = denoise.clone();
+        let loudnorm = loudnorm.clone();
+        let request = job.request.clone();
         let segments = segment_paths.clone();
         let output_dir = output_dir.clone();
         let job_id = job_id.clone();
         let output_path_str = output_path_str.clone();
+        let group_id = job.group_id.clone();
         let handle = thread::spawn(move || {
             loop {
                 if abort_handle.load(Ordering::Relaxed) {
@@ -1237,21 +5136,84 @@ fn run_segmented_export(
                 if idx >= segment_count {
                     break;
                 }
-                let start_ms = idx as u64 * segment_ms;
-                let end_ms = (start_ms + segment_ms).min(total_ms);
+                if manifest_handle.lock().is_ok_and(|m| m.done.contains(&idx)) {
+                    // Rendered by an earlier, interrupted run.
+                    continue;
+                }
+                let start_ms = window_start_ms + idx as u64 * segment_ms;
+                let end_ms = (start_ms + segment_ms).min(window_end_ms);
                 if end_ms <= start_ms {
                     break;
                 }
                 let duration_ms = end_ms - start_ms;
                 let start_s = start_ms as f64 / 1000.0;
                 let end_s = end_ms as f64 / 1000.0;
-                let clip_select =
-                    clip_track.as_ref().and_then(|t| build_clip_select_window(t, start_s, end_s));
+                let window_segments = clip_track
+                    .as_ref()
+                    .filter(|t| !t.segments.is_empty())
+                    .map(|t| clip_segments_window(t, start_s, end_s));
+                if window_segments.as_ref().is_some_and(|segments| segments.is_empty()) {
+                    // The whole window was cut; leave it out of the concat.
+                    if let Ok(mut guard) = progress_handle.lock() {
+                        guard[idx] = 1.0;
+                    }
+                    continue;
+                }
+                let clip_select = window_segments.as_ref().and_then(|s| clip_select_for(s));
                 let camera_enable = camera_track
                     .as_ref()
                     .and_then(|t| build_camera_enable_window(t, start_s, end_s));
+                let zoom =
+                    source_effects_for(&input_path, &edit_state, &probe, (start_s, end_s), (profile.width, profile.height));
+                let filter = build_export_filter(
+                    &edit_state,
+                    &profile,
+                    has_camera,
+                    camera_enable,
+                    clip_select,
+                    zoom,
+                );
+                let filter = append_camera_framing(filter, &input_path);
                 let filter =
-                    build_export_filter(&edit_state, &profile, has_camera, camera_enable, clip_select);
+                    append_keystrokes(filter, &input_path, &edit_state, &profile, (start_s, end_s));
+                let filter = append_subtitles(
+                    filter,
+                    &input_path,
+                    subtitles.as_ref(),
+                    &edit_state,
+                    &profile,
+                    (start_s, end_s),
+                );
+                let filter = append_timecode(
+                    filter,
+                    &input_path,
+                    timecode.as_ref(),
+                    &profile,
+                    (start_s, end_s),
+                );
+                let audio_filter = if has_audio {
+                    build_export_audio_filter(
+                        &input_path,
+                        window_segments.as_deref(),
+                        start_s,
+                        total_ms as f64 / 1000.0,
+                        denoise.as_deref(),
+                    )
+                } else {
+                    None
+                };
+                let audio = export_audio_for(
+                    &request,
+                    audio_filter,
+                    has_audio,
+                    has_camera,
+                    loudnorm.as_deref(),
+                );
+                let audio_map = audio.map;
+                let filter = match audio.graph {
+                    Some(audio) => format!("{filter};{audio}"),
+                    None => filter,
+                };
                 let filter_path = {
                     let path = output_dir.join(format!("fr_filter_{}_{}.txt", job_id, idx));
                     if fs::write(&path, &filter).is_ok() {
@@ -1260,19 +5222,30 @@ fn run_segmented_export(
                         None
                     }
                 };
+                // -t on the input: with cuts and speed ramps the rendered part
+                // can be shorter or longer than the source window.
                 let mut args = vec![
                     "-y".to_string(),
                     "-ss".to_string(),
                     format!("{:.3}", start_s),
-                    "-i".to_string(),
-                    input_path.clone(),
+                    "-t".to_string(),
+                    format!("{:.3}", (duration_ms as f64) / 1000.0),
                 ];
+                args.extend(request.extra_input_args.iter().cloned());
+                args.extend(["-i".to_string(), input_path.clone()]);
                 if let Some(path) = camera_path.as_ref() {
                     if has_camera {
                         args.push("-i".to_string());
                         args.push(path.to_string());
                     }
                 }
+                if let Some(music) = music.as_ref() {
+                    let offset_s = clip_output_offset(clip_track.as_ref(), start_s);
+                    args.extend(music_input_args(music, offset_s, music_ms));
+                    if !has_audio {
+                        args.push("-shortest".to_string());
+                    }
+                }
                 if let Some(path) = filter_path.as_ref() {
                     args.extend([
                         "-filter_complex_script".to_string(),
@@ -1285,42 +5258,14 @@ fn run_segmented_export(
                     "-map".to_string(),
                     "[v]".to_string(),
                     "-map".to_string(),
-                    "0:a?".to_string(),
+                    audio_map.to_string(),
                     "-r".to_string(),
                     profile.fps.to_string(),
-                    "-t".to_string(),
-                    format!("{:.3}", (duration_ms as f64) / 1000.0),
                 ]);
-                let bitrate = format!("{}k", profile.bitrate_kbps.max(1));
-                match profile.format.as_str() {
-                    "h265" | "hevc" => {
-                        args.extend([
-                            "-c:v".to_string(),
-                            "libx265".to_string(),
-                            "-preset".to_string(),
-                            "fast".to_string(),
-                            "-b:v".to_string(),
-                            bitrate,
-                        ]);
-                    }
-                    _ => {
-                        args.extend([
-                            "-c:v".to_string(),
-                            "libx264".to_string(),
-                            "-preset".to_string(),
-                            "fast".to_string(),
-                            "-pix_fmt".to_string(),
-                            "yuv420p".to_string(),
-                            "-b:v".to_string(),
-                            bitrate,
-                        ]);
-                    }
-                }
+                args.extend(encoder_video_args(&profile));
+                args.extend(export_audio_args(&profile));
+                args.extend(request.extra_output_args.iter().cloned());
                 args.extend([
-                    "-c:a".to_string(),
-                    "aac".to_string(),
-                    "-b:a".to_string(),
-                    "160k".to_string(),
                     "-progress".to_string(),
                     "pipe:1".to_string(),
                     "-nostats".to_string(),
@@ -1333,18 +5278,32 @@ fn run_segmented_export(
                             .map(|guard| guard.cancellations.get(&job_id).copied().unwrap_or(false))
                             .unwrap_or(false)
                 };
-                let progress_cb = |p: f32| {
+                let progress_cb = |p: &FfmpegProgress| {
                     let mut guard = progress_handle.lock().unwrap();
-                    guard[idx] = p.min(1.0).max(0.0);
+                    guard[idx] = p.fraction(duration_ms).min(1.0).max(0.0);
                     let sum = guard.iter().copied().sum::<f32>();
                     let overall = sum / segment_count as f32;
                     drop(guard);
+                    let (fps, speed) = {
+                        let mut rates = rates_handle.lock().unwrap();
+                        rates[idx] = Some(*p);
+                        let active = rates.iter().flatten();
+                        let fps: f32 = active.clone().filter_map(|r| r.fps).sum();
+                        let speed: f32 = active.filter_map(|r| r.speed).sum();
+                        ((fps > 0.0).then_some(fps), (speed > 0.0).then_some(speed))
+                    };
+                    let left_s = (1.0 - overall).max(0.0) * window_ms as f32 / 1000.0;
                     let status = ExportStatus {
                         job_id: job_id.clone(),
                         state: "running".to_string(),
                         progress: overall.min(1.0).max(0.0),
                         error: None,
                         output_path: Some(output_path_str.clone()),
+                        group_id: group_id.clone(),
+                        fps,
+                        speed,
+                        eta_s: speed.map(|speed| left_s / speed),
+                        poster_path: None,
                     };
                     if let Ok(mut guard) = state_handle.lock() {
                         guard.statuses.insert(job_id.clone(), status.clone());
@@ -1353,16 +5312,26 @@ fn run_segmented_export(
                 };
                 let result = run_ffmpeg_with_progress(
                     &app_handle,
+                    &state_handle,
+                    &job_id,
                     args,
-                    duration_ms,
                     progress_cb,
                     cancel_check,
                 );
                 if let Some(path) = filter_path.as_ref() {
                     let _ = fs::remove_file(path);
                 }
+                remove_cursor_script(&input_path, start_s);
                 match result {
                     Ok(()) => {
+                        rates_handle.lock().unwrap()[idx] = None;
+                        if let Ok(mut manifest) = manifest_handle.lock() {
+                            manifest.done.push(idx);
+                            let _ = fs::write(
+                                &manifest_path,
+                                serde_json::to_string(&*manifest).unwrap_or_default(),
+                            );
+                        }
                         {
                             let mut guard = progress_handle.lock().unwrap();
                             guard[idx] = 1.0;
@@ -1375,6 +5344,11 @@ fn run_segmented_export(
                                 progress: overall.min(1.0).max(0.0),
                                 error: None,
                                 output_path: Some(output_path_str.clone()),
+                                group_id: group_id.clone(),
+                                fps: None,
+                                speed: None,
+                                eta_s: None,
+                                poster_path: None,
                             };
                             if let Ok(mut guard) = state_handle.lock() {
                                 guard.statuses.insert(job_id.clone(), status.clone());
@@ -1402,16 +5376,24 @@ fn run_segmented_export(
     }
     if let Ok(err) = error_ref.lock().map(|guard| guard.clone()) {
         if let Some(message) = err {
-            for path in segment_paths.iter() {
-                let _ = fs::remove_file(path);
+            // Finished parts stay on disk with the manifest so that running
+            // the same export again picks up where this one stopped.
+            let done = manifest.lock().map(|m| m.done.clone()).unwrap_or_default();
+            for (idx, path) in segment_paths.iter().enumerate() {
+                if !done.contains(&idx) {
+                    let _ = fs::remove_file(path);
+                }
             }
             return Err(message);
         }
     }
     let list_path = output_dir.join(format!("{stem}_concat.txt"));
     let mut list_content = String::new();
-    for path in segment_paths.iter() {
-        list_content.push_str(&format!("file '{}'\n", path.to_string_lossy()));
+    for path in segment_paths.iter().filter(|path| path.exists()) {
+        list_content.push_str(&concat_list_entry(path));
+    }
+    if list_content.is_empty() {
+        return Err("export_empty".to_string());
     }
     fs::write(&list_path, list_content).map_err(|_| "concat_list_write_failed".to_string())?;
     let bin = ffmpeg_binary_with_app_handle(app);
@@ -1431,10 +5413,11 @@ fn run_segmented_export(
         .status()
         .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
     let _ = fs::remove_file(&list_path);
-    for path in segment_paths.iter() {
-        let _ = fs::remove_file(path);
-    }
     if status.success() {
+        for path in segment_paths.iter() {
+            let _ = fs::remove_file(path);
+        }
+        let _ = fs::remove_file(&manifest_path);
         emit_progress(1.0);
         Ok(())
     } else {
@@ -1442,27 +5425,518 @@ fn run_segmented_export(
     }
 }
 
-fn run_export_job(
-    app: &tauri::AppHandle,
-    state: &Arc<Mutex<ExportManager>>,
-    job: &ExportJob,
-) -> Result<(), String> {
-    let duration_ms = get_media_duration_ms(app, &job.request.input_path);
-    let total_ms = duration_ms.unwrap_or(0);
-    if total_ms > 300_000 {
-        return run_segmented_export(app, state, job, total_ms);
+fn export_output_range(request: &ExportRequest) -> (f64, f64) {
+    (request.start_s.unwrap_or(0.0), request.end_s.unwrap_or(f64::MAX))
+}
+
+// The recording window that start_s/end_s of the edited timeline cover. The
+// clip track inside that window yields exactly the requested range.
+fn export_recording_window(request: &ExportRequest) -> Option<(f64, f64)> {
+    if request.start_s.is_none() && request.end_s.is_none() {
+        return None;
     }
-    let camera_path = job
-        .request
+    let (start_s, end_s) = export_output_range(request);
+    let Some(track) = load_clip_track(&request.input_path).filter(|t| !t.segments.is_empty()) else {
+        return Some((start_s, end_s));
+    };
+    let segments = clip_segments_window(&track, 0.0, f64::MAX);
+    let last_s = segments.last().map(|seg| seg.end_s).unwrap_or(0.0);
+    let window_start = recording_time_for(&segments, start_s).map(|(at, _)| at).unwrap_or(last_s);
+    let window_end = recording_time_for(&segments, end_s).map(|(at, _)| at).unwrap_or(last_s);
+    Some((window_start, window_end.max(window_start)))
+}
+
+// A job qualifies for stream copy when nothing would touch the pixels: no
+// background, frame styling, theme or camera, a single clip at normal speed,
+// and an output profile and container that match the source stream. Returns
+// the range.
+fn trim_only_range(app: &tauri::AppHandle, request: &ExportRequest) -> Option<(f64, f64)> {
+    let edit_state = &request.edit_state;
+    if edit_state.background_type != "none"
+        || edit_state.padding != 0
+        || edit_state.radius != 0
+        || edit_state.shadow != 0
+        || edit_state.theme.is_some()
+        || edit_state.click_effect
+        || edit_state.synthetic_cursor
+        || edit_state.keystrokes
+        || edit_state.watermark.is_some()
+        || screen_color_stage(edit_state).is_some()
+        || edit_state.frame.is_some()
+        || blur_fill_layout(edit_state)
+        || request.timecode.is_some()
+        || request.music.is_some()
+        || request.subtitles.is_some()
+        || request.denoise.is_some()
+        || export_loudness(request).is_some()
+        || !request.extra_input_args.is_empty()
+        || !request.extra_output_args.is_empty()
+        || !request.extra_outputs.is_empty()
+    {
+        return None;
+    }
+    if load_zoom_track(&request.input_path).is_some_and(|track| !track.windows.is_empty())
+        || load_crop_track(&request.input_path).is_some()
+        || load_audio_track(&request.input_path).is_some_and(|t| !audio_track_is_neutral(&t))
+    {
+        return None;
+    }
+    let camera_present = request
         .camera_path
         .as_ref()
-        .filter(|path| !path.is_empty());
-    let has_camera = camera_path
-        .map(|path| PathBuf::from(path).exists())
-        .unwrap_or(false);
-    let camera_enable = derive_camera_enable(&job.request.input_path);
+        .filter(|path| !path.is_empty() && PathBuf::from(path).exists())
+        .is_some();
+    if camera_present {
+        let hidden = load_camera_track(&request.input_path)
+            .map(|track| {
+                !track.segments.is_empty() && track.segments.iter().all(|seg| !seg.visible)
+            })
+            .unwrap_or(false);
+        if !hidden {
+            return None;
+        }
+    }
+    let probe = probe_media(app, &request.input_path)?;
+    let source_codec = probe.video_codec.as_deref()?;
+    // By the resolved encoder, so ProRes/DNxHR or a custom preset never
+    // silently turns into a copy of the H.264 source.
+    let wanted_codec = match resolve_encoder_preset(&request.profile).codec.as_str() {
+        "libx264" => "h264",
+        "libx265" => "hevc",
+        _ => return None,
+    };
+    if !source_codec.starts_with(wanted_codec) {
+        return None;
+    }
+    let container = PathBuf::from(&request.output_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    if !matches!(container.as_deref(), None | Some("mp4" | "mov" | "mkv" | "m4v")) {
+        return None;
+    }
+    let size_matches = |wanted: u32, actual: Option<u32>| wanted == 0 || Some(wanted) == actual;
+    if !size_matches(request.profile.width, probe.width)
+        || !size_matches(request.profile.height, probe.height)
+    {
+        return None;
+    }
+    if let (Some(fps), true) = (probe.fps, request.profile.fps > 0) {
+        if (fps - request.profile.fps as f32).abs() > 0.5 {
+            return None;
+        }
+    }
+    let duration_s = probe.duration_ms? as f64 / 1000.0;
+    let (window_start, window_end) = export_recording_window(request).unwrap_or((0.0, f64::MAX));
+    let duration_s = duration_s.min(window_end);
+    let range = match load_clip_track(&request.input_path) {
+        None => Some((0.0, duration_s)),
+        Some(track) if track.segments.is_empty() => Some((0.0, duration_s)),
+        Some(track) if track.segments.len() == 1 => {
+            let seg = &track.segments[0];
+            if seg.speed.map(|s| (s - 1.0).abs() > f32::EPSILON).unwrap_or(false) {
+                return None;
+            }
+            let start = seg.start_s.max(0.0);
+            let end = seg.end_s.min(duration_s);
+            (end > start).then_some((start, end))
+        }
+        _ => None,
+    }?;
+    let start = range.0.max(window_start);
+    (range.1 > start).then_some((start, range.1))
+}
+
+// Keyframe times come from decoding only the key frames, which is quick even
+// for long recordings.
+fn keyframe_at_or_before(app: &tauri::AppHandle, input_path: &str, time_s: f64) -> f64 {
+    if time_s <= 0.0 {
+        return 0.0;
+    }
+    let output = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args([
+            "-hide_banner",
+            "-skip_frame",
+            "nokey",
+            "-i",
+            input_path,
+            "-map",
+            "0:v:0",
+            "-vf",
+            "showinfo",
+            "-f",
+            "null",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output();
+    let Ok(output) = output else {
+        return 0.0;
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut best = 0.0f64;
+    for line in stderr.lines() {
+        let Some(idx) = line.find("pts_time:") else {
+            continue;
+        };
+        let value = line[idx + 9..].split_whitespace().next().unwrap_or("");
+        if let Ok(pts) = value.parse::<f64>() {
+            if pts <= time_s + 0.001 && pts > best {
+                best = pts;
+            }
+        }
+    }
+    best
+}
+
+fn run_stream_copy_export(
+    app: &tauri::AppHandle,
+    job: &ExportJob,
+    range: (f64, f64),
+) -> Result<(), String> {
+    stream_copy_range(
+        app,
+        &job.request.input_path,
+        &job.request.output_path,
+        range,
+        Some(&job.job_id),
+    )
+    .map(|_| ())
+}
+
+// Copies [start_s, end_s) without re-encoding. The cut starts on the keyframe
+// at or before start_s, which is returned.
+fn stream_copy_range(
+    app: &tauri::AppHandle,
+    input_path: &str,
+    output_path: &str,
+    (start_s, end_s): (f64, f64),
+    log_job_id: Option<&str>,
+) -> Result<f64, String> {
+    let cut_start = keyframe_at_or_before(app, input_path, start_s);
+    let mut args = vec!["-y".to_string()];
+    if cut_start > 0.0 {
+        args.extend(["-ss".to_string(), format!("{cut_start:.3}")]);
+    }
+    args.extend([
+        "-i".to_string(),
+        input_path.to_string(),
+        "-t".to_string(),
+        format!("{:.3}", end_s - cut_start),
+        "-map".to_string(),
+        "0".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        "-avoid_negative_ts".to_string(),
+        "make_zero".to_string(),
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        output_path.to_string(),
+    ]);
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let command_line = args.join(" ");
+    let output = new_cmd(&bin)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e, bin))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if let Some(job_id) = log_job_id {
+        append_export_log(job_id, &command_line, &stderr);
+    }
+    if output.status.success() {
+        return Ok(cut_start);
+    }
+    let tail = stderr
+        .lines()
+        .rev()
+        .take(12)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(format!("export_failed:\n{tail}"))
+}
+
+#[derive(Serialize)]
+struct QuickTrimResponse {
+    output_path: String,
+    start_s: f64,
+    end_s: f64,
+}
+
+// Head/tail trim without compositing; finishes in about the time of a file
+// copy. start_s snaps back to the previous keyframe.
+#[tauri::command]
+async fn quick_trim(
+    app: tauri::AppHandle,
+    input_path: String,
+    start_s: f64,
+    end_s: f64,
+    output_path: String,
+) -> Result<QuickTrimResponse, AppError> {
+    if !PathBuf::from(&input_path).is_file() {
+        return Err("input_not_found".into());
+    }
+    if !start_s.is_finite() || !end_s.is_finite() || start_s < 0.0 || end_s <= start_s {
+        return Err("invalid_range".into());
+    }
+    if PathBuf::from(&output_path) == PathBuf::from(&input_path) {
+        return Err("output_same_as_input".into());
+    }
+    let duration_s = get_media_duration_ms(&app, &input_path).unwrap_or(0) as f64 / 1000.0;
+    let end_s = if duration_s > 0.0 { end_s.min(duration_s) } else { end_s };
+    if end_s <= start_s {
+        return Err("invalid_range".into());
+    }
+    let output = output_path.clone();
+    let cut_start = tauri::async_runtime::spawn_blocking(move || {
+        stream_copy_range(&app, &input_path, &output, (start_s, end_s), None)
+    })
+    .await
+    .map_err(|_| "quick_trim_join_failed".to_string())??;
+    Ok(QuickTrimResponse {
+        output_path,
+        start_s: cut_start,
+        end_s,
+    })
+}
+
+// The request's own output followed by its extra outputs, each as a request
+// of its own for the post-export steps.
+fn export_outputs(request: &ExportRequest) -> Vec<ExportRequest> {
+    let primary = ExportRequest {
+        extra_outputs: Vec::new(),
+        ..request.clone()
+    };
+    let extra = request.extra_outputs.iter().map(|output| ExportRequest {
+        profile: output.profile.clone(),
+        output_path: output.output_path.clone(),
+        ..primary.clone()
+    });
+    std::iter::once(primary.clone()).chain(extra).collect()
+}
+
+// Splits the rendered [v] (and a filtered audio label) once per output so
+// each encoder gets its own copy; stream specifiers can be mapped repeatedly.
+// Returns the graph and the (video, audio) maps per output.
+fn fan_out_graph(graph: String, audio_map: &str, count: usize) -> (String, Vec<(String, String)>) {
+    if count <= 1 {
+        return (graph, vec![("[v]".to_string(), audio_map.to_string())]);
+    }
+    let labels = |prefix: &str| (0..count).map(|i| format!("[{prefix}{i}]")).collect::<Vec<_>>();
+    let video = labels("vout");
+    let mut graph = format!("{graph};[v]split={count}{}", video.concat());
+    let audio = if audio_map.starts_with('[') {
+        let audio = labels("aout");
+        graph.push_str(&format!(";{audio_map}asplit={count}{}", audio.concat()));
+        audio
+    } else {
+        vec![audio_map.to_string(); count]
+    };
+    (graph, video.into_iter().zip(audio).collect())
+}
+
+// Folds batch requests that render identically (same size, fps, layout and
+// no range) into one job with extra outputs, so the graph runs once.
+fn merge_shared_renders(requests: Vec<ExportRequest>) -> Vec<ExportRequest> {
+    let mut merged: Vec<ExportRequest> = Vec::new();
+    for request in requests {
+        let shares_render = |other: &&mut ExportRequest| {
+            request.start_s.is_none()
+                && request.end_s.is_none()
+                && other.start_s.is_none()
+                && other.end_s.is_none()
+                && other.profile.width == request.profile.width
+                && other.profile.height == request.profile.height
+                && other.profile.fps == request.profile.fps
+                && other.image_sequence.is_none()
+                && request.image_sequence.is_none()
+                && other.edit_state.aspect == request.edit_state.aspect
+                // The audio chain (and its loudnorm) is built once per render.
+                && other.loudness_lufs.or(other.profile.loudness_lufs)
+                    == request.loudness_lufs.or(request.profile.loudness_lufs)
+                && other.profile.true_peak_db == request.profile.true_peak_db
+        };
+        match merged.iter_mut().find(shares_render) {
+            Some(shared) => shared.extra_outputs.push(ExportOutput {
+                output_path: normalize_export_output_path(&request),
+                profile: request.profile,
+            }),
+            None => merged.push(request),
+        }
+    }
+    merged
+}
+
+// The filters after [v] that pick the frames: a fixed rate, or the frame
+// nearest each marker.
+fn image_sequence_stage(request: &ExportRequest, sequence: &ImageSequenceOptions) -> Result<String, String> {
+    if !sequence.at_markers {
+        let fps = sequence.fps.unwrap_or(request.profile.fps as f64);
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err("invalid_image_sequence".to_string());
+        }
+        return Ok(format!("fps={fps}"));
+    }
+    let track: MarkerTrack = marker_track_path(&request.input_path)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    let segments = load_clip_track(&request.input_path)
+        .filter(|track| !track.segments.is_empty())
+        .map(|track| clip_segments_window(&track, 0.0, f64::MAX));
+    let (start_s, end_s) = export_output_range(request);
+    let times: Vec<f64> = track
+        .markers
+        .iter()
+        .filter_map(|marker| output_time_for(segments.as_deref(), marker.time_s))
+        .filter(|t| *t >= start_s && *t < end_s)
+        .collect();
+    if times.is_empty() {
+        return Err("no_markers_in_range".to_string());
+    }
+    // At a fixed rate exactly one frame lies within half a frame of each time.
+    let fps = request.profile.fps.max(1);
+    let half = 0.5 / fps as f64;
+    let terms: Vec<String> = times.iter().map(|t| format!("lt(abs(t-{t:.4}),{half:.6})")).collect();
+    Ok(format!("fps={fps},select='{}'", terms.join("+")))
+}
+
+// Removes frame_* files only, so nothing else the user put there is lost.
+fn clear_sequence_frames(dir: &Path) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if entry.file_name().to_string_lossy().starts_with("frame_") {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+// Image encoder and numbered pattern inside the frames directory, which is
+// created and cleared of frames from an earlier run.
+fn image_sequence_output_args(request: &ExportRequest, sequence: &ImageSequenceOptions) -> Result<Vec<String>, String> {
+    let ext = match sequence.format.as_deref() {
+        None | Some("png") => "png",
+        Some("webp") => "webp",
+        Some(_) => return Err("invalid_image_sequence".to_string()),
+    };
+    let dir = PathBuf::from(&request.output_path);
+    fs::create_dir_all(&dir).map_err(|_| "export_dir_create_failed".to_string())?;
+    clear_sequence_frames(&dir);
+    let mut args = vec!["-map".to_string(), "[vseq]".to_string()];
+    let (start_s, end_s) = export_output_range(request);
+    if start_s > 0.0 {
+        args.extend(["-ss".to_string(), format!("{start_s:.3}")]);
+    }
+    if end_s.is_finite() {
+        args.extend(["-to".to_string(), format!("{end_s:.3}")]);
+    }
+    args.extend(["-fps_mode".to_string(), "vfr".to_string()]);
+    if ext == "webp" {
+        args.extend(["-c:v", "libwebp", "-quality", "90"].map(String::from));
+    } else {
+        args.extend(["-c:v".to_string(), "png".to_string()]);
+    }
+    args.extend(request.extra_output_args.iter().cloned());
+    args.extend(["-f".to_string(), "image2".to_string()]);
+    args.push(dir.join(format!("frame_%05d.{ext}")).to_string_lossy().to_string());
+    Ok(args)
+}
+
+fn run_export_job(
+    app: &tauri::AppHandle,
+    state: &Arc<Mutex<ExportManager>>,
+    job: &ExportJob,
+) -> Result<(), String> {
+    // Jobs restored from the queue were checked on a possibly different install.
+    check_denoise_model(app, &job.request)?;
+    // Image sequences always render in one pass and cut the range on output.
+    let sequence = job.request.image_sequence.as_ref();
+    if let Some(range) = trim_only_range(app, &job.request).filter(|_| sequence.is_none()) {
+        return run_stream_copy_export(app, job, range);
+    }
+    let duration_ms = get_media_duration_ms(app, &job.request.input_path);
+    let total_ms = duration_ms.unwrap_or(0);
+    if let Some((start_s, end_s)) = export_recording_window(&job.request).filter(|_| sequence.is_none()) {
+        let end_ms = ((end_s * 1000.0) as u64).min(total_ms);
+        return run_segmented_export(app, state, job, total_ms, ((start_s * 1000.0) as u64, end_ms));
+    }
+    // Fanned-out encodes stay single pass so the graph runs once.
+    let outputs = export_outputs(&job.request);
+    if total_ms > 300_000 && outputs.len() == 1 && sequence.is_none() {
+        return run_segmented_export(app, state, job, total_ms, (0, total_ms));
+    }
+    let camera_path = job
+        .request
+        .camera_path
+        .as_ref()
+        .filter(|path| !path.is_empty());
+    let has_camera = camera_path
+        .map(|path| PathBuf::from(path).exists())
+        .unwrap_or(false);
+    let camera_enable = derive_camera_enable(&job.request.input_path);
     let clip_select = derive_clip_select(&job.request.input_path);
-    let filter = build_export_filter(&job.request.edit_state, &job.request.profile, has_camera, camera_enable, clip_select);
+    let probe = probe_media(app, &job.request.input_path).unwrap_or_default();
+    let zoom = source_effects_for(
+        &job.request.input_path,
+        &job.request.edit_state,
+        &probe,
+        (0.0, f64::MAX),
+        (job.request.profile.width, job.request.profile.height),
+    );
+    let filter = build_export_filter(&job.request.edit_state, &job.request.profile, has_camera, camera_enable, clip_select, zoom);
+    let filter = append_camera_framing(filter, &job.request.input_path);
+    let filter = append_keystrokes(
+        filter,
+        &job.request.input_path,
+        &job.request.edit_state,
+        &job.request.profile,
+        (0.0, f64::MAX),
+    );
+    let filter = append_subtitles(
+        filter,
+        &job.request.input_path,
+        job.request.subtitles.as_ref(),
+        &job.request.edit_state,
+        &job.request.profile,
+        (0.0, f64::MAX),
+    );
+    let filter = append_timecode(
+        filter,
+        &job.request.input_path,
+        job.request.timecode.as_ref(),
+        &job.request.profile,
+        (0.0, f64::MAX),
+    );
+    let has_audio = probe.has_audio && sequence.is_none();
+    let audio_filter = full_export_audio_filter(app, &job.request, has_audio, total_ms);
+    let loudnorm =
+        loudnorm_filter(app, &job.request, audio_filter.clone(), has_audio, has_camera);
+    let audio = export_audio_for(
+        &job.request,
+        audio_filter,
+        has_audio,
+        has_camera,
+        loudnorm.as_deref(),
+    );
+    let music = job
+        .request
+        .music
+        .as_ref()
+        .filter(|music| PathBuf::from(&music.path).is_file() && sequence.is_none());
+    let filter = match audio.graph {
+        Some(audio) if sequence.is_none() => format!("{filter};{audio}"),
+        _ => filter,
+    };
+    let (filter, maps) = match sequence {
+        Some(sequence) => {
+            let stage = image_sequence_stage(&job.request, sequence)?;
+            (format!("{filter};[v]{stage}[vseq]"), Vec::new())
+        }
+        None => fan_out_graph(filter, &audio.map, outputs.len()),
+    };
     let filter_path = {
         let dir = PathBuf::from(&job.request.output_path)
             .parent()
@@ -1479,14 +5953,23 @@ fn run_export_job(
         if let Some(p) = path.as_ref() {
             let _ = fs::remove_file(p);
         }
+        remove_cursor_script(&job.request.input_path, 0.0);
     };
-    let mut args = vec!["-y".to_string(), "-i".to_string(), job.request.input_path.clone()];
+    let mut args = vec!["-y".to_string()];
+    args.extend(job.request.extra_input_args.iter().cloned());
+    args.extend(["-i".to_string(), job.request.input_path.clone()]);
     if let Some(path) = camera_path {
         if has_camera {
             args.push("-i".to_string());
             args.push(path.to_string());
         }
     }
+    if let Some(music) = music {
+        args.extend(music_input_args(music, 0.0, None));
+        if !has_audio {
+            args.push("-shortest".to_string());
+        }
+    }
     if let Some(path) = filter_path.as_ref() {
         args.extend([
             "-filter_complex_script".to_string(),
@@ -1496,49 +5979,29 @@ fn run_export_job(
         args.extend(["-filter_complex".to_string(), filter]);
     }
     args.extend([
-        "-map".to_string(),
-        "[v]".to_string(),
-        "-map".to_string(),
-        "0:a?".to_string(),
-        "-r".to_string(),
-        job.request.profile.fps.to_string(),
-    ]);
-    let bitrate = format!("{}k", job.request.profile.bitrate_kbps.max(1));
-    match job.request.profile.format.as_str() {
-        "h265" | "hevc" => {
-            args.extend([
-                "-c:v".to_string(),
-                "libx265".to_string(),
-                "-preset".to_string(),
-                "fast".to_string(),
-                "-b:v".to_string(),
-                bitrate,
-            ]);
-        }
-        _ => {
-            args.extend([
-                "-c:v".to_string(),
-                "libx264".to_string(),
-                "-preset".to_string(),
-                "fast".to_string(),
-                "-pix_fmt".to_string(),
-                "yuv420p".to_string(),
-                "-b:v".to_string(),
-                bitrate,
-            ]);
-        }
-    }
-    args.extend([
-        "-c:a".to_string(),
-        "aac".to_string(),
-        "-b:a".to_string(),
-        "160k".to_string(),
         "-progress".to_string(),
         "pipe:1".to_string(),
         "-nostats".to_string(),
-        job.request.output_path.clone(),
     ]);
+    if let Some(sequence) = sequence {
+        args.extend(image_sequence_output_args(&job.request, sequence)?);
+    }
+    for (request, (video_map, audio_map)) in outputs.iter().zip(maps) {
+        args.extend([
+            "-map".to_string(),
+            video_map,
+            "-map".to_string(),
+            audio_map,
+            "-r".to_string(),
+            request.profile.fps.to_string(),
+        ]);
+        args.extend(encoder_video_args(&request.profile));
+        args.extend(export_audio_args(&request.profile));
+        args.extend(request.extra_output_args.iter().cloned());
+        args.push(request.output_path.clone());
+    }
     let bin = ffmpeg_binary_with_app_handle(app);
+    let command_line = args.join(" ");
     let mut child = new_cmd(&bin)
         .args(args)
         .stdout(Stdio::piped())
@@ -1562,13 +6025,16 @@ fn run_export_job(
             cleanup_filter(&filter_path);
             "export_stderr_unavailable".to_string()
         })?;
+    let child = track_export_child(state, &job.job_id, child);
     let job_id = job.job_id.clone();
     let app_handle = app.clone();
     let state_handle = Arc::clone(state);
     let job_output_path = job.request.output_path.clone();
+    let job_group_id = job.group_id.clone();
     let reader_handle = thread::spawn(move || {
         let mut reader = BufReader::new(stdout);
         let mut line = String::new();
+        let mut progress = FfmpegProgress::default();
         loop {
             line.clear();
             let bytes = match reader.read_line(&mut line) {
@@ -1578,26 +6044,27 @@ fn run_export_job(
             if bytes == 0 {
                 break;
             }
-            let trimmed = line.trim();
-            if let Some(value) = trimmed.strip_prefix("out_time_ms=") {
-                if let Ok(out_time_ms) = value.parse::<u64>() {
-                    if let Some(duration_ms) = duration_ms {
-                        let progress = (out_time_ms as f64 / duration_ms as f64).min(1.0);
-                        let status = ExportStatus {
-                            job_id: job_id.clone(),
-                            state: "running".to_string(),
-                            progress: progress as f32,
-                            error: None,
-                            output_path: Some(job_output_path.clone()),
-                        };
-                        if let Ok(mut guard) = state_handle.lock() {
-                            guard.statuses.insert(job_id.clone(), status.clone());
-                        }
-                        emit_export_status(&app_handle, &status);
+            if progress.update(&line) {
+                if let Some(duration_ms) = duration_ms {
+                    let status = ExportStatus {
+                        job_id: job_id.clone(),
+                        state: "running".to_string(),
+                        progress: progress.fraction(duration_ms),
+                        error: None,
+                        output_path: Some(job_output_path.clone()),
+                        group_id: job_group_id.clone(),
+                        fps: progress.fps,
+                        speed: progress.speed,
+                        eta_s: progress.eta_s(duration_ms),
+                        poster_path: None,
+                    };
+                    if let Ok(mut guard) = state_handle.lock() {
+                        guard.statuses.insert(job_id.clone(), status.clone());
                     }
+                    emit_export_status(&app_handle, &status);
                 }
             }
-            if trimmed == "progress=end" {
+            if line.trim() == "progress=end" {
                 break;
             }
         }
@@ -1609,24 +6076,22 @@ fn run_export_job(
         buffer
     });
     loop {
-        let cancelled = {
-            if let Ok(guard) = state.lock() {
-                guard.cancellations.get(&job.job_id).copied().unwrap_or(false)
-            } else {
-                false
+        if export_cancelled(state, &job.job_id) {
+            if let Ok(mut child) = child.lock() {
+                let _ = child.kill();
+                let _ = child.wait();
             }
-        };
-        if cancelled {
-            let _ = child.kill();
-            let _ = child.wait();
             let _ = reader_handle.join();
-            let _ = stderr_handle.join();
+            let stderr_output = stderr_handle.join().unwrap_or_default();
+            append_export_log(&job.job_id, &command_line, &stderr_output);
             cleanup_filter(&filter_path);
             return Err("export_cancelled".to_string());
         }
-        if let Ok(Some(status)) = child.try_wait() {
+        let exited = child.lock().ok().and_then(|mut child| child.try_wait().ok().flatten());
+        if let Some(status) = exited {
             let _ = reader_handle.join();
             let stderr_output = stderr_handle.join().unwrap_or_default();
+            append_export_log(&job.job_id, &command_line, &stderr_output);
             let result = if status.success() {
                 Ok(())
             } else if stderr_output.trim().is_empty() {
@@ -1650,14 +6115,89 @@ fn run_export_job(
     }
 }
 
-async fn create_preview_session() -> Result<PreviewSession, String> {
+fn preview_rtc_configuration(settings: &PreviewSettings) -> RTCConfiguration {
+    let ice_servers = if settings.host_candidates_only {
+        Vec::new()
+    } else {
+        settings
+            .ice_servers
+            .iter()
+            .filter(|server| !server.urls.is_empty())
+            .map(|server| RTCIceServer {
+                urls: server.urls.clone(),
+                username: server.username.clone().unwrap_or_default(),
+                credential: server.credential.clone().unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect()
+    };
+    let ice_transport_policy = if settings.relay_only && !settings.host_candidates_only {
+        RTCIceTransportPolicy::Relay
+    } else {
+        RTCIceTransportPolicy::All
+    };
+    RTCConfiguration {
+        ice_servers,
+        ice_transport_policy,
+        ..Default::default()
+    }
+}
+
+async fn bind_preview_rtp_socket() -> Result<(UdpSocket, u16), String> {
+    let socket = UdpSocket::bind(("127.0.0.1", 0))
+        .await
+        .map_err(|e| format!("preview_rtp_bind_failed: {e}"))?;
+    let port = socket
+        .local_addr()
+        .map_err(|e| format!("preview_rtp_bind_failed: {e}"))?
+        .port();
+    Ok((socket, port))
+}
+
+fn spawn_rtp_forwarder(
+    socket: UdpSocket,
+    track: Arc<TrackLocalStaticRTP>,
+    muted: Option<Arc<AtomicBool>>,
+) -> async_runtime::JoinHandle<()> {
+    async_runtime::spawn(async move {
+        let mut buf = vec![0u8; 2048];
+        loop {
+            let (len, _) = match socket.recv_from(&mut buf).await {
+                Ok(result) => result,
+                Err(_) => break,
+            };
+            if muted.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+                continue;
+            }
+            let mut raw = &buf[..len];
+            let packet = match Packet::unmarshal(&mut raw) {
+                Ok(packet) => packet,
+                Err(_) => continue,
+            };
+            let _ = track.write_rtp(&packet).await;
+        }
+    })
+}
+
+async fn create_preview_session(
+    settings: &PreviewSettings,
+    with_audio: bool,
+) -> Result<PreviewSession, String> {
     let mut media_engine = MediaEngine::default();
     media_engine
         .register_default_codecs()
         .map_err(|e| e.to_string())?;
-    let api = APIBuilder::new().with_media_engine(media_engine).build();
+    let mut setting_engine = SettingEngine::default();
+    if settings.host_candidates_only {
+        setting_engine.set_ice_multicast_dns_mode(MulticastDnsMode::Disabled);
+        setting_engine.set_include_loopback_candidate(true);
+    }
+    let api = APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_setting_engine(setting_engine)
+        .build();
     let peer = Arc::new(
-        api.new_peer_connection(RTCConfiguration::default())
+        api.new_peer_connection(preview_rtc_configuration(settings))
             .await
             .map_err(|e| e.to_string())?,
     );
@@ -1682,768 +6222,6250 @@ async fn create_preview_session() -> Result<PreviewSession, String> {
             }
         }
     });
-    let track_for_task = track.clone();
-    let udp_task = async_runtime::spawn(async move {
-        let socket = match UdpSocket::bind(("127.0.0.1", PREVIEW_RTP_PORT)).await {
-            Ok(socket) => socket,
-            Err(_) => return,
-        };
-        let mut buf = vec![0u8; 2048];
-        loop {
-            let (len, _) = match socket.recv_from(&mut buf).await {
-                Ok(result) => result,
-                Err(_) => break,
-            };
-            let mut raw = &buf[..len];
-            let packet = match Packet::unmarshal(&mut raw) {
-                Ok(packet) => packet,
-                Err(_) => continue,
-            };
-            let _ = track_for_task.write_rtp(&packet).await;
+    // Let the OS pick the port so a second instance or another app holding a
+    // fixed port cannot break the preview; ffmpeg is pointed at it afterwards.
+    let (socket, rtp_port) = bind_preview_rtp_socket().await?;
+    let udp_task = spawn_rtp_forwarder(socket, track, None);
+
+    let audio_muted = Arc::new(AtomicBool::new(false));
+    let mut audio_task = None;
+    let mut audio_rtp_port = None;
+    if with_audio {
+        let audio_track = Arc::new(TrackLocalStaticRTP::new(
+            RTCRtpCodecCapability {
+                mime_type: "audio/opus".to_string(),
+                clock_rate: 48000,
+                channels: 2,
+                sdp_fmtp_line: "minptime=10;useinbandfec=1".to_string(),
+                rtcp_feedback: vec![],
+            },
+            "audio".to_string(),
+            "preview".to_string(),
+        ));
+        let audio_sender = peer
+            .add_track(audio_track.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        async_runtime::spawn(async move {
+            let mut buf = vec![0u8; 1500];
+            loop {
+                if audio_sender.read(&mut buf).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let (audio_socket, port) = bind_preview_rtp_socket().await?;
+        audio_task = Some(spawn_rtp_forwarder(
+            audio_socket,
+            audio_track,
+            Some(audio_muted.clone()),
+        ));
+        audio_rtp_port = Some(port);
+    }
+
+    Ok(PreviewSession {
+        peer,
+        udp_task,
+        rtp_port,
+        audio_task,
+        audio_rtp_port,
+        audio_muted,
+        relay: None,
+    })
+}
+
+const PREVIEW_SOURCE_SIZE: u32 = 480;
+const PREVIEW_SOURCE_FPS: u32 = 30;
+
+fn reserve_local_udp_port() -> Result<u16, String> {
+    let socket = std::net::UdpSocket::bind(("127.0.0.1", 0))
+        .map_err(|e| format!("preview_relay_bind_failed: {e}"))?;
+    socket
+        .local_addr()
+        .map(|addr| addr.port())
+        .map_err(|e| format!("preview_relay_bind_failed: {e}"))
+}
+
+// The reserved port is free again once its socket drops, so another process
+// can take it before the relay binds it. A relay that exits right away lost
+// that race and is retried on a fresh port.
+fn spawn_recorder_relay(
+    bin: &str,
+    rtp_port: u16,
+    quality: &PreviewQuality,
+) -> Result<(u16, Child), String> {
+    let mut last_error = "preview_relay_bind_failed".to_string();
+    for _ in 0..3 {
+        let port = reserve_local_udp_port()?;
+        let mut child = spawn_preview_relay(bin, &PreviewSource::Recorder(port), rtp_port, quality)?;
+        thread::sleep(Duration::from_millis(200));
+        match child.try_wait() {
+            Ok(None) => return Ok((port, child)),
+            Ok(Some(status)) => last_error = format!("preview_relay_bind_failed: {status}"),
+            Err(e) => last_error = format!("preview_relay_bind_failed: {e}"),
         }
-    });
-    Ok(PreviewSession { peer, udp_task })
+    }
+    Err(last_error)
+}
+
+fn clamp_preview_quality(quality: &PreviewQuality) -> PreviewQuality {
+    PreviewQuality {
+        bitrate_kbps: quality.bitrate_kbps.clamp(100, 4000),
+        fps: quality.fps.clamp(5, PREVIEW_SOURCE_FPS),
+        size: (quality.size.clamp(120, PREVIEW_SOURCE_SIZE) / 2) * 2,
+    }
+}
+
+fn spawn_preview_relay(
+    bin: &str,
+    source: &PreviewSource,
+    rtp_port: u16,
+    quality: &PreviewQuality,
+) -> Result<Child, String> {
+    let quality = clamp_preview_quality(quality);
+    let bitrate = format!("{}k", quality.bitrate_kbps);
+    let fps = quality.fps.to_string();
+    let mut args = vec![
+        "-hide_banner".to_string(),
+        "-loglevel".to_string(),
+        "error".to_string(),
+        "-fflags".to_string(),
+        "nobuffer".to_string(),
+        "-flags".to_string(),
+        "low_delay".to_string(),
+    ];
+    let filter = match source {
+        PreviewSource::Recorder(input_port) => {
+            args.extend([
+                "-f".to_string(),
+                "mpegts".to_string(),
+                "-i".to_string(),
+                format!("udp://127.0.0.1:{input_port}?overrun_nonfatal=1&fifo_size=50000"),
+            ]);
+            format!("fps={},scale={}:{}", quality.fps, quality.size, quality.size)
+        }
+        PreviewSource::Camera(device) => {
+            args.extend([
+                "-f".to_string(),
+                "dshow".to_string(),
+                "-rtbufsize".to_string(),
+                "64M".to_string(),
+                "-i".to_string(),
+                format!("video={device}"),
+            ]);
+            // Same framing as the recorded avatar so what you see is what you get.
+            format!(
+                "crop='min(iw,ih)':'min(iw,ih)',hflip,fps={fps},scale={size}:{size}",
+                fps = quality.fps,
+                size = quality.size
+            )
+        }
+    };
+    new_cmd(bin)
+        .args(args)
+        .args([
+            "-an".to_string(),
+            "-vf".to_string(),
+            filter,
+            "-c:v".to_string(),
+            "libx264".to_string(),
+            "-preset".to_string(),
+            "ultrafast".to_string(),
+            "-tune".to_string(),
+            "zerolatency".to_string(),
+            "-pix_fmt".to_string(),
+            "yuv420p".to_string(),
+            "-profile:v".to_string(),
+            "baseline".to_string(),
+            "-b:v".to_string(),
+            bitrate.clone(),
+            "-maxrate".to_string(),
+            bitrate.clone(),
+            "-bufsize".to_string(),
+            bitrate,
+            "-g".to_string(),
+            fps.clone(),
+            "-keyint_min".to_string(),
+            fps,
+            "-bf".to_string(),
+            "0".to_string(),
+            "-f".to_string(),
+            "rtp".to_string(),
+            format!("rtp://127.0.0.1:{rtp_port}?pkt_size=1200"),
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("preview_relay_spawn_failed: {e}"))
 }
 
 async fn stop_preview_session(session: PreviewSession) {
     let _ = session.peer.close().await;
     session.udp_task.abort();
+    if let Some(task) = session.audio_task {
+        task.abort();
+    }
+    if let Some(mut relay) = session.relay {
+        let _ = relay.child.kill();
+        let _ = relay.child.wait();
+    }
+}
+
+#[tauri::command]
+fn exclude_window_from_capture(app: tauri::AppHandle, label: String) -> Result<(), AppError> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::HWND;
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            SetWindowDisplayAffinity, WDA_EXCLUDEFROMCAPTURE,
+        };
+
+        let window = app.get_webview_window(&label).ok_or("window_not_found")?;
+        let hwnd = window.hwnd().map_err(|_| "hwnd_unavailable")?;
+        let hwnd: HWND = hwnd.0 as HWND;
+        let result = unsafe { SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE) };
+        if result == 0 {
+            return Err("exclude_from_capture_failed".into());
+        }
+        return Ok(());
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (app, label);
+        Ok(())
+    }
+}
+
+#[tauri::command]
+fn start_recording(
+    app: tauri::AppHandle,
+    state: State<RecordingState>,
+    preview_state: State<PreviewState>,
+    request: StartRecordingRequest,
+) -> Result<StartRecordingResponse, AppError> {
+    let remembered = request.clone();
+    let response = start_recording_session(app, state, preview_state, request, None)?;
+    remember_recording_request(remembered);
+    Ok(response)
+}
+
+// Saved for get_default_recording_request. Only user-started recordings are
+// remembered, not internal ones such as the diagnostics capture.
+fn remember_recording_request(request: StartRecordingRequest) {
+    let mut settings = load_settings();
+    settings.last_recording = Some(request);
+    let _ = save_settings(&settings);
+}
+
+// Files of the existing session that a new take moves aside while it records
+// into the usual names; stop_recording joins them back together.
+const TAKE_BASE_FILES: &[(&str, &str)] = &[
+    ("recording.mp4", "take_base_recording.mp4"),
+    ("camera.mp4", "take_base_camera.mp4"),
+    ("cursor.jsonl", "take_base_cursor.jsonl"),
+];
+
+fn restore_take_base(dir: &Path) {
+    for (name, aside) in TAKE_BASE_FILES {
+        let aside = dir.join(aside);
+        if aside.exists() {
+            let _ = fs::rename(&aside, dir.join(name));
+        }
+    }
+}
+
+// Records another take into an existing session. The take is appended to
+// recording.mp4 and clip_track.json when it stops.
+#[tauri::command]
+fn continue_recording(
+    app: tauri::AppHandle,
+    state: State<RecordingState>,
+    preview_state: State<PreviewState>,
+    session_id: String,
+    request: StartRecordingRequest,
+) -> Result<StartRecordingResponse, AppError> {
+    let dir = session_dir(&session_id)?;
+    {
+        let guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
+        if guard.is_some() {
+            return Err("recording_already_running".into());
+        }
+    }
+    let base = dir.join("recording.mp4");
+    if !base.exists() {
+        return Err("recording_not_found".into());
+    }
+    if dir.join(TAKE_BASE_FILES[0].1).exists() {
+        return Err("take_in_progress".into());
+    }
+    let base_ms = get_media_duration_ms(&app, &base.to_string_lossy())
+        .filter(|ms| *ms > 0)
+        .ok_or("duration_unknown")?;
+    for (name, aside) in TAKE_BASE_FILES {
+        let path = dir.join(name);
+        if path.exists() {
+            if fs::rename(&path, dir.join(aside)).is_err() {
+                restore_take_base(&dir);
+                return Err("take_prepare_failed".into());
+            }
+        }
+    }
+    let remembered = request.clone();
+    let result =
+        start_recording_session(app, state, preview_state, request, Some((session_id, dir.clone(), base_ms)));
+    if result.is_err() {
+        for (name, _) in TAKE_BASE_FILES {
+            let _ = fs::remove_file(dir.join(name));
+        }
+        restore_take_base(&dir);
+    } else {
+        remember_recording_request(remembered);
+    }
+    Ok(result?)
+}
+
+fn start_recording_session(
+    app: tauri::AppHandle,
+    state: State<RecordingState>,
+    preview_state: State<PreviewState>,
+    request: StartRecordingRequest,
+    // Session id, its resolved folder and length, when continuing a session.
+    take: Option<(String, PathBuf, u64)>,
+) -> Result<StartRecordingResponse, String> {
+    // Checked again once the devices are probed; the state stays unlocked
+    // meanwhile so status queries don't stall behind ffmpeg.
+    if state.inner.lock().map_err(|_| "state_lock_failed")?.is_some() {
+        return Err("recording_already_running".into());
+    }
+
+    let (session_id, output_dir, append_after_ms) = match take {
+        Some((id, dir, base_ms)) => (id, dir, Some(base_ms)),
+        None => {
+            let id = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| e.to_string())?
+                .as_millis()
+                .to_string();
+            let dir = work_base_dir().join(&id);
+            (id, dir, None)
+        }
+    };
+    fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+    let log_error = |message: String| {
+        write_error_log(&output_dir, &message);
+        message
+    };
+    let output_path = output_dir.join("recording.mp4");
+    let camera_path = output_dir.join("camera.mp4");
+    let log_path = output_dir.join("ffmpeg.log");
+    let cursor_path = output_dir.join("cursor.jsonl");
+
+    let fps = if request.fps == 0 { 60 } else { request.fps };
+    // Timelapse: grab 1/speed of the frames and re-time them to the target fps,
+    // so long sessions play back speed_factor times faster without audio.
+    let speed_factor = request
+        .speed_factor
+        .filter(|value| value.is_finite() && *value > 1.0)
+        .unwrap_or(1.0);
+    let is_timelapse = speed_factor > 1.0;
+    let capture_framerate = if is_timelapse {
+        format!("{:.3}", (fps as f32 / speed_factor).max(0.1))
+    } else {
+        fps.to_string()
+    };
+    let resolution_value = parse_resolution_value(&request.resolution);
+    let bitrate_kbps = bitrate_for_resolution(resolution_value);
+
+    let capture_mode = request
+        .capture_mode
+        .as_deref()
+        .unwrap_or("screen")
+        .to_string();
+    let screen_rect = {
+        #[cfg(target_os = "windows")]
+        {
+            use windows_sys::Win32::UI::WindowsAndMessaging::{
+                GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+                SM_YVIRTUALSCREEN,
+            };
+            let x = unsafe { GetSystemMetrics(SM_XVIRTUALSCREEN) };
+            let y = unsafe { GetSystemMetrics(SM_YVIRTUALSCREEN) };
+            let width = evenize(unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) }.max(2));
+            let height = evenize(unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) }.max(2));
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            }
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            Rect {
+                x: 0,
+                y: 0,
+                width: 1920,
+                height: 1080,
+            }
+        }
+    };
+    let mut region_rect: Option<Rect> = None;
+    let mut args = vec![
+        "-y".into(),
+        "-thread_queue_size".into(),
+        "512".into(),
+        "-rtbufsize".into(),
+        "256M".into(),
+        "-f".into(),
+        "gdigrab".into(),
+        "-framerate".into(),
+        capture_framerate,
+    ];
+    let hide_cursor = request.hide_cursor.unwrap_or(false);
+    if hide_cursor {
+        args.extend(["-draw_mouse".into(), "0".into()]);
+    }
+
+    if capture_mode == "window" {
+        let window_title = request
+            .window_title
+            .clone()
+            .ok_or("window_title_required")?;
+        args.extend(["-i".into(), format!("title={window_title}")]);
+    } else if capture_mode == "region" {
+        let mut region = request.region.clone().ok_or("region_required")?;
+        if region.width <= 0 || region.height <= 0 {
+            return Err("invalid_region".into());
+        }
+        if region.x % 2 != 0 {
+            region.x += 1;
+            region.width -= 1;
+        }
+        if region.y % 2 != 0 {
+            region.y += 1;
+            region.height -= 1;
+        }
+        if region.width % 2 != 0 {
+            region.width -= 1;
+        }
+        if region.height % 2 != 0 {
+            region.height -= 1;
+        }
+        if region.width <= 0 || region.height <= 0 {
+            return Err("invalid_region".into());
+        }
+        region_rect = Some(Rect {
+            x: region.x,
+            y: region.y,
+            width: region.width,
+            height: region.height,
+        });
+        args.extend([
+            "-offset_x".into(),
+            region.x.to_string(),
+            "-offset_y".into(),
+            region.y.to_string(),
+            "-video_size".into(),
+            format!("{}x{}", region.width, region.height),
+            "-i".into(),
+            "desktop".into(),
+        ]);
+    } else {
+        args.extend([
+            "-offset_x".into(),
+            screen_rect.x.to_string(),
+            "-offset_y".into(),
+            screen_rect.y.to_string(),
+            "-video_size".into(),
+            format!("{}x{}", screen_rect.width, screen_rect.height),
+            "-i".into(),
+            "desktop".into(),
+        ]);
+    }
+
+    let mut input_index: usize = 1;
+    let mut camera_index: Option<usize> = None;
+    let mut audio_index: Option<usize> = None;
+
+    let camera_device = request.camera_device.unwrap_or_else(|| "auto".into());
+    let mut selected_camera: Option<String> = None;
+    if camera_device == "auto" || camera_device == "default" {
+        let devices = list_video_devices_internal(&app).map_err(log_error)?;
+        selected_camera = devices.into_iter().next();
+    } else if camera_device != "off"
+        && camera_device != "none"
+        && camera_device != "no-camera"
+        && !camera_device.trim().is_empty()
+    {
+        selected_camera = Some(camera_device.clone());
+    }
+
+    let mut warnings: Vec<String> = Vec::new();
+    if let Some(camera_name) = selected_camera.as_ref() {
+        // A running preview may hold the camera, and the recording replaces
+        // it anyway.
+        let existing = preview_state
+            .inner
+            .lock()
+            .map_err(|_| "preview_state_lock_failed")?
+            .take();
+        if let Some(existing) = existing {
+            async_runtime::block_on(stop_preview_session(existing));
+        }
+        if let Some(average_luma) = probe_camera_luma(&app, camera_name) {
+            if average_luma < CAMERA_BLACK_LUMA {
+                warnings.push("camera_black_frames".to_string());
+                let _ = app.emit(
+                    "camera_black_frames",
+                    CameraBlackFramesEvent {
+                        session_id: session_id.clone(),
+                        camera: camera_name.clone(),
+                        average_luma,
+                    },
+                );
+            }
+        }
+    }
+
+    let mut guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
+    if guard.is_some() {
+        return Err("recording_already_running".into());
+    }
+
+    if let Some(camera_name) = selected_camera.as_ref() {
+        args.extend([
+            "-thread_queue_size".into(),
+            "512".into(),
+            "-f".into(),
+            "dshow".into(),
+            "-i".into(),
+            format!("video={}", camera_name),
+        ]);
+        camera_index = Some(input_index);
+        input_index += 1;
+    }
+
+    let mic_device = request.mic_device.unwrap_or_else(|| "auto".into());
+    let mut selected_device: Option<String> = None;
+    if mic_device == "auto" || mic_device == "default" {
+        let devices = list_audio_devices_internal(&app).map_err(log_error)?;
+        selected_device = devices.into_iter().next();
+    } else if mic_device != "mute" && !mic_device.trim().is_empty() {
+        selected_device = Some(mic_device.clone());
+    }
+    if is_timelapse {
+        selected_device = None;
+    }
+
+    if let Some(device_name) = selected_device.as_ref() {
+        args.extend([
+            "-thread_queue_size".into(),
+            "512".into(),
+            "-f".into(),
+            "dshow".into(),
+            "-i".into(),
+            format!("audio={}", device_name),
+        ]);
+        audio_index = Some(input_index);
+    } else {
+        args.push("-an".into());
+    }
+
+    let preview_url = if camera_index.is_some() {
+        Some("webrtc://local".to_string())
+    } else {
+        None
+    };
+
+    let mut preview_rtp_port: Option<u16> = None;
+    let mut preview_audio_port: Option<u16> = None;
+    let mut preview_relay_port: Option<u16> = None;
+    if preview_url.is_some() {
+        {
+            let mut preview_guard = preview_state
+                .inner
+                .lock()
+                .map_err(|_| "preview_state_lock_failed")?;
+            if let Some(existing) = preview_guard.take() {
+                async_runtime::block_on(stop_preview_session(existing));
+            }
+        }
+        let preview_settings = load_settings().preview;
+        let mut session = async_runtime::block_on(create_preview_session(
+            &preview_settings,
+            audio_index.is_some(),
+        ))
+        .map_err(log_error)?;
+        let relay_bin = ffmpeg_binary_with_app_handle(&app);
+        let (relay_port, relay_child) =
+            spawn_recorder_relay(&relay_bin, session.rtp_port, &preview_settings.quality)
+                .map_err(log_error)?;
+        let relay_source = PreviewSource::Recorder(relay_port);
+        session.relay = Some(PreviewRelay {
+            bin: relay_bin,
+            source: relay_source,
+            child: relay_child,
+        });
+        preview_rtp_port = Some(session.rtp_port);
+        preview_audio_port = session.audio_rtp_port;
+        preview_relay_port = Some(relay_port);
+        let mut preview_guard = preview_state
+            .inner
+            .lock()
+            .map_err(|_| "preview_state_lock_failed")?;
+        *preview_guard = Some(session);
+    }
+
+    if let Some(camera_input) = camera_index {
+        let avatar_retime = if is_timelapse {
+            format!("setpts=PTS/{speed_factor},")
+        } else {
+            String::new()
+        };
+        let filter = format!(
+            "[{camera_input}:v]crop='min(iw,ih)':'min(iw,ih)',hflip,split=2[cam_preview][cam_avatar];[cam_preview]fps={PREVIEW_SOURCE_FPS},scale={PREVIEW_SOURCE_SIZE}:{PREVIEW_SOURCE_SIZE}:force_original_aspect_ratio=increase,crop={PREVIEW_SOURCE_SIZE}:{PREVIEW_SOURCE_SIZE},format=yuv420p[preview];[cam_avatar]{avatar_retime}fps=30,scale=240:240:force_original_aspect_ratio=increase,crop=240:240,format=yuv420p[avatar]"
+        );
+        args.extend([
+            "-filter_complex".into(),
+            filter,
+            "-map".into(),
+            "0:v".into(),
+        ]);
+        if let Some(audio_input) = audio_index {
+            args.push("-map".into());
+            args.push(format!("{audio_input}:a"));
+        }
+    }
+
+    if is_timelapse {
+        args.extend([
+            "-filter:v".into(),
+            format!("setpts=PTS/{speed_factor}"),
+            "-r".into(),
+            fps.to_string(),
+        ]);
+    }
+
+    let bitrate_value = format!("{}k", bitrate_kbps.max(1));
+    match request.format.as_str() {
+        "h265" | "hevc" => {
+            args.extend([
+                "-c:v".into(),
+                "libx265".into(),
+                "-preset".into(),
+                "fast".into(),
+                "-b:v".into(),
+                bitrate_value.clone(),
+            ]);
+        }
+        _ => {
+            args.extend([
+                "-c:v".into(),
+                "libx264".into(),
+                "-preset".into(),
+                "fast".into(),
+                "-pix_fmt".into(),
+                "yuv420p".into(),
+                "-b:v".into(),
+                bitrate_value.clone(),
+            ]);
+        }
+    }
+
+    if selected_device.is_some() {
+        args.extend(["-c:a".into(), "aac".into(), "-b:a".into(), "160k".into()]);
+    }
+
+    let camera_output_args = camera_index.map(|_| {
+        vec![
+            "-map".to_string(),
+            "[avatar]".to_string(),
+            "-c:v".to_string(),
+            "libx264".to_string(),
+            "-preset".to_string(),
+            "veryfast".to_string(),
+            "-crf".to_string(),
+            "23".to_string(),
+            "-pix_fmt".to_string(),
+            "yuv420p".to_string(),
+        ]
+    });
+    let preview_output_args = preview_relay_port.map(|port| {
+        let mut preview_args = vec![
+            "-map".to_string(),
+            "[preview]".to_string(),
+            "-c:v".to_string(),
+            "mjpeg".to_string(),
+            "-q:v".to_string(),
+            "4".to_string(),
+            "-pix_fmt".to_string(),
+            "yuvj420p".to_string(),
+            "-f".to_string(),
+            "mpegts".to_string(),
+            format!("udp://127.0.0.1:{port}?pkt_size=1316"),
+        ];
+        // Low-bitrate mic tap so the preview can confirm the mic is live.
+        if let (Some(audio_input), Some(audio_port)) = (audio_index, preview_audio_port) {
+            preview_args.extend([
+                "-map".to_string(),
+                format!("{audio_input}:a"),
+                "-c:a".to_string(),
+                "libopus".to_string(),
+                "-b:a".to_string(),
+                "32k".to_string(),
+                "-ar".to_string(),
+                "48000".to_string(),
+                "-application".to_string(),
+                "lowdelay".to_string(),
+                "-f".to_string(),
+                "rtp".to_string(),
+                format!("rtp://127.0.0.1:{audio_port}?pkt_size=1200"),
+            ]);
+        }
+        preview_args
+    });
+
+    let rect = if capture_mode == "region" {
+        region_rect.ok_or("region_required")?
+    } else {
+        screen_rect.clone()
+    };
+    let started_at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_millis() as u64;
+    let meta = CaptureMeta {
+        mode: capture_mode.clone(),
+        rect: rect.clone(),
+        started_at_ms,
+        speed_factor: if is_timelapse { Some(speed_factor) } else { None },
+        cursor_hidden: hide_cursor,
+        title: None,
+        favorite: false,
+        tags: Vec::new(),
+    };
+    if append_after_ms.is_some() {
+        // Takes are joined with a stream copy, so they must share the frame size.
+        let previous: Option<CaptureMeta> = fs::read_to_string(output_dir.join("capture.json"))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        if let Some(previous) = previous {
+            if previous.rect.width != rect.width || previous.rect.height != rect.height {
+                return Err("take_size_mismatch".to_string());
+            }
+        }
+    } else {
+        let _ = fs::write(output_dir.join("capture.json"), serde_json::to_string(&meta).unwrap_or_default());
+    }
+
+    let plan = RecordingPlan {
+        bin: ffmpeg_binary_with_app_handle(&app),
+        output_dir: output_dir.clone(),
+        input_args: args,
+        camera_output_args,
+        preview_output_args,
+    };
+    let child = spawn_recording_child(&plan, 0).map_err(log_error)?;
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let clock = Arc::new(RecordingClock::new());
+    {
+        let started = Instant::now();
+        let stop_flag_clone = stop_flag.clone();
+        let clock_clone = clock.clone();
+        let cursor_path_clone = cursor_path.clone();
+        let rect_clone = rect.clone();
+        thread::spawn(move || {
+            #[cfg(target_os = "windows")]
+            {
+                use std::io::BufWriter;
+                use windows_sys::Win32::Foundation::POINT;
+                use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON};
+                use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+                let file = fs::File::create(&cursor_path_clone);
+                if file.is_err() {
+                    return;
+                }
+                let mut writer = BufWriter::new(file.unwrap());
+                let mut last_btn = false;
+                let mut last_axn = -1f32;
+                let mut last_ayn = -1f32;
+                loop {
+                    if stop_flag_clone.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if clock_clone.is_paused() {
+                        let _ = writer.flush();
+                        thread::sleep(Duration::from_millis(100));
+                        continue;
+                    }
+                    let mut pt = POINT { x: 0, y: 0 };
+                    let ok = unsafe { GetCursorPos(&mut pt as *mut POINT) };
+                    if ok == 0 {
+                        thread::sleep(Duration::from_millis(30));
+                        continue;
+                    }
+                    let rel_x = (pt.x - rect_clone.x) as f64;
+                    let rel_y = (pt.y - rect_clone.y) as f64;
+                    let axn = (rel_x / (rect_clone.width as f64)).clamp(0.0, 1.0) as f32;
+                    let ayn = (rel_y / (rect_clone.height as f64)).clamp(0.0, 1.0) as f32;
+                    let btn = unsafe { GetAsyncKeyState(VK_LBUTTON as i32) } < 0;
+                    let offset_ms =
+                        (clock_clone.active_ms(started) as f64 / speed_factor as f64) as u64;
+                    let mut wrote_move = false;
+                    if (axn - last_axn).abs() > 0.0001 || (ayn - last_ayn).abs() > 0.0001 {
+                        let rec = CursorEventRecord { kind: "move".into(), offset_ms, axn, ayn };
+                        if let Ok(line) = serde_json::to_string(&rec) {
+                            let _ = writeln!(writer, "{line}");
+                            wrote_move = true;
+                        }
+                        last_axn = axn;
+                        last_ayn = ayn;
+                    }
+                    if btn && !last_btn {
+                        let rec = CursorEventRecord { kind: "down".into(), offset_ms, axn, ayn };
+                        if let Ok(line) = serde_json::to_string(&rec) {
+                            let _ = writeln!(writer, "{line}");
+                            wrote_move = true;
+                        }
+                    } else if !btn && last_btn {
+                        let rec = CursorEventRecord { kind: "up".into(), offset_ms, axn, ayn };
+                        if let Ok(line) = serde_json::to_string(&rec) {
+                            let _ = writeln!(writer, "{line}");
+                            wrote_move = true;
+                        }
+                    }
+                    last_btn = btn;
+                    if !wrote_move {
+                        thread::sleep(Duration::from_millis(30));
+                    } else {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                }
+            }
+        });
+    }
+
+    *guard = Some(RecordingSession {
+        id: session_id.clone(),
+        started_at: Instant::now(),
+        child,
+        cursor_stop: stop_flag,
+        clock,
+        plan,
+        segment_count: 1,
+        suspend_reasons: 0,
+        speed_factor,
+        append_after_ms,
+    });
+
+    Ok(StartRecordingResponse {
+        session_id,
+        output_path: output_path.to_string_lossy().to_string(),
+        log_path: log_path.to_string_lossy().to_string(),
+        preview_url,
+        preview_rtp_port,
+        preview_audio_rtp_port: preview_audio_port,
+        camera_path: camera_index.map(|_| camera_path.to_string_lossy().to_string()),
+        warnings,
+    })
+}
+
+#[tauri::command]
+async fn webrtc_create_answer(
+    preview_state: State<'_, PreviewState>,
+    offer_sdp: String,
+) -> Result<String, AppError> {
+    let peer = {
+        let guard = preview_state
+            .inner
+            .lock()
+            .map_err(|_| "preview_state_lock_failed")?;
+        guard
+            .as_ref()
+            .map(|session| session.peer.clone())
+            .ok_or("preview_not_ready")?
+    };
+    let offer = RTCSessionDescription::offer(offer_sdp).map_err(|e| e.to_string())?;
+    peer.set_remote_description(offer)
+        .await
+        .map_err(|e| e.to_string())?;
+    let answer = peer.create_answer(None).await.map_err(|e| e.to_string())?;
+    let mut gather = peer.gathering_complete_promise().await;
+    peer.set_local_description(answer)
+        .await
+        .map_err(|e| e.to_string())?;
+    let _ = gather.recv().await;
+    let local = peer
+        .local_description()
+        .await
+        .ok_or("missing_local_description")?;
+    Ok(local.sdp)
+}
+
+// Mean Y of a covered lens sits at the limited-range floor (16); anything this
+// dark across the sampled frames is treated as a closed privacy shutter.
+const CAMERA_BLACK_LUMA: f32 = 22.0;
+
+// Grabs a handful of frames from the camera (skipping the auto-exposure
+// warm-up) and returns their average luma. Has to run with the preview
+// stopped and before the recording ffmpeg opens the device, since dshow
+// cameras are exclusive.
+fn probe_camera_luma(app: &tauri::AppHandle, camera_name: &str) -> Option<f32> {
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let mut child = new_cmd(&bin)
+        .args([
+            "-hide_banner",
+            "-f",
+            "dshow",
+            "-i",
+            &format!("video={camera_name}"),
+            "-vf",
+            "select='gte(n,12)',signalstats,metadata=print:key=lavfi.signalstats.YAVG",
+            "-frames:v",
+            "8",
+            "-an",
+            "-f",
+            "null",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let stderr = child.stderr.take()?;
+    let reader_handle = thread::spawn(move || {
+        let mut reader = BufReader::new(stderr);
+        let mut buffer = String::new();
+        let _ = reader.read_to_string(&mut buffer);
+        buffer
+    });
+    let deadline = Instant::now() + Duration::from_secs(4);
+    loop {
+        if let Ok(Some(_)) = child.try_wait() {
+            break;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let output = reader_handle.join().unwrap_or_default();
+    let values: Vec<f32> = output
+        .lines()
+        .filter_map(|line| line.split("lavfi.signalstats.YAVG=").nth(1))
+        .filter_map(|value| value.trim().parse::<f32>().ok())
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f32>() / values.len() as f32)
+    }
+}
+
+fn recording_segment_name(base: &str, segment: usize) -> String {
+    if segment == 0 {
+        format!("{base}.mp4")
+    } else {
+        format!("{base}_seg{segment:03}.mp4")
+    }
+}
+
+fn recording_args(plan: &RecordingPlan, segment: usize) -> Vec<String> {
+    let mut args = plan.input_args.clone();
+    args.push(
+        plan.output_dir
+            .join(recording_segment_name("recording", segment))
+            .to_string_lossy()
+            .to_string(),
+    );
+    if let Some(camera_args) = plan.camera_output_args.as_ref() {
+        args.extend(camera_args.iter().cloned());
+        args.push(
+            plan.output_dir
+                .join(recording_segment_name("camera", segment))
+                .to_string_lossy()
+                .to_string(),
+        );
+    }
+    if let Some(preview_args) = plan.preview_output_args.as_ref() {
+        args.extend(preview_args.iter().cloned());
+    }
+    args
+}
+
+fn spawn_recording_child(plan: &RecordingPlan, segment: usize) -> Result<Child, String> {
+    let log_path = plan.output_dir.join("ffmpeg.log");
+    let log_file = if segment == 0 {
+        fs::File::create(&log_path)
+    } else {
+        fs::OpenOptions::new().create(true).append(true).open(&log_path)
+    }
+    .map_err(|e| e.to_string())?;
+    new_cmd(&plan.bin)
+        .args(recording_args(plan, segment))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::from(log_file))
+        .spawn()
+        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), plan.bin))
+}
+
+fn finalize_recording_child(child: &mut Child) {
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(b"q");
+        let _ = stdin.flush();
+    }
+    let mut exited = false;
+    for _ in 0..20 {
+        if let Ok(Some(_)) = child.try_wait() {
+            exited = true;
+            break;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    if !exited {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+fn merge_recording_segments(
+    plan: &RecordingPlan,
+    base: &str,
+    segment_count: usize,
+) -> Result<(), String> {
+    if segment_count <= 1 {
+        return Ok(());
+    }
+    let dir = &plan.output_dir;
+    let target = dir.join(recording_segment_name(base, 0));
+    let first = dir.join(format!("{base}_seg000.mp4"));
+    fs::rename(&target, &first).map_err(|_| "segment_rename_failed".to_string())?;
+    let mut parts = vec![first.clone()];
+    for idx in 1..segment_count {
+        let path = dir.join(recording_segment_name(base, idx));
+        if path.exists() {
+            parts.push(path);
+        }
+    }
+    match concat_copy(&plan.bin, &parts, &target) {
+        Ok(()) => {
+            for path in parts.iter() {
+                let _ = fs::remove_file(path);
+            }
+            Ok(())
+        }
+        Err(error) => {
+            // Keep the later segments on disk and restore the first one so the
+            // session stays openable in the editor.
+            let _ = fs::rename(&first, &target);
+            Err(if error.starts_with("ffmpeg_not_found") { error } else { "segment_concat_failed".to_string() })
+        }
+    }
+}
+
+// One line of a concat demuxer list. Quotes in the path are closed, escaped
+// and reopened, the demuxer's equivalent of shell quoting.
+fn concat_list_entry(path: &Path) -> String {
+    format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn concat_copy(bin: &str, parts: &[PathBuf], target: &Path) -> Result<(), String> {
+    let dir = target.parent().ok_or("invalid_output_path")?;
+    let stem = target.file_stem().and_then(|stem| stem.to_str()).unwrap_or("concat");
+    let list_path = dir.join(format!("{stem}_concat.txt"));
+    let mut list_content = String::new();
+    for path in parts.iter() {
+        list_content.push_str(&concat_list_entry(path));
+    }
+    fs::write(&list_path, list_content).map_err(|_| "concat_list_write_failed".to_string())?;
+    let status = new_cmd(bin)
+        .args([
+            "-y",
+            "-f",
+            "concat",
+            "-safe",
+            "0",
+            "-i",
+            list_path.to_string_lossy().as_ref(),
+            "-c",
+            "copy",
+            target.to_string_lossy().as_ref(),
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e, bin))?;
+    let _ = fs::remove_file(&list_path);
+    if status.success() {
+        Ok(())
+    } else {
+        let _ = fs::remove_file(target);
+        Err("take_concat_failed".to_string())
+    }
+}
+
+// Joins the take that just stopped onto the session it continues: media is
+// concatenated, cursor offsets are shifted and the take becomes a new clip.
+fn append_recording_take(plan: &RecordingPlan, base_ms: u64) -> Result<(), String> {
+    let dir = &plan.output_dir;
+    let take_path = dir.join("recording.mp4");
+    let base_path = dir.join(TAKE_BASE_FILES[0].1);
+    let take_ms = media_duration_ms_with_bin(&plan.bin, &take_path.to_string_lossy())
+        .filter(|ms| *ms > 0)
+        .ok_or("take_duration_unknown")?;
+    let joined = dir.join("recording_take.mp4");
+    if let Err(err) = concat_copy(&plan.bin, &[base_path.clone(), take_path.clone()], &joined) {
+        // Keep the take next to the restored session instead of losing it.
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let _ = fs::rename(&take_path, dir.join(format!("take_{stamp}.mp4")));
+        let camera = dir.join("camera.mp4");
+        if camera.exists() {
+            let _ = fs::rename(&camera, dir.join(format!("take_{stamp}_camera.mp4")));
+        }
+        let cursor = dir.join("cursor.jsonl");
+        if cursor.exists() {
+            let _ = fs::rename(&cursor, dir.join(format!("take_{stamp}_cursor.jsonl")));
+        }
+        restore_take_base(dir);
+        return Err(err);
+    }
+    fs::rename(&joined, &take_path).map_err(|_| "take_rename_failed".to_string())?;
+    let _ = fs::remove_file(&base_path);
+
+    let camera_path = dir.join("camera.mp4");
+    let camera_base = dir.join(TAKE_BASE_FILES[1].1);
+    if camera_base.exists() {
+        if camera_path.exists() {
+            let joined = dir.join("camera_take.mp4");
+            concat_copy(&plan.bin, &[camera_base.clone(), camera_path.clone()], &joined)
+                .and_then(|_| {
+                    fs::rename(&joined, &camera_path).map_err(|_| "take_rename_failed".to_string())
+                })?;
+            let _ = fs::remove_file(&camera_base);
+        } else {
+            let _ = fs::rename(&camera_base, &camera_path);
+        }
+    } else if camera_path.exists() {
+        // The camera would start at 0 on the joined timeline; keep it aside.
+        let _ = fs::rename(&camera_path, dir.join(format!("take_{base_ms}_camera.mp4")));
+    }
+
+    let cursor_path = dir.join("cursor.jsonl");
+    let cursor_base = dir.join(TAKE_BASE_FILES[2].1);
+    let mut cursor = fs::read_to_string(&cursor_base).unwrap_or_default();
+    if !cursor.is_empty() && !cursor.ends_with('\n') {
+        cursor.push('\n');
+    }
+    for line in fs::read_to_string(&cursor_path).unwrap_or_default().lines() {
+        if let Ok(mut record) = serde_json::from_str::<CursorEventRecord>(line) {
+            record.offset_ms += base_ms;
+            if let Ok(line) = serde_json::to_string(&record) {
+                cursor.push_str(&line);
+                cursor.push('\n');
+            }
+        }
+    }
+    fs::write(&cursor_path, cursor).map_err(|_| "cursor_write_failed".to_string())?;
+    let _ = fs::remove_file(&cursor_base);
+
+    let base_s = base_ms as f64 / 1000.0;
+    let mut segments = load_clip_track(&take_path.to_string_lossy())
+        .map(|track| track.segments)
+        .filter(|segments| !segments.is_empty())
+        .unwrap_or_else(|| vec![ClipSegment { start_s: 0.0, end_s: base_s, speed: None }]);
+    segments.push(ClipSegment {
+        start_s: base_s,
+        end_s: base_s + take_ms as f64 / 1000.0,
+        speed: None,
+    });
+    let value = to_versioned_value(&ClipTrack { segments }, CLIP_TRACK_MIGRATIONS)
+        .map_err(|_| "track_serialize_failed")?;
+    save_track_file(
+        &dir.join("clip_track.json"),
+        &serde_json::to_string(&value).map_err(|_| "track_serialize_failed")?,
+    )
+}
+
+#[derive(Serialize, Clone)]
+struct RecordingSegmentEvent {
+    session_id: String,
+    segment: usize,
+    reason: String,
+}
+
+fn suspend_reason_name(reason: u8) -> String {
+    match reason {
+        SUSPEND_LOCK => "lock".to_string(),
+        SUSPEND_USER => "user".to_string(),
+        _ => "power".to_string(),
+    }
+}
+
+fn suspend_active_recording(app: &tauri::AppHandle, reason: u8) {
+    let state = app.state::<RecordingState>();
+    let Ok(mut guard) = state.inner.lock() else {
+        return;
+    };
+    let Some(session) = guard.as_mut() else {
+        return;
+    };
+    let already_suspended = session.suspend_reasons != 0;
+    session.suspend_reasons |= reason;
+    if already_suspended {
+        return;
+    }
+    session.clock.pause();
+    finalize_recording_child(&mut session.child);
+    let _ = app.emit(
+        "recording_suspended",
+        RecordingSegmentEvent {
+            session_id: session.id.clone(),
+            segment: session.segment_count - 1,
+            reason: suspend_reason_name(reason),
+        },
+    );
+}
+
+fn resume_active_recording(app: &tauri::AppHandle, reason: u8) {
+    let state = app.state::<RecordingState>();
+    let Ok(mut guard) = state.inner.lock() else {
+        return;
+    };
+    let Some(session) = guard.as_mut() else {
+        return;
+    };
+    if session.suspend_reasons & reason == 0 {
+        return;
+    }
+    session.suspend_reasons &= !reason;
+    if session.suspend_reasons != 0 {
+        return;
+    }
+    let err = match spawn_recording_child(&session.plan, session.segment_count) {
+        Ok(child) => {
+            session.child = child;
+            session.segment_count += 1;
+            session.clock.resume();
+            let _ = app.emit(
+                "recording_resumed",
+                RecordingSegmentEvent {
+                    session_id: session.id.clone(),
+                    segment: session.segment_count - 1,
+                    reason: suspend_reason_name(reason),
+                },
+            );
+            return;
+        }
+        Err(err) => err,
+    };
+    // Without a recorder the session can't go on; end it so the segments
+    // captured so far are merged and saved instead of left behind a dead
+    // child and a paused clock.
+    write_error_log(&session.plan.output_dir, &err);
+    drop(guard);
+    let _ = stop_recording_internal(app.state(), app.state());
+    let _ = app.emit("recording_resume_failed", AppError::from(err));
+}
+
+#[cfg(target_os = "windows")]
+static SYSTEM_EVENT_APP: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+#[cfg(target_os = "windows")]
+fn spawn_system_event_watcher(app: tauri::AppHandle) {
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows_sys::Win32::System::RemoteDesktop::{
+        WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+        TranslateMessage, MSG, PBT_APMRESUMEAUTOMATIC, PBT_APMRESUMESUSPEND, PBT_APMSUSPEND,
+        WM_POWERBROADCAST, WM_WTSSESSION_CHANGE, WNDCLASSW, WS_OVERLAPPED, WTS_SESSION_LOCK,
+        WTS_SESSION_UNLOCK,
+    };
+
+    unsafe extern "system" fn wnd_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == WM_POWERBROADCAST {
+            if let Some(app) = SYSTEM_EVENT_APP.get() {
+                match wparam as u32 {
+                    PBT_APMSUSPEND => suspend_active_recording(app, SUSPEND_POWER),
+                    PBT_APMRESUMEAUTOMATIC | PBT_APMRESUMESUSPEND => {
+                        resume_active_recording(app, SUSPEND_POWER)
+                    }
+                    _ => {}
+                }
+            }
+            return 1;
+        }
+        if msg == WM_WTSSESSION_CHANGE {
+            // The lock screen is useless footage and may show private
+            // notifications, so stop capturing until the user is back.
+            if let Some(app) = SYSTEM_EVENT_APP.get() {
+                match wparam as u32 {
+                    WTS_SESSION_LOCK => suspend_active_recording(app, SUSPEND_LOCK),
+                    WTS_SESSION_UNLOCK => resume_active_recording(app, SUSPEND_LOCK),
+                    _ => {}
+                }
+            }
+            return 0;
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    if SYSTEM_EVENT_APP.set(app).is_err() {
+        return;
+    }
+    thread::spawn(|| unsafe {
+        // A hidden top-level window: message-only windows do not receive
+        // broadcast messages such as WM_POWERBROADCAST.
+        let class_name: Vec<u16> = "FlashRecorderSystemEvents\0".encode_utf16().collect();
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(wnd_proc),
+            lpszClassName: class_name.as_ptr(),
+            ..std::mem::zeroed()
+        };
+        RegisterClassW(&class);
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            class_name.as_ptr(),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null(),
+        );
+        if hwnd.is_null() {
+            return;
+        }
+        WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION);
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+fn spawn_system_event_watcher(app: tauri::AppHandle) {
+    let _ = app;
+}
+
+#[tauri::command]
+fn stop_recording(
+    app: tauri::AppHandle,
+    state: State<RecordingState>,
+    preview_state: State<PreviewState>,
+) -> Result<StopRecordingResponse, AppError> {
+    let response = stop_recording_internal(state, preview_state)?;
+    if let Ok(dir) = session_dir(&response.session_id) {
+        schedule_auto_proxy(app, dir.join("recording.mp4"));
+    }
+    Ok(response)
+}
+
+fn stop_recording_internal(
+    state: State<RecordingState>,
+    preview_state: State<PreviewState>,
+) -> Result<StopRecordingResponse, String> {
+    let mut guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
+    let mut session = guard.take().ok_or("no_active_recording")?;
+    session.cursor_stop.store(true, Ordering::Relaxed);
+    let duration_ms =
+        (session.clock.active_ms(session.started_at) as f64 / session.speed_factor as f64) as u64;
+    let session_id = session.id.clone();
+    finalize_recording_child(&mut session.child);
+    if let Err(err) = merge_recording_segments(&session.plan, "recording", session.segment_count) {
+        write_error_log(&session.plan.output_dir, &err);
+    }
+    if session.plan.camera_output_args.is_some() {
+        if let Err(err) = merge_recording_segments(&session.plan, "camera", session.segment_count) {
+            write_error_log(&session.plan.output_dir, &err);
+        }
+    }
+    if let Some(base_ms) = session.append_after_ms {
+        if let Err(err) = append_recording_take(&session.plan, base_ms) {
+            write_error_log(&session.plan.output_dir, &err);
+        }
+    }
+    if let Ok(mut preview_guard) = preview_state.inner.lock() {
+        if let Some(preview_session) = preview_guard.take() {
+            async_runtime::block_on(stop_preview_session(preview_session));
+        }
+    }
+    Ok(StopRecordingResponse {
+        session_id,
+        duration_ms,
+    })
+}
+
+fn set_recording_paused(app: &tauri::AppHandle, paused: bool) -> Result<(), String> {
+    {
+        let state = app.state::<RecordingState>();
+        let guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
+        if guard.is_none() {
+            return Err("no_active_recording".to_string());
+        }
+    }
+    if paused {
+        suspend_active_recording(app, SUSPEND_USER);
+    } else {
+        resume_active_recording(app, SUSPEND_USER);
+    }
+    Ok(())
+}
+
+// Pausing ends the current segment like a system suspend does; the segments
+// are joined when the recording stops.
+#[tauri::command]
+fn pause_recording(app: tauri::AppHandle) -> Result<(), AppError> {
+    Ok(set_recording_paused(&app, true)?)
+}
+
+#[tauri::command]
+fn resume_recording(app: tauri::AppHandle) -> Result<(), AppError> {
+    Ok(set_recording_paused(&app, false)?)
+}
+
+const TRAY_ID: &str = "main";
+
+struct TrayItems {
+    start: tauri::menu::MenuItem<tauri::Wry>,
+    pause: tauri::menu::MenuItem<tauri::Wry>,
+    stop: tauri::menu::MenuItem<tauri::Wry>,
+}
+
+// A red dot shown as the tray icon while recording.
+fn recording_tray_icon() -> tauri::image::Image<'static> {
+    let size = 32u32;
+    let center = (size - 1) as f32 / 2.0;
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let dist = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            let alpha = if dist <= 13.0 { 255 } else { 0 };
+            rgba.extend_from_slice(&[220, 38, 38, alpha]);
+        }
+    }
+    tauri::image::Image::new_owned(rgba, size, size)
+}
+
+fn format_elapsed(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn setup_tray(app: &tauri::App) -> tauri::Result<()> {
+    use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+    use tauri::tray::TrayIconBuilder;
+
+    let start = MenuItem::with_id(app, "tray_start", "Start recording", true, None::<&str>)?;
+    let pause = MenuItem::with_id(app, "tray_pause", "Pause", false, None::<&str>)?;
+    let stop = MenuItem::with_id(app, "tray_stop", "Stop recording", false, None::<&str>)?;
+    let show = MenuItem::with_id(app, "tray_show", "Show window", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "tray_quit", "Quit", true, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let menu = Menu::with_items(app, &[&start, &pause, &stop, &separator, &show, &quit])?;
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("Flash Recorder")
+        .on_menu_event(|app, event| handle_tray_menu(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    spawn_tray_updater(app.handle().clone(), TrayItems { start, pause, stop });
+    Ok(())
+}
+
+// Tray actions mirror the commands; the frontend hears about them through
+// tray_* events since it did not start them.
+fn handle_tray_menu(app: &tauri::AppHandle, id: &str) {
+    let result = match id {
+        "tray_start" => get_default_recording_request()
+            .and_then(|request| start_recording(app.clone(), app.state(), app.state(), request))
+            .map(|response| {
+                let _ = app.emit("tray_recording_started", response);
+            }),
+        "tray_pause" => {
+            let paused = app
+                .state::<RecordingState>()
+                .inner
+                .lock()
+                .ok()
+                .and_then(|guard| guard.as_ref().map(|session| session.suspend_reasons & SUSPEND_USER != 0))
+                .unwrap_or(false);
+            set_recording_paused(app, !paused).map(|()| {
+                let event = if paused { "tray_recording_resumed" } else { "tray_recording_paused" };
+                let _ = app.emit(event, ());
+            })
+            .map_err(AppError::from)
+        }
+        "tray_stop" => stop_recording(app.clone(), app.state(), app.state()).map(|response| {
+            let _ = app.emit("tray_recording_stopped", response.session_id);
+        }),
+        "tray_show" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+            Ok(())
+        }
+        "tray_quit" => {
+            // Stopping finalizes the file; quitting mid-recording would leave
+            // the segment unplayable.
+            let _ = stop_recording(app.clone(), app.state(), app.state());
+            app.exit(0);
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    if let Err(err) = result {
+        let _ = app.emit("tray_action_failed", err);
+    }
+}
+
+// Keeps the tray icon, tooltip and menu in step with the recording state.
+fn spawn_tray_updater(app: tauri::AppHandle, items: TrayItems) {
+    thread::spawn(move || {
+        let idle_icon = app.default_window_icon().cloned();
+        let recording_icon = recording_tray_icon();
+        let mut was_recording = false;
+        loop {
+            let status = app.state::<RecordingState>().inner.lock().ok().and_then(|guard| {
+                guard.as_ref().map(|session| {
+                    (
+                        session.clock.active_ms(session.started_at),
+                        session.suspend_reasons,
+                    )
+                })
+            });
+            let Some(tray) = app.tray_by_id(TRAY_ID) else {
+                return;
+            };
+            let recording = status.is_some();
+            if recording != was_recording {
+                let icon = if recording { Some(recording_icon.clone()) } else { idle_icon.clone() };
+                let _ = tray.set_icon(icon);
+                let _ = items.start.set_enabled(!recording);
+                let _ = items.pause.set_enabled(recording);
+                let _ = items.stop.set_enabled(recording);
+                was_recording = recording;
+            }
+            let tooltip = match status {
+                Some((elapsed_ms, reasons)) => {
+                    let _ = items
+                        .pause
+                        .set_text(if reasons & SUSPEND_USER != 0 { "Resume" } else { "Pause" });
+                    let label = if reasons != 0 { "Paused" } else { "Recording" };
+                    format!("Flash Recorder - {label} {}", format_elapsed(elapsed_ms))
+                }
+                None => "Flash Recorder".to_string(),
+            };
+            let _ = tray.set_tooltip(Some(tooltip));
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+}
+
+fn settings_path() -> PathBuf {
+    user_config_dir().join("settings.json")
+}
+
+// Read from disk once; save_settings keeps it current, so hot paths such as
+// ffmpeg_override and work_base_dir don't reread settings.json.
+static SETTINGS_CACHE: RwLock<Option<AppSettings>> = RwLock::new(None);
+
+fn load_settings() -> AppSettings {
+    if let Some(settings) = SETTINGS_CACHE.read().ok().and_then(|cache| cache.clone()) {
+        return settings;
+    }
+    let settings = read_settings_file();
+    if let Ok(mut cache) = SETTINGS_CACHE.write() {
+        *cache = Some(settings.clone());
+    }
+    settings
+}
+
+fn read_settings_file() -> AppSettings {
+    let path = settings_path();
+    // Older builds kept settings.json in the install folder.
+    let data = fs::read_to_string(&path)
+        .or_else(|_| fs::read_to_string(app_data_root().join("settings.json")));
+    data.ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &AppSettings) -> Result<(), String> {
+    let serialized = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    fs::write(path, serialized).map_err(|_| "settings_write_failed".to_string())?;
+    if let Ok(mut cache) = SETTINGS_CACHE.write() {
+        *cache = Some(settings.clone());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_settings() -> Result<AppSettings, AppError> {
+    Ok(load_settings())
+}
+
+// Replaces the whole settings document after checking it.
+#[tauri::command]
+fn update_settings(settings: AppSettings) -> Result<AppSettings, AppError> {
+    validate_ice_servers(&settings.preview.ice_servers)?;
+    validate_retention_policy(&settings.retention)?;
+    for dir in [&settings.directories.work_dir, &settings.directories.export_dir]
+        .into_iter()
+        .flatten()
+    {
+        let path = PathBuf::from(dir);
+        if !dir.trim().is_empty() && (!path.is_absolute() || fs::create_dir_all(&path).is_err()) {
+            return Err(AppError::InvalidDirectory { path: dir.clone() });
+        }
+    }
+    if let Some(name) = settings.defaults.export_preset.as_deref() {
+        if !builtin_export_presets().iter().any(|preset| preset.name == name) {
+            return Err(AppError::UnknownExportPreset { name: name.to_string() });
+        }
+    }
+    if settings.defaults.export_history_max == 0 {
+        return Err("invalid_export_history_max".into());
+    }
+    if let Some(endpoint) = settings.crash_reports.endpoint.as_deref() {
+        if !endpoint.starts_with("https://") {
+            return Err("invalid_crash_endpoint".into());
+        }
+    }
+    if let Some(endpoint) = settings.updates.endpoint.as_deref() {
+        if !endpoint.trim().is_empty() && !endpoint.starts_with("https://") {
+            return Err("invalid_update_endpoint".into());
+        }
+    }
+    if settings.updates.check_interval_hours == 0 {
+        return Err("invalid_update_interval".into());
+    }
+    if let Some(path) = settings.ffmpeg_path.as_deref().filter(|path| !path.trim().is_empty()) {
+        if ffmpeg_version_of(path).is_none() {
+            return Err("invalid_ffmpeg_path".into());
+        }
+    }
+    validate_hotkeys(&settings.hotkeys)?;
+    let old_work_dir = work_base_dir();
+    let new_work_dir = configured_dir(settings.directories.work_dir.clone())
+        .unwrap_or_else(|| app_data_root().join("work"));
+    if new_work_dir != old_work_dir {
+        keep_sessions_reachable(&old_work_dir, &new_work_dir)?;
+    }
+    save_settings(&settings)?;
+    Ok(settings)
+}
+
+// Sessions stay where they are when work_dir changes; they are recorded in
+// session_locations.json like sessions moved by move_session.
+fn keep_sessions_reachable(old_dir: &Path, new_dir: &Path) -> Result<(), String> {
+    let original = read_session_locations();
+    let mut locations = original.clone();
+    for entry in fs::read_dir(old_dir).into_iter().flatten().flatten() {
+        let session_id = entry.file_name().to_string_lossy().to_string();
+        let dir = entry.path();
+        if session_id.starts_with('.')
+            || !is_session_folder(&dir)
+            || new_dir.join(&session_id).exists()
+            || locations.contains_key(&session_id)
+        {
+            continue;
+        }
+        locations.insert(session_id, dir.to_string_lossy().to_string());
+    }
+    // Entries pointing into the new work folder are redundant now.
+    locations.retain(|_, dir| PathBuf::from(dir.as_str()).parent() != Some(new_dir));
+    if locations == original {
+        return Ok(());
+    }
+    let data = serde_json::to_string_pretty(&locations).map_err(|e| e.to_string())?;
+    write_file_atomic(&session_locations_path(), &data)
+}
+
+const HOTKEY_MODIFIERS: &[&str] = &[
+    "cmdorctrl",
+    "commandorcontrol",
+    "ctrl",
+    "control",
+    "cmd",
+    "command",
+    "super",
+    "meta",
+    "alt",
+    "option",
+    "altgr",
+    "shift",
+];
+
+const HOTKEY_NAMED_KEYS: &[&str] = &[
+    "space", "tab", "enter", "return", "escape", "esc", "backspace", "delete", "insert", "home",
+    "end", "pageup", "pagedown", "up", "down", "left", "right", "plus", "minus", "equal",
+    "comma", "period", "slash", "backslash", "semicolon", "quote", "backquote", "bracketleft",
+    "bracketright", "printscreen", "pause", "numlock", "scrolllock", "capslock",
+];
+
+// Accelerators as the global shortcut plugin parses them: modifiers joined
+// with "+", then exactly one key. An empty string leaves the action unbound.
+fn validate_hotkey(accelerator: &str) -> bool {
+    if accelerator.trim().is_empty() {
+        return true;
+    }
+    let parts: Vec<String> = accelerator.split('+').map(|part| part.trim().to_ascii_lowercase()).collect();
+    let Some((key, modifiers)) = parts.split_last() else {
+        return false;
+    };
+    let key_ok = (key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()))
+        || key
+            .strip_prefix('f')
+            .and_then(|n| n.parse::<u8>().ok())
+            .is_some_and(|n| (1..=24).contains(&n))
+        || HOTKEY_NAMED_KEYS.contains(&key.as_str());
+    key_ok && modifiers.iter().all(|modifier| HOTKEY_MODIFIERS.contains(&modifier.as_str()))
+}
+
+fn validate_hotkeys(hotkeys: &HotkeySettings) -> Result<(), String> {
+    let all = [
+        &hotkeys.toggle_recording,
+        &hotkeys.pause_recording,
+        &hotkeys.add_marker,
+    ];
+    for accelerator in all {
+        if !validate_hotkey(accelerator) {
+            return Err(format!("invalid_hotkey: {accelerator}"));
+        }
+    }
+    for (idx, accelerator) in all.iter().enumerate() {
+        let normalized = accelerator.to_ascii_lowercase().replace(' ', "");
+        if !normalized.is_empty()
+            && all[idx + 1..]
+                .iter()
+                .any(|other| other.to_ascii_lowercase().replace(' ', "") == normalized)
+        {
+            return Err(format!("duplicate_hotkey: {accelerator}"));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_preview_audio_muted(
+    preview_state: State<'_, PreviewState>,
+    muted: bool,
+) -> Result<bool, AppError> {
+    let guard = preview_state
+        .inner
+        .lock()
+        .map_err(|_| "preview_state_lock_failed")?;
+    let session = guard.as_ref().ok_or("preview_not_ready")?;
+    if session.audio_task.is_none() {
+        return Err("preview_audio_unavailable".into());
+    }
+    session.audio_muted.store(muted, Ordering::Relaxed);
+    Ok(muted)
+}
+
+#[derive(Serialize)]
+struct CameraPreviewResponse {
+    preview_url: String,
+    camera: String,
+}
+
+// Runs the camera into the WebRTC preview without recording, so the user can
+// frame the shot and pick a camera first. start_recording replaces it.
+#[tauri::command]
+fn start_camera_preview(
+    app: tauri::AppHandle,
+    state: State<'_, RecordingState>,
+    preview_state: State<'_, PreviewState>,
+    camera_device: Option<String>,
+) -> Result<CameraPreviewResponse, AppError> {
+    {
+        let guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
+        if guard.is_some() {
+            return Err("recording_in_progress".into());
+        }
+    }
+    let camera = match camera_device.filter(|d| !d.trim().is_empty()) {
+        Some(device) if device != "auto" && device != "default" => device,
+        _ => list_video_devices_internal(&app)?
+            .into_iter()
+            .next()
+            .ok_or("no_camera_found")?,
+    };
+    {
+        let mut preview_guard = preview_state
+            .inner
+            .lock()
+            .map_err(|_| "preview_state_lock_failed")?;
+        if let Some(existing) = preview_guard.take() {
+            async_runtime::block_on(stop_preview_session(existing));
+        }
+    }
+    let preview_settings = load_settings().preview;
+    let mut session = async_runtime::block_on(create_preview_session(&preview_settings, false))?;
+    let bin = ffmpeg_binary_with_app_handle(&app);
+    let source = PreviewSource::Camera(camera.clone());
+    let child = spawn_preview_relay(&bin, &source, session.rtp_port, &preview_settings.quality)?;
+    session.relay = Some(PreviewRelay { bin, source, child });
+    let mut preview_guard = preview_state
+        .inner
+        .lock()
+        .map_err(|_| "preview_state_lock_failed")?;
+    *preview_guard = Some(session);
+    Ok(CameraPreviewResponse {
+        preview_url: "webrtc://local".to_string(),
+        camera,
+    })
+}
+
+#[tauri::command]
+fn stop_camera_preview(preview_state: State<'_, PreviewState>) -> Result<(), AppError> {
+    let session = {
+        let mut guard = preview_state
+            .inner
+            .lock()
+            .map_err(|_| "preview_state_lock_failed")?;
+        let is_camera = guard
+            .as_ref()
+            .and_then(|session| session.relay.as_ref())
+            .map(|relay| matches!(relay.source, PreviewSource::Camera(_)))
+            .unwrap_or(false);
+        if !is_camera {
+            return Ok(());
+        }
+        guard.take()
+    };
+    if let Some(session) = session {
+        async_runtime::block_on(stop_preview_session(session));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_preview_quality(
+    preview_state: State<'_, PreviewState>,
+    quality: PreviewQuality,
+) -> Result<PreviewQuality, AppError> {
+    let quality = clamp_preview_quality(&quality);
+    let mut settings = load_settings();
+    settings.preview.quality = quality.clone();
+    save_settings(&settings)?;
+
+    let mut guard = preview_state
+        .inner
+        .lock()
+        .map_err(|_| "preview_state_lock_failed")?;
+    let Some(session) = guard.as_mut() else {
+        return Ok(quality);
+    };
+    let rtp_port = session.rtp_port;
+    if let Some(relay) = session.relay.as_mut() {
+        // Only the relay restarts; the recorder keeps feeding the same port.
+        let _ = relay.child.kill();
+        let _ = relay.child.wait();
+        relay.child = spawn_preview_relay(&relay.bin, &relay.source, rtp_port, &quality)?;
+    }
+    Ok(quality)
+}
+
+fn validate_ice_servers(servers: &[IceServerConfig]) -> Result<(), String> {
+    for server in servers.iter() {
+        let valid = server.urls.iter().all(|url| {
+            url.starts_with("stun:") || url.starts_with("turn:") || url.starts_with("turns:")
+        });
+        if !valid {
+            return Err("invalid_ice_server_url".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_preview_settings() -> Result<PreviewSettings, AppError> {
+    Ok(load_settings().preview)
+}
+
+#[tauri::command]
+fn set_preview_settings(preview: PreviewSettings) -> Result<PreviewSettings, AppError> {
+    validate_ice_servers(&preview.ice_servers)?;
+    let mut settings = load_settings();
+    settings.preview = preview;
+    save_settings(&settings)?;
+    Ok(settings.preview)
+}
+
+fn recording_presets_path() -> PathBuf {
+    app_data_root().join("recording_presets.json")
+}
+
+fn builtin_recording_presets() -> Vec<RecordingPreset> {
+    let preset = |name: &str, resolution: &str, fps: u32, speed_factor: Option<f32>| RecordingPreset {
+        name: name.to_string(),
+        request: StartRecordingRequest {
+            resolution: resolution.to_string(),
+            fps,
+            format: "h264".to_string(),
+            mic_device: Some("auto".to_string()),
+            camera_device: Some("auto".to_string()),
+            capture_mode: Some("screen".to_string()),
+            window_title: None,
+            region: None,
+            speed_factor,
+            hide_cursor: None,
+        },
+    };
+    let mut quick_clip = preset("Quick clip", "720p", 30, None);
+    quick_clip.request.camera_device = Some("off".to_string());
+    vec![
+        preset("Full HD tutorial", "1080p", 30, None),
+        preset("4K demo", "2160p", 60, None),
+        quick_clip,
+    ]
+}
+
+// The last recording's devices, mode, region and fps, or the first built-in
+// preset before anything was recorded.
+#[tauri::command]
+fn get_default_recording_request() -> Result<StartRecordingRequest, AppError> {
+    if let Some(request) = load_settings().last_recording {
+        return Ok(request);
+    }
+    Ok(builtin_recording_presets().remove(0).request)
+}
+
+fn load_recording_presets() -> Vec<RecordingPreset> {
+    fs::read_to_string(recording_presets_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_else(builtin_recording_presets)
+}
+
+fn write_recording_presets(presets: &[RecordingPreset]) -> Result<(), String> {
+    let path = recording_presets_path();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let serialized = serde_json::to_string_pretty(presets).map_err(|e| e.to_string())?;
+    fs::write(path, serialized).map_err(|_| "presets_write_failed".to_string())
+}
+
+#[tauri::command]
+fn list_recording_presets() -> Result<Vec<RecordingPreset>, AppError> {
+    Ok(load_recording_presets())
+}
+
+#[tauri::command]
+fn save_recording_preset(preset: RecordingPreset) -> Result<Vec<RecordingPreset>, AppError> {
+    if preset.name.trim().is_empty() {
+        return Err("preset_name_required".into());
+    }
+    let mut presets = load_recording_presets();
+    if let Some(existing) = presets.iter_mut().find(|p| p.name == preset.name) {
+        *existing = preset;
+    } else {
+        presets.push(preset);
+    }
+    write_recording_presets(&presets)?;
+    Ok(presets)
+}
+
+#[tauri::command]
+fn delete_recording_preset(name: String) -> Result<Vec<RecordingPreset>, AppError> {
+    let mut presets = load_recording_presets();
+    let before = presets.len();
+    presets.retain(|p| p.name != name);
+    if presets.len() == before {
+        return Err("preset_not_found".into());
+    }
+    write_recording_presets(&presets)?;
+    Ok(presets)
+}
+
+#[tauri::command]
+fn apply_recording_preset(
+    app: tauri::AppHandle,
+    state: State<RecordingState>,
+    preview_state: State<PreviewState>,
+    name: String,
+) -> Result<StartRecordingResponse, AppError> {
+    let preset = load_recording_presets()
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or("preset_not_found")?;
+    start_recording(app, state, preview_state, preset.request)
+}
+
+#[derive(Serialize)]
+struct CaptureDiagnosticsReport {
+    ok: bool,
+    file_valid: bool,
+    duration_ms: u64,
+    expected_fps: u32,
+    achieved_fps: Option<f32>,
+    resolution: Option<String>,
+    has_audio: bool,
+    audio_expected: bool,
+    mean_volume_db: Option<f32>,
+    black_ratio: f32,
+    camera_valid: Option<bool>,
+    issues: Vec<String>,
+    log_tail: String,
+}
+
+struct SignalStats {
+    frames: Option<u64>,
+    black_s: f64,
+    mean_volume_db: Option<f32>,
+}
+
+fn analyze_signal(app: &tauri::AppHandle, input_path: &str) -> SignalStats {
+    let output = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args([
+            "-hide_banner",
+            "-i",
+            input_path,
+            "-vf",
+            "blackdetect=d=0.2:pix_th=0.10",
+            "-af",
+            "volumedetect",
+            "-f",
+            "null",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output();
+    let Ok(output) = output else {
+        return SignalStats {
+            frames: None,
+            black_s: 0.0,
+            mean_volume_db: None,
+        };
+    };
+    let text = String::from_utf8_lossy(&output.stderr).to_string();
+    let frames = text.rfind("frame=").and_then(|index| {
+        text[index + "frame=".len()..]
+            .trim_start()
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .and_then(|v| v.parse::<u64>().ok())
+    });
+    let mut black_s = 0.0;
+    let mut mean_volume_db = None;
+    for line in text.lines() {
+        if let Some(index) = line.find("black_duration:") {
+            let value = line[index + "black_duration:".len()..]
+                .split_whitespace()
+                .next()
+                .and_then(|v| v.parse::<f64>().ok());
+            black_s += value.unwrap_or(0.0);
+        }
+        if let Some(index) = line.find("mean_volume:") {
+            mean_volume_db = line[index + "mean_volume:".len()..]
+                .trim()
+                .trim_end_matches("dB")
+                .trim()
+                .parse::<f32>()
+                .ok();
+        }
+    }
+    SignalStats {
+        frames,
+        black_s,
+        mean_volume_db,
+    }
+}
+
+fn log_tail(path: &PathBuf, lines: usize) -> String {
+    let data = fs::read_to_string(path).unwrap_or_default();
+    data.lines()
+        .rev()
+        .take(lines)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Records a short take with the given settings, inspects it and throws the
+// session away again.
+#[tauri::command]
+async fn run_capture_diagnostics(
+    app: tauri::AppHandle,
+    request: StartRecordingRequest,
+) -> Result<CaptureDiagnosticsReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || capture_diagnostics(&app, request))
+        .await
+        .map_err(|_| "diagnostics_join_failed".to_string())?
+        .map_err(AppError::from)
+}
+
+fn capture_diagnostics(
+    app: &tauri::AppHandle,
+    request: StartRecordingRequest,
+) -> Result<CaptureDiagnosticsReport, String> {
+    let expected_fps = if request.fps == 0 { 60 } else { request.fps };
+    let audio_expected = request
+        .mic_device
+        .as_deref()
+        .map(|d| d != "mute" && !d.trim().is_empty())
+        .unwrap_or(true)
+        && request.speed_factor.map(|f| f <= 1.0).unwrap_or(true);
+    let started = start_recording_session(app.clone(), app.state(), app.state(), request, None)?;
+    thread::sleep(Duration::from_secs(3));
+    stop_recording_internal(app.state(), app.state())?;
+    let output_path = PathBuf::from(&started.output_path);
+    let session_dir = output_path
+        .parent()
+        .map(|p| p.to_path_buf())
+        .ok_or("invalid_output_path")?;
+
+    let probe = probe_media(app, &started.output_path).unwrap_or_default();
+    let file_size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+    let duration_ms = probe.duration_ms.unwrap_or(0);
+    let file_valid = file_size > 0 && probe.has_video && duration_ms > 0;
+    let stats = if file_valid {
+        analyze_signal(app, &started.output_path)
+    } else {
+        SignalStats {
+            frames: None,
+            black_s: 0.0,
+            mean_volume_db: None,
+        }
+    };
+    let achieved_fps = stats
+        .frames
+        .filter(|_| duration_ms > 0)
+        .map(|frames| frames as f32 / (duration_ms as f32 / 1000.0));
+    let black_ratio = if duration_ms > 0 {
+        ((stats.black_s * 1000.0) / duration_ms as f64).min(1.0) as f32
+    } else {
+        0.0
+    };
+    let camera_valid = started.camera_path.as_ref().map(|path| {
+        probe_media(app, path)
+            .map(|p| p.has_video && p.duration_ms.unwrap_or(0) > 0)
+            .unwrap_or(false)
+    });
+
+    let mut issues = Vec::new();
+    if !file_valid {
+        issues.push("recording_file_invalid".to_string());
+    }
+    if let Some(fps) = achieved_fps {
+        if fps < expected_fps as f32 * 0.8 {
+            issues.push("low_fps".to_string());
+        }
+    }
+    if black_ratio > 0.9 {
+        issues.push("video_black".to_string());
+    }
+    if audio_expected && !probe.has_audio {
+        issues.push("audio_missing".to_string());
+    }
+    if probe.has_audio && stats.mean_volume_db.map(|v| v < -60.0).unwrap_or(false) {
+        issues.push("audio_silent".to_string());
+    }
+    if camera_valid == Some(false) {
+        issues.push("camera_file_invalid".to_string());
+    }
+    let report = CaptureDiagnosticsReport {
+        ok: issues.is_empty(),
+        file_valid,
+        duration_ms,
+        expected_fps,
+        achieved_fps,
+        resolution: probe
+            .width
+            .zip(probe.height)
+            .map(|(w, h)| format!("{w}x{h}")),
+        has_audio: probe.has_audio,
+        audio_expected,
+        mean_volume_db: stats.mean_volume_db,
+        black_ratio,
+        camera_valid,
+        issues,
+        log_tail: log_tail(&PathBuf::from(&started.log_path), 12),
+    };
+    let _ = fs::remove_dir_all(&session_dir);
+    Ok(report)
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct CapabilityProbe {
+    ffmpeg_path: String,
+    ffmpeg_version: Option<String>,
+    encoders: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct RecentError {
+    session_id: String,
+    modified_ms: u64,
+    lines: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct AppStateSnapshot {
+    version: u32,
+    created_at_ms: u64,
+    app_version: String,
+    os: String,
+    arch: String,
+    settings: AppSettings,
+    audio_devices: Vec<String>,
+    video_devices: Vec<String>,
+    capabilities: CapabilityProbe,
+    export_jobs: Vec<ExportStatus>,
+    recent_errors: Vec<RecentError>,
+}
+
+const APP_STATE_SNAPSHOT_VERSION: u32 = 1;
+
+fn probe_capabilities(app: &tauri::AppHandle) -> CapabilityProbe {
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let run = |args: &[&str]| {
+        new_cmd(&bin)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    let ffmpeg_version = run(&["-hide_banner", "-version"])
+        .and_then(|text| text.lines().next().map(|line| line.trim().to_string()));
+    let wanted = [
+        "libx264", "libx265", "h264_nvenc", "hevc_nvenc", "h264_qsv", "hevc_qsv", "h264_amf",
+        "hevc_amf", "aac", "libopus",
+    ];
+    let encoders = run(&["-hide_banner", "-encoders"])
+        .map(|text| {
+            text.lines()
+                .filter_map(|line| line.split_whitespace().nth(1))
+                .filter(|name| wanted.contains(name))
+                .map(|name| name.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    CapabilityProbe {
+        ffmpeg_path: bin,
+        ffmpeg_version,
+        encoders,
+    }
+}
+
+// First line of `-version`, or None when the binary doesn't run as ffmpeg.
+fn ffmpeg_version_of(bin: &str) -> Option<String> {
+    let output = new_cmd(bin)
+        .args(["-hide_banner", "-version"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    text.lines()
+        .next()
+        .filter(|line| line.starts_with("ffmpeg version"))
+        .map(|line| line.trim().to_string())
+}
+
+#[derive(Serialize)]
+struct FfmpegInfo {
+    path: String,
+    // "settings" or "bundled".
+    source: String,
+    valid: bool,
+    version: Option<String>,
+    encoders: Vec<String>,
+    hardware_encoders: Vec<String>,
+    audio_devices: Vec<String>,
+    video_devices: Vec<String>,
+    error: Option<String>,
+}
+
+// Which ffmpeg is in use and whether it works. Never fails: a missing or
+// broken binary is reported through valid and error.
+#[tauri::command]
+async fn get_ffmpeg_info(app: tauri::AppHandle) -> Result<FfmpegInfo, AppError> {
+    Ok(tauri::async_runtime::spawn_blocking(move || ffmpeg_info(&app))
+        .await
+        .map_err(|_| "ffmpeg_info_join_failed".to_string())?)
+}
+
+fn ffmpeg_info(app: &tauri::AppHandle) -> FfmpegInfo {
+    let path = ffmpeg_binary_with_app_handle(app);
+    let source = if ffmpeg_override().is_some() { "settings" } else { "bundled" };
+    let version = ffmpeg_version_of(&path);
+    let error = if !PathBuf::from(&path).is_file() {
+        Some("ffmpeg_not_found".to_string())
+    } else if version.is_none() {
+        Some("ffmpeg_not_runnable".to_string())
+    } else {
+        None
+    };
+    let valid = error.is_none();
+    FfmpegInfo {
+        encoders: if valid { probe_capabilities(app).encoders } else { Vec::new() },
+        hardware_encoders: get_hardware_encoders(),
+        audio_devices: valid.then(|| list_audio_devices_internal(app).ok()).flatten().unwrap_or_default(),
+        video_devices: valid.then(|| list_video_devices_internal(app).ok()).flatten().unwrap_or_default(),
+        path,
+        source: source.to_string(),
+        valid,
+        version,
+        error,
+    }
+}
+
+// Strips anything that identifies the user: profile paths and ICE credentials.
+fn sanitize_support_text(text: &str) -> String {
+    let mut out = text.to_string();
+    for var in ["USERPROFILE", "HOME"] {
+        if let Ok(value) = env::var(var) {
+            if !value.trim().is_empty() {
+                out = out.replace(&value, &format!("%{var}%"));
+            }
+        }
+    }
+    if let Ok(user) = env::var("USERNAME") {
+        if user.len() > 2 {
+            out = out.replace(&user, "%USERNAME%");
+        }
+    }
+    out
+}
+
+const UPDATE_ENDPOINT: &str =
+    "https://github.com/huangwenxuangod/flash-recorder/releases/latest/download/latest.json";
+
+#[derive(Deserialize)]
+struct UpdateManifest {
+    version: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    pub_date: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct UpdateInfo {
+    current_version: String,
+    latest_version: String,
+    update_available: bool,
+    // Release notes from the manifest.
+    changelog: Option<String>,
+    pub_date: Option<String>,
+    checked_at_ms: u64,
+}
+
+// Numeric dotted compare; a leading "v" and any pre-release suffix are ignored.
+fn version_newer(latest: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim()
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (latest, current) = (parts(latest), parts(current));
+    for idx in 0..latest.len().max(current.len()) {
+        let (a, b) = (latest.get(idx).copied().unwrap_or(0), current.get(idx).copied().unwrap_or(0));
+        if a != b {
+            return a > b;
+        }
+    }
+    false
+}
+
+fn fetch_update_info(current_version: &str) -> Result<UpdateInfo, String> {
+    let endpoint = load_settings()
+        .updates
+        .endpoint
+        .filter(|endpoint| !endpoint.trim().is_empty())
+        .unwrap_or_else(|| UPDATE_ENDPOINT.to_string());
+    let output = new_cmd("curl")
+        .args(["-fsSL", "--max-time", "20"])
+        .arg(&endpoint)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|_| "update_check_failed")?;
+    if !output.status.success() {
+        return Err("update_check_failed".to_string());
+    }
+    let manifest: UpdateManifest =
+        serde_json::from_slice(&output.stdout).map_err(|_| "update_manifest_invalid")?;
+    Ok(UpdateInfo {
+        update_available: version_newer(&manifest.version, current_version),
+        current_version: current_version.to_string(),
+        latest_version: manifest.version.trim_start_matches('v').to_string(),
+        changelog: manifest.notes,
+        pub_date: manifest.pub_date,
+        checked_at_ms: unix_now_ms(),
+    })
+}
+
+// Reads the release manifest only; installing stays with the updater plugin.
+#[tauri::command]
+async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, AppError> {
+    let current_version = app.package_info().version.to_string();
+    tauri::async_runtime::spawn_blocking(move || fetch_update_info(&current_version))
+        .await
+        .map_err(|_| "update_check_join_failed".to_string())?
+        .map_err(AppError::from)
+}
+
+// Checks shortly after startup and then every check_interval_hours, emitting
+// update_available when a newer version is out. Settings are re-read each
+// round so turning the check off takes effect without a restart.
+fn spawn_update_checker(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        let current_version = app.package_info().version.to_string();
+        thread::sleep(Duration::from_secs(60));
+        loop {
+            let settings = load_settings().updates;
+            if settings.background_check {
+                if let Ok(info) = fetch_update_info(&current_version) {
+                    if info.update_available {
+                        let _ = app.emit("update_available", info);
+                    }
+                }
+            }
+            thread::sleep(Duration::from_secs(u64::from(settings.check_interval_hours.max(1)) * 3600));
+        }
+    });
+}
+
+fn crash_dir() -> PathBuf {
+    user_config_dir().join("crashes")
+}
+
+// Log tails of the most recently touched session, which is the recording in
+// progress when a crash hits mid-capture.
+fn latest_session_logs(lines: usize) -> String {
+    let latest = fs::read_dir(work_base_dir()).ok().and_then(|entries| {
+        entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .max_by_key(|entry| entry.metadata().and_then(|meta| meta.modified()).ok())
+    });
+    let Some(entry) = latest else {
+        return String::new();
+    };
+    let mut out = format!("session: {}\n", entry.file_name().to_string_lossy());
+    for name in ["ffmpeg.log", "error.log"] {
+        let tail = log_tail(&entry.path().join(name), lines);
+        if !tail.is_empty() {
+            out.push_str(&format!("--- {name} ---\n{tail}\n"));
+        }
+    }
+    out
+}
+
+// Writes the panic and a backtrace, then hands over to the default hook.
+// Session logs and sanitizing are left to complete_crash_reports on the next
+// launch so the hook itself does one write and nothing else.
+fn install_crash_handler() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        let time_ms = unix_now_ms();
+        let report = format!(
+            "version: {}\nos: {} {}\nthread: {}\ntime_ms: {time_ms}\npanic: {info}\n\nbacktrace:\n{backtrace}\n",
+            env!("CARGO_PKG_VERSION"),
+            env::consts::OS,
+            env::consts::ARCH,
+            thread::current().name().unwrap_or("unnamed"),
+        );
+        let dir = crash_dir();
+        if fs::create_dir_all(&dir).is_ok() {
+            let _ = fs::write(dir.join(format!("crash-{time_ms}.txt")), report);
+        }
+        default_hook(info);
+    }));
+}
+
+// Adds the latest session logs to reports written by the panic hook and
+// strips user details from them. Runs at startup, before a new recording can
+// replace the session the crash happened in.
+fn complete_crash_reports() {
+    let Ok(entries) = fs::read_dir(crash_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+            continue;
+        }
+        let Ok(report) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if report.contains("\nlogs:\n") {
+            continue;
+        }
+        let report = format!("{report}\nlogs:\n{}", latest_session_logs(50));
+        let _ = write_file_atomic(&path, &sanitize_support_text(&report));
+    }
+}
+
+#[derive(Serialize)]
+struct CrashReport {
+    id: String,
+    created_ms: u64,
+    // The panic line of the report.
+    summary: String,
+    uploaded: bool,
+}
+
+fn crash_report_path(id: &str) -> Result<PathBuf, String> {
+    if !id.starts_with("crash-") || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err("crash_report_not_found".to_string());
+    }
+    let path = crash_dir().join(format!("{id}.txt"));
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err("crash_report_not_found".to_string())
+    }
+}
+
+#[tauri::command]
+fn list_crash_reports() -> Result<Vec<CrashReport>, AppError> {
+    let Ok(entries) = fs::read_dir(crash_dir()) else {
+        return Ok(Vec::new());
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let id = name.strip_suffix(".txt")?.to_string();
+            let created_ms = id.strip_prefix("crash-")?.parse().ok()?;
+            let data = fs::read_to_string(entry.path()).ok()?;
+            let summary = data
+                .lines()
+                .find_map(|line| line.strip_prefix("panic: "))
+                .unwrap_or_default()
+                .to_string();
+            Some(CrashReport {
+                uploaded: crash_dir().join(format!("{id}.uploaded")).exists(),
+                id,
+                created_ms,
+                summary,
+            })
+        })
+        .collect();
+    reports.sort_by(|a, b| b.created_ms.cmp(&a.created_ms));
+    Ok(reports)
+}
+
+#[tauri::command]
+fn get_crash_report(id: String) -> Result<String, AppError> {
+    fs::read_to_string(crash_report_path(&id)?).map_err(|_| "crash_report_not_found".into())
+}
+
+#[tauri::command]
+fn delete_crash_report(id: String) -> Result<(), AppError> {
+    let path = crash_report_path(&id)?;
+    let _ = fs::remove_file(crash_dir().join(format!("{id}.uploaded")));
+    fs::remove_file(path).map_err(|_| "crash_report_delete_failed".into())
+}
+
+#[tauri::command]
+async fn upload_crash_report(id: String) -> Result<(), AppError> {
+    tauri::async_runtime::spawn_blocking(move || send_crash_report(&id))
+        .await
+        .map_err(|_| "crash_upload_join_failed".to_string())?
+        .map_err(AppError::from)
+}
+
+// Posts one report to the configured endpoint. Refuses unless the user has
+// opted in.
+fn send_crash_report(id: &str) -> Result<(), String> {
+    let path = crash_report_path(id)?;
+    let settings = load_settings().crash_reports;
+    if !settings.upload {
+        return Err("crash_upload_disabled".to_string());
+    }
+    let endpoint = settings
+        .endpoint
+        .filter(|endpoint| !endpoint.trim().is_empty())
+        .ok_or("crash_endpoint_missing")?;
+    let status = new_cmd("curl")
+        .args(["-fsS", "--max-time", "30", "-H", "Content-Type: text/plain", "--data-binary"])
+        .arg(format!("@{}", path.to_string_lossy()))
+        .arg(&endpoint)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|_| "crash_upload_failed")?;
+    if !status.success() {
+        return Err("crash_upload_failed".to_string());
+    }
+    let _ = fs::write(crash_dir().join(format!("{id}.uploaded")), unix_now_ms().to_string());
+    Ok(())
+}
+
+// Sends reports left by earlier runs, when the user has opted in.
+fn upload_pending_crash_reports() {
+    if !load_settings().crash_reports.upload {
+        return;
+    }
+    for report in list_crash_reports().unwrap_or_default() {
+        if !report.uploaded {
+            let _ = send_crash_report(&report.id);
+        }
+    }
+}
+
+fn sanitize_settings(settings: &AppSettings) -> AppSettings {
+    let mut settings = settings.clone();
+    for server in settings.preview.ice_servers.iter_mut() {
+        if server.username.is_some() {
+            server.username = Some("<redacted>".to_string());
+        }
+        if server.credential.is_some() {
+            server.credential = Some("<redacted>".to_string());
+        }
+    }
+    settings
+}
+
+fn recent_session_errors(limit: usize) -> Vec<RecentError> {
+    let mut errors = Vec::new();
+    if let Ok(entries) = fs::read_dir(work_base_dir()) {
+        for entry in entries.flatten() {
+            let log_path = entry.path().join("error.log");
+            let modified_ms = match fs::metadata(&log_path).and_then(|meta| meta.modified()) {
+                Ok(modified) => modified
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0),
+                Err(_) => continue,
+            };
+            errors.push(RecentError {
+                session_id: entry.file_name().to_string_lossy().to_string(),
+                modified_ms,
+                lines: sanitize_support_text(&log_tail(&log_path, 20)),
+            });
+        }
+    }
+    errors.sort_by(|a, b| b.modified_ms.cmp(&a.modified_ms));
+    errors.truncate(limit);
+    errors
+}
+
+#[tauri::command]
+fn export_app_state(
+    app: tauri::AppHandle,
+    export_state: State<ExportState>,
+    output_path: Option<String>,
+) -> Result<String, AppError> {
+    let export_jobs = {
+        let guard = export_state
+            .inner
+            .lock()
+            .map_err(|_| "export_state_lock_failed")?;
+        guard
+            .statuses
+            .values()
+            .cloned()
+            .map(|mut status| {
+                status.error = status.error.map(|e| sanitize_support_text(&e));
+                status.output_path = status.output_path.map(|p| sanitize_support_text(&p));
+                status
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut capabilities = probe_capabilities(&app);
+    capabilities.ffmpeg_path = sanitize_support_text(&capabilities.ffmpeg_path);
+    let snapshot = AppStateSnapshot {
+        version: APP_STATE_SNAPSHOT_VERSION,
+        created_at_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_millis() as u64,
+        app_version: app.package_info().version.to_string(),
+        os: env::consts::OS.to_string(),
+        arch: env::consts::ARCH.to_string(),
+        settings: sanitize_settings(&load_settings()),
+        audio_devices: list_audio_devices_internal(&app).unwrap_or_default(),
+        video_devices: list_video_devices_internal(&app).unwrap_or_default(),
+        capabilities,
+        export_jobs,
+        recent_errors: recent_session_errors(10),
+    };
+    let path = match output_path {
+        Some(path) if !path.trim().is_empty() => PathBuf::from(path),
+        _ => {
+            let dir = app_data_root().join("support");
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            dir.join(format!("app_state_{}.json", snapshot.created_at_ms))
+        }
+    };
+    let data = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    fs::write(&path, data).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Loads a snapshot from a user report. With apply_settings the reported
+// settings replace ours, keeping local ICE credentials where urls match.
+#[tauri::command]
+fn import_app_state(path: String, apply_settings: Option<bool>) -> Result<AppStateSnapshot, AppError> {
+    let data = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let snapshot: AppStateSnapshot =
+        serde_json::from_str(&data).map_err(|_| "invalid_app_state".to_string())?;
+    if snapshot.version > APP_STATE_SNAPSHOT_VERSION {
+        return Err("unsupported_app_state_version".into());
+    }
+    if apply_settings.unwrap_or(false) {
+        let current = load_settings();
+        let mut settings = snapshot.settings.clone();
+        for server in settings.preview.ice_servers.iter_mut() {
+            let local = current
+                .preview
+                .ice_servers
+                .iter()
+                .find(|candidate| candidate.urls == server.urls);
+            server.username = local.and_then(|s| s.username.clone());
+            server.credential = local.and_then(|s| s.credential.clone());
+        }
+        save_settings(&settings)?;
+    }
+    Ok(snapshot)
+}
+
+#[tauri::command]
+fn list_audio_devices(app: tauri::AppHandle) -> Result<Vec<String>, AppError> {
+    Ok(list_audio_devices_internal(&app)?)
+}
+
+fn list_audio_devices_internal(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let (stderr_output, stdout_output) = new_cmd(&bin)
+        .args(["-list_devices", "true", "-f", "dshow", "-i", "dummy"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            let mut stderr_bytes = Vec::new();
+            if let Some(mut stderr_reader) = child.stderr.take() {
+                let _ = stderr_reader.read_to_end(&mut stderr_bytes);
+            }
+            let mut stdout_bytes = Vec::new();
+            if let Some(mut stdout_reader) = child.stdout.take() {
+                let _ = stdout_reader.read_to_end(&mut stdout_bytes);
+            }
+            let _ = child.wait();
+            let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
+            let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
+            Ok((stderr, stdout))
+        })
+        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
+
+    let combined = format!("{stderr_output}\n{stdout_output}");
+    Ok(parse_dshow_audio_devices(&combined))
+}
+
+#[tauri::command]
+fn list_video_devices(app: tauri::AppHandle) -> Result<Vec<String>, AppError> {
+    Ok(list_video_devices_internal(&app)?)
+}
+
+#[tauri::command]
+fn list_windows() -> Result<Vec<String>, AppError> {
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            EnumWindows, GetWindowTextLengthW, GetWindowTextW, IsWindowVisible,
+        };
+
+        unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            if IsWindowVisible(hwnd) == 0 {
+                return 1;
+            }
+            let length = GetWindowTextLengthW(hwnd);
+            if length == 0 {
+                return 1;
+            }
+            let mut buffer = vec![0u16; (length + 1) as usize];
+            let written = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+            if written <= 0 {
+                return 1;
+            }
+            let title = String::from_utf16_lossy(&buffer[..written as usize]);
+            let trimmed = title.trim();
+            if trimmed.is_empty() {
+                return 1;
+            }
+            let titles = unsafe { &mut *(lparam as *mut Vec<String>) };
+            if !titles.iter().any(|item| item == trimmed) {
+                titles.push(trimmed.to_string());
+            }
+            1
+        }
+
+        let mut titles: Vec<String> = Vec::new();
+        let result = unsafe {
+            EnumWindows(Some(enum_windows_proc), &mut titles as *mut _ as LPARAM)
+        };
+        if result == 0 {
+            return Err("list_windows_failed".into());
+        }
+        if titles.is_empty() {
+            return Ok(Vec::new());
+        }
+        titles.sort();
+        return Ok(titles);
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+fn list_video_devices_internal(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let (stderr_output, stdout_output) = new_cmd(&bin)
+        .args(["-list_devices", "true", "-f", "dshow", "-i", "dummy"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            let mut stderr_bytes = Vec::new();
+            if let Some(mut stderr_reader) = child.stderr.take() {
+                let _ = stderr_reader.read_to_end(&mut stderr_bytes);
+            }
+            let mut stdout_bytes = Vec::new();
+            if let Some(mut stdout_reader) = child.stdout.take() {
+                let _ = stdout_reader.read_to_end(&mut stdout_bytes);
+            }
+            let _ = child.wait();
+            let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
+            let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
+            Ok((stderr, stdout))
+        })
+        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
+
+    let combined = format!("{stderr_output}\n{stdout_output}");
+    Ok(parse_dshow_video_devices(&combined))
+}
+
+fn parse_dshow_audio_devices(stderr: &str) -> Vec<String> {
+    let mut devices = Vec::new();
+    let mut in_audio = false;
+    for line in stderr.lines() {
+        if line.contains("DirectShow audio devices") {
+            in_audio = true;
+            continue;
+        }
+        if line.contains("DirectShow video devices") {
+            in_audio = false;
+            continue;
+        }
+        if !in_audio && !line.contains("(audio)") {
+            continue;
+        }
+        if line.contains("(none)") {
+            continue;
+        }
+        if let Some(start) = line.find('"') {
+            let rest = &line[start + 1..];
+            if let Some(end) = rest.find('"') {
+                let name = rest[..end].trim();
+                if !name.is_empty() && !devices.iter().any(|item| item == name) {
+                    devices.push(name.to_string());
+                }
+            }
+        }
+    }
+    devices
+}
+
+fn parse_dshow_video_devices(stderr: &str) -> Vec<String> {
+    let mut devices = Vec::new();
+    let mut in_video = false;
+    for line in stderr.lines() {
+        if line.contains("DirectShow video devices") {
+            in_video = true;
+            continue;
+        }
+        if line.contains("DirectShow audio devices") {
+            in_video = false;
+            continue;
+        }
+        if !in_video && !line.contains("(video)") {
+            continue;
+        }
+        if line.contains("(none)") {
+            continue;
+        }
+        if let Some(start) = line.find('"') {
+            let rest = &line[start + 1..];
+            if let Some(end) = rest.find('"') {
+                let name = rest[..end].trim();
+                if !name.is_empty() && !devices.iter().any(|item| item == name) {
+                    devices.push(name.to_string());
+                }
+            }
+        }
+    }
+    devices
+}
+
+fn write_edit_state(path: &Path, edit_state: &EditState) -> Result<(), String> {
+    let value = to_versioned_value(edit_state, EDIT_STATE_MIGRATIONS)?;
+    let serialized = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+    fs::write(path, serialized).map_err(|e| e.to_string())?;
+    refresh_project_json(path)
+}
+
+fn read_edit_state(path: &Path) -> Result<EditState, String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut value: serde_json::Value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+    apply_migrations(&mut value, EDIT_STATE_MIGRATIONS);
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+// edit_state.json is the last used layout; a copy is also kept per aspect so
+// switching aspect and back restores that layout.
+#[tauri::command]
+fn save_edit_state(output_path: String, edit_state: EditState) -> Result<(), AppError> {
+    if let Ok(path) = aspect_edit_state_path(&output_path, &edit_state.aspect) {
+        write_edit_state(&path, &edit_state)?;
+    }
+    Ok(write_edit_state(&edit_state_path(&output_path), &edit_state)?)
+}
+
+#[tauri::command]
+fn load_edit_state(output_path: String) -> Result<EditState, AppError> {
+    let path = edit_state_path(&output_path);
+    if !path.exists() {
+        return Ok(EditState::default());
+    }
+    Ok(read_edit_state(&path)?)
+}
+
+#[tauri::command]
+fn save_edit_state_for_aspect(
+    output_path: String,
+    aspect: String,
+    mut edit_state: EditState,
+) -> Result<(), AppError> {
+    let path = aspect_edit_state_path(&output_path, &aspect)?;
+    edit_state.aspect = aspect;
+    Ok(write_edit_state(&path, &edit_state)?)
+}
+
+// Falls back to the last used layout re-targeted to the aspect, so a new
+// aspect starts from the current look.
+#[tauri::command]
+fn load_edit_state_for_aspect(output_path: String, aspect: String) -> Result<EditState, AppError> {
+    let path = aspect_edit_state_path(&output_path, &aspect)?;
+    if path.exists() {
+        return Ok(read_edit_state(&path)?);
+    }
+    let mut edit_state = load_edit_state(output_path)?;
+    edit_state.aspect = aspect;
+    Ok(edit_state)
+}
+
+#[derive(Serialize)]
+struct FileMigrationReport {
+    file: String,
+    from_version: u32,
+    to_version: u32,
+    changes: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SessionMigrationReport {
+    session_id: String,
+    dry_run: bool,
+    files: Vec<FileMigrationReport>,
+}
+
+fn session_migration_targets() -> Vec<(&'static str, &'static [JsonMigration])> {
+    vec![
+        ("edit_state.json", EDIT_STATE_MIGRATIONS),
+        ("edit_state_16x9.json", EDIT_STATE_MIGRATIONS),
+        ("edit_state_1x1.json", EDIT_STATE_MIGRATIONS),
+        ("edit_state_9x16.json", EDIT_STATE_MIGRATIONS),
+        ("clip_track.json", CLIP_TRACK_MIGRATIONS),
+        ("camera_track.json", CAMERA_TRACK_MIGRATIONS),
+        ("project.json", PROJECT_MIGRATIONS),
+    ]
+}
+
+// Everything a session edit consists of, in one portable document. The loose
+// per-track files stay the working copies the renderer reads; project.json is
+// rewritten whenever one of them is saved, and save_project writes them out
+// from a project copied in on its own.
+#[derive(Serialize, Deserialize, Default)]
+struct Project {
+    #[serde(default)]
+    edit_state: Option<EditState>,
+    #[serde(default)]
+    clip_track: Option<ClipTrack>,
+    #[serde(default)]
+    camera_track: Option<CameraTrack>,
+    #[serde(default)]
+    zoom_track: Option<ZoomTrack>,
+    #[serde(default)]
+    crop_track: Option<CropTrack>,
+    #[serde(default)]
+    audio_track: Option<AudioTrack>,
+    #[serde(default)]
+    capture: Option<CaptureMeta>,
+}
+
+fn read_json_file<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn member_json<T: Serialize>(member: &T, migrations: Option<&[JsonMigration]>) -> Result<String, String> {
+    let value = match migrations {
+        Some(migrations) => to_versioned_value(member, migrations)?,
+        None => serde_json::to_value(member).map_err(|e| e.to_string())?,
+    };
+    serde_json::to_string(&value).map_err(|_| "project_serialize_failed".to_string())
+}
+
+// Writes the loose files for the members that are set.
+fn write_project_files(dir: &Path, project: &Project) -> Result<(), String> {
+    let mut files: Vec<(&str, String)> = Vec::new();
+    if let Some(edit_state) = project.edit_state.as_ref() {
+        files.push(("edit_state.json", member_json(edit_state, Some(EDIT_STATE_MIGRATIONS))?));
+    }
+    if let Some(track) = project.clip_track.as_ref() {
+        files.push(("clip_track.json", member_json(track, Some(CLIP_TRACK_MIGRATIONS))?));
+    }
+    if let Some(track) = project.camera_track.as_ref() {
+        files.push(("camera_track.json", member_json(track, Some(CAMERA_TRACK_MIGRATIONS))?));
+    }
+    if let Some(track) = project.zoom_track.as_ref() {
+        let mut track = track.clone();
+        track.frames = compute_zoom_frames(&track);
+        files.push(("zoom_track.json", member_json(&track, None)?));
+    }
+    if let Some(track) = project.crop_track.as_ref() {
+        files.push(("crop_track.json", member_json(track, None)?));
+    }
+    if let Some(track) = project.audio_track.as_ref() {
+        files.push(("audio_track.json", member_json(track, None)?));
+    }
+    if let Some(capture) = project.capture.as_ref() {
+        files.push(("capture.json", member_json(capture, None)?));
+    }
+    for (name, data) in files {
+        let path = dir.join(name);
+        if name.ends_with("_track.json") {
+            save_track_file(&path, &data)?;
+        } else {
+            write_file_atomic(&path, &data)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_project_json(dir: &Path, project: &Project) -> Result<PathBuf, String> {
+    let mut value = to_versioned_value(project, PROJECT_MIGRATIONS)?;
+    if let Some(obj) = value.as_object_mut() {
+        for (key, migrations) in project_member_migrations() {
+            if let Some(member) = obj.get_mut(key).and_then(|m| m.as_object_mut()) {
+                member.insert("version".to_string(), schema_version(migrations).into());
+            }
+        }
+    }
+    let path = dir.join("project.json");
+    let data = serde_json::to_string_pretty(&value).map_err(|_| "project_serialize_failed")?;
+    write_file_atomic(&path, &data)?;
+    Ok(path)
+}
+
+// Files whose saves are mirrored into project.json.
+const PROJECT_MEMBER_FILES: &[&str] = &[
+    "edit_state.json",
+    "clip_track.json",
+    "camera_track.json",
+    "zoom_track.json",
+    "crop_track.json",
+    "audio_track.json",
+    "capture.json",
+];
+
+// Called after a loose file is written; rebuilds project.json when the file
+// is one of its members.
+fn refresh_project_json(member_path: &Path) -> Result<(), String> {
+    let is_member = member_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| PROJECT_MEMBER_FILES.contains(&name));
+    let Some(dir) = member_path.parent().filter(|_| is_member) else {
+        return Ok(());
+    };
+    let input_path = dir.join("recording.mp4").to_string_lossy().to_string();
+    write_project_json(dir, &assemble_project(&input_path)?)?;
+    Ok(())
+}
+
+// The project from the loose files. project.json is only read when none of
+// them exist, so a member that was cleared stays cleared.
+fn assemble_project(input_path: &str) -> Result<Project, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    if !PROJECT_MEMBER_FILES.iter().any(|name| dir.join(name).exists()) {
+        return Ok(read_migrated(&dir.join("project.json"), PROJECT_MIGRATIONS).unwrap_or_default());
+    }
+    Ok(Project {
+        edit_state: read_migrated(&dir.join("edit_state.json"), EDIT_STATE_MIGRATIONS),
+        clip_track: load_clip_track(input_path),
+        camera_track: load_camera_track(input_path),
+        zoom_track: load_zoom_track(input_path),
+        crop_track: load_crop_track(input_path),
+        audio_track: load_audio_track(input_path),
+        capture: read_json_file(&dir.join("capture.json")),
+    })
+}
+
+#[tauri::command]
+fn load_project(input_path: String) -> Result<Project, AppError> {
+    Ok(assemble_project(&input_path)?)
+}
+
+#[tauri::command]
+fn save_project(input_path: String, project: Project) -> Result<String, AppError> {
+    let dir = PathBuf::from(&input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    write_project_files(&dir, &project)?;
+    let path = write_project_json(&dir, &assemble_project(&input_path)?)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Upgrades every versioned file in a session to the current schema. The
+// original of each rewritten file is kept as <name>.v<from>.bak.
+#[tauri::command]
+fn migrate_session(
+    session_id: String,
+    dry_run: Option<bool>,
+) -> Result<SessionMigrationReport, AppError> {
+    let dir = session_dir(&session_id)?;
+    let dry_run = dry_run.unwrap_or(false);
+    let mut files = Vec::new();
+    for (name, migrations) in session_migration_targets() {
+        let path = dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let to_version = schema_version(migrations);
+        let parsed = fs::read_to_string(&path)
+            .map_err(|_| "read_failed".to_string())
+            .and_then(|data| {
+                serde_json::from_str::<serde_json::Value>(&data).map_err(|e| format!("parse_failed: {e}"))
+            });
+        let mut value = match parsed {
+            Ok(value) => value,
+            Err(error) => {
+                files.push(FileMigrationReport {
+                    file: name.to_string(),
+                    from_version: 0,
+                    to_version,
+                    changes: Vec::new(),
+                    error: Some(error),
+                });
+                continue;
+            }
+        };
+        let (from_version, changes) = apply_migrations(&mut value, migrations);
+        let mut error = None;
+        if !dry_run && from_version < to_version {
+            let backup = dir.join(format!("{name}.v{from_version}.bak"));
+            let result = fs::copy(&path, &backup)
+                .map_err(|_| "backup_failed".to_string())
+                .and_then(|_| serde_json::to_string_pretty(&value).map_err(|e| e.to_string()))
+                .and_then(|data| fs::write(&path, data).map_err(|_| "write_failed".to_string()));
+            error = result.err();
+        }
+        files.push(FileMigrationReport {
+            file: name.to_string(),
+            from_version,
+            to_version,
+            changes,
+            error,
+        });
+    }
+    Ok(SessionMigrationReport {
+        session_id,
+        dry_run,
+        files,
+    })
+}
+
+// Refuses while the session is being recorded or has an export running or
+// queued.
+fn ensure_session_idle(
+    recording_state: &RecordingState,
+    export_state: &ExportState,
+    session_id: &str,
+    dir: &Path,
+) -> Result<(), String> {
+    {
+        let guard = recording_state.inner.lock().map_err(|_| "state_lock_failed")?;
+        if guard.as_ref().is_some_and(|session| session.id == session_id) {
+            return Err("session_recording".to_string());
+        }
+    }
+    {
+        let guard = export_state.inner.lock().map_err(|_| "export_state_lock_failed")?;
+        let exporting = guard.current.iter().chain(guard.queue.iter()).any(|job| {
+            !guard.cancellations.contains_key(&job.job_id)
+                && PathBuf::from(&job.request.input_path).parent() == Some(dir)
+        });
+        if exporting {
+            return Err("session_exporting".to_string());
+        }
+    }
+    Ok(())
+}
+
+// Moves the session into the trash; restore_session brings it back until it
+// is purged. Sessions on another drive are copied, so this runs off the main
+// thread.
+#[tauri::command]
+async fn delete_session(
+    recording_state: State<'_, RecordingState>,
+    export_state: State<'_, ExportState>,
+    session_id: String,
+) -> Result<(), AppError> {
+    let dir = session_dir(&session_id)?;
+    ensure_session_idle(&recording_state, &export_state, &session_id, &dir)?;
+    tauri::async_runtime::spawn_blocking(move || trash_session(&session_id, &dir))
+        .await
+        .map_err(|_| "session_delete_join_failed".to_string())?
+        .map_err(AppError::from)
+}
+
+// Each deletion gets its own trash folder, so deleting a re-imported id
+// never replaces an earlier trashed copy.
+fn trash_session(session_id: &str, dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(trash_dir()).map_err(|e| format!("session_delete_failed: {e}"))?;
+    let deleted_at_ms = unix_now_ms();
+    let mut trash_id = format!("{session_id}-{deleted_at_ms}");
+    let mut suffix = 1;
+    while trash_dir().join(&trash_id).exists() {
+        suffix += 1;
+        trash_id = format!("{session_id}-{deleted_at_ms}-{suffix}");
+    }
+    let trashed = trash_dir().join(&trash_id);
+    move_dir(dir, &trashed).map_err(|e| format!("session_delete_failed: {e}"))?;
+    let entry = TrashEntry {
+        trash_id: String::new(),
+        session_id: session_id.to_string(),
+        original_dir: dir.to_string_lossy().to_string(),
+        deleted_at_ms,
+        bytes: 0,
+    };
+    let data = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    // Without the entry the folder can't be restored and reads as ancient, so
+    // put the session back instead.
+    if let Err(err) = write_file_atomic(&trashed.join(TRASH_ENTRY_FILE), &data) {
+        let _ = move_dir(&trashed, dir);
+        return Err(format!("session_delete_failed: {err}"));
+    }
+    Ok(())
+}
+
+const TRASH_ENTRY_FILE: &str = "trashed.json";
+const TRASH_PURGE_DAYS: u32 = 30;
+
+fn trash_dir() -> PathBuf {
+    app_data_root().join("trash")
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrashEntry {
+    // Folder name in the trash; filled in by read_trash.
+    #[serde(default)]
+    trash_id: String,
+    session_id: String,
+    original_dir: String,
+    deleted_at_ms: u64,
+    // Filled in by list_trash.
+    #[serde(default)]
+    bytes: u64,
+}
+
+// Rename when possible; across drives copy, then delete the source.
+fn move_dir(src: &Path, dst: &Path) -> Result<(), String> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    let mut copied = 0;
+    if let Err(err) = copy_tree_with_progress(src, dst, &mut copied, &mut |_| {}) {
+        let _ = fs::remove_dir_all(dst);
+        return Err(err.to_string());
+    }
+    if !tree_copied(src, dst) {
+        let _ = fs::remove_dir_all(dst);
+        return Err("copy_incomplete".to_string());
+    }
+    let _ = fs::remove_dir_all(src);
+    Ok(())
+}
+
+fn read_trash() -> Vec<TrashEntry> {
+    let mut entries: Vec<TrashEntry> = fs::read_dir(trash_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let dir = entry.path();
+            let mut trashed: TrashEntry = read_json_file(&dir.join(TRASH_ENTRY_FILE))?;
+            trashed.trash_id = entry.file_name().to_string_lossy().to_string();
+            trashed.bytes = dir_size(&dir);
+            Some(trashed)
+        })
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at_ms));
+    entries
+}
+
+// Newest deletion first.
+#[tauri::command]
+fn list_trash() -> Result<Vec<TrashEntry>, AppError> {
+    Ok(read_trash())
+}
+
+// Puts a trashed session back where it was deleted from. Returns its
+// session id.
+#[tauri::command]
+fn restore_session(trash_id: String) -> Result<String, AppError> {
+    if trash_id.trim().is_empty() || trash_id.contains(['/', '\\']) || trash_id.contains("..") {
+        return Err("invalid_trash_id".into());
+    }
+    let trashed = trash_dir().join(&trash_id);
+    let entry: TrashEntry =
+        read_json_file(&trashed.join(TRASH_ENTRY_FILE)).ok_or("trash_entry_not_found")?;
+    let session_id = entry.session_id.clone();
+    if session_dir(&session_id).is_ok() {
+        return Err("session_exists".into());
+    }
+    let original = PathBuf::from(&entry.original_dir);
+    // A folder that lived on a drive that is gone comes back to the work folder.
+    let target = match original.parent() {
+        Some(parent) if parent.is_dir() && !original.exists() => original.clone(),
+        _ => work_base_dir().join(&session_id),
+    };
+    let _ = fs::remove_file(trashed.join(TRASH_ENTRY_FILE));
+    if let Err(err) = move_dir(&trashed, &target) {
+        let data = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        let _ = fs::write(trashed.join(TRASH_ENTRY_FILE), data);
+        return Err(format!("session_restore_failed: {err}").into());
+    }
+    let mut locations = read_session_locations();
+    if target.parent() == Some(work_base_dir().as_path()) {
+        locations.remove(&session_id);
+    } else {
+        locations.insert(session_id.clone(), target.to_string_lossy().to_string());
+    }
+    let data = serde_json::to_string_pretty(&locations).map_err(|e| e.to_string())?;
+    write_file_atomic(&session_locations_path(), &data)?;
+    Ok(session_id)
+}
+
+// Permanently removes everything in the trash, or only entries older than
+// older_than_days.
+#[tauri::command]
+fn empty_trash(older_than_days: Option<u32>) -> Result<Vec<String>, AppError> {
+    let cutoff_ms = older_than_days
+        .map(|days| unix_now_ms().saturating_sub(days as u64 * 86_400_000))
+        .unwrap_or(u64::MAX);
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(trash_dir()).into_iter().flatten().flatten() {
+        let dir = entry.path();
+        // Folders without an entry fall back to their mtime rather than
+        // counting as infinitely old.
+        let deleted_at_ms = read_json_file::<TrashEntry>(&dir.join(TRASH_ENTRY_FILE))
+            .map(|trashed| trashed.deleted_at_ms)
+            .or_else(|| {
+                let modified = entry.metadata().and_then(|meta| meta.modified()).ok()?;
+                Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+            })
+            .unwrap_or(0);
+        if deleted_at_ms <= cutoff_ms && fs::remove_dir_all(&dir).is_ok() {
+            removed.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    Ok(removed)
+}
+
+fn session_locations_path() -> PathBuf {
+    app_data_root().join("session_locations.json")
+}
+
+// Session id -> folder, for sessions that live outside the work folder.
+fn read_session_locations() -> HashMap<String, String> {
+    read_json_file(&session_locations_path()).unwrap_or_default()
+}
+
+#[derive(Serialize, Clone)]
+struct SessionMoveProgress {
+    session_id: String,
+    copied_bytes: u64,
+    total_bytes: u64,
+}
+
+fn copy_tree_with_progress(
+    src: &Path,
+    dst: &Path,
+    copied: &mut u64,
+    on_progress: &mut impl FnMut(u64),
+) -> std::io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_tree_with_progress(&entry.path(), &target, copied, on_progress)?;
+            continue;
+        }
+        let mut reader = fs::File::open(entry.path())?;
+        let mut writer = fs::File::create(&target)?;
+        let mut buf = vec![0u8; 4 * 1024 * 1024];
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buf[..read])?;
+            *copied += read as u64;
+            on_progress(*copied);
+        }
+        writer.sync_all()?;
+    }
+    Ok(())
+}
+
+// Every file of src exists in dst with the same size.
+fn tree_copied(src: &Path, dst: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(src) else {
+        return false;
+    };
+    entries.flatten().all(|entry| {
+        let target = dst.join(entry.file_name());
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => tree_copied(&entry.path(), &target),
+            _ => match (entry.metadata(), fs::metadata(&target)) {
+                (Ok(a), Ok(b)) => a.len() == b.len(),
+                _ => false,
+            },
+        }
+    })
+}
+
+const SESSION_ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SessionArchiveManifest {
+    version: u32,
+    session_id: String,
+    created_at_ms: u64,
+}
+
+// Caches the editor rebuilds on demand stay out of archives.
+fn archive_skips(relative: &Path) -> bool {
+    let name = relative.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let top = relative
+        .components()
+        .next()
+        .and_then(|c| c.as_os_str().to_str())
+        .unwrap_or("");
+    matches!(top, "loops" | "thumbs" | "history")
+        || name == "preview.mp4"
+        || name.ends_with("_proxy.mp4")
+        || name.ends_with(".log")
+        || name.ends_with(".tmp")
+}
+
+fn add_dir_to_archive<W: std::io::Write + std::io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    root: &Path,
+    dir: &Path,
+) -> Result<(), String> {
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+    for entry in fs::read_dir(dir).map_err(|_| "archive_read_failed")?.flatten() {
+        let path = entry.path();
+        let relative = path.strip_prefix(root).map_err(|_| "archive_read_failed")?;
+        if archive_skips(relative) {
+            continue;
+        }
+        if path.is_dir() {
+            add_dir_to_archive(zip, root, &path)?;
+            continue;
+        }
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        zip.start_file(name, options).map_err(|_| "archive_write_failed")?;
+        let mut file = fs::File::open(&path).map_err(|_| "archive_read_failed")?;
+        std::io::copy(&mut file, zip).map_err(|_| "archive_write_failed")?;
+    }
+    Ok(())
+}
+
+// Packs the session (recording, camera, tracks, edit state and the rest of
+// the folder minus caches) into one uncompressed .frproj zip. Defaults to
+// <export dir>/<session id>.frproj; returns the archive path.
+#[tauri::command]
+async fn export_session_archive(
+    session_id: String,
+    output_path: Option<String>,
+) -> Result<String, AppError> {
+    let dir = session_dir(&session_id)?;
+    let output = match output_path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => PathBuf::from(path).with_extension("frproj"),
+        None => export_dir_with_fallback().join(format!("{session_id}.frproj")),
+    };
+    let archive = output.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let partial = archive.with_extension("frproj.partial");
+        let file = fs::File::create(&partial).map_err(|_| "archive_write_failed")?;
+        let mut zip = zip::ZipWriter::new(std::io::BufWriter::new(file));
+        let manifest = SessionArchiveManifest {
+            version: SESSION_ARCHIVE_VERSION,
+            session_id: session_id.clone(),
+            created_at_ms: unix_now_ms(),
+        };
+        let result = zip
+            .start_file("frproj.json", zip::write::SimpleFileOptions::default())
+            .map_err(|_| "archive_write_failed".to_string())
+            .and_then(|_| {
+                let data = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+                zip.write_all(&data).map_err(|_| "archive_write_failed".to_string())
+            })
+            .and_then(|_| add_dir_to_archive(&mut zip, &dir, &dir))
+            .and_then(|_| zip.finish().map(|_| ()).map_err(|_| "archive_write_failed".to_string()))
+            .and_then(|_| fs::rename(&partial, &archive).map_err(|_| "archive_write_failed".to_string()));
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        result
+    })
+    .await
+    .map_err(|_| "archive_join_failed".to_string())??;
+    Ok(output.to_string_lossy().to_string())
+}
+
+fn unpack_session_archive(archive: &Path, dir: &Path) -> Result<SessionArchiveManifest, String> {
+    let file = fs::File::open(archive).map_err(|_| "archive_not_found")?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).map_err(|_| "archive_invalid")?;
+    let manifest: SessionArchiveManifest = {
+        let mut entry = zip.by_name("frproj.json").map_err(|_| "archive_invalid")?;
+        let mut data = String::new();
+        entry.read_to_string(&mut data).map_err(|_| "archive_invalid")?;
+        serde_json::from_str(&data).map_err(|_| "archive_invalid")?
+    };
+    if manifest.version > SESSION_ARCHIVE_VERSION {
+        return Err("archive_unsupported_version".to_string());
+    }
+    for idx in 0..zip.len() {
+        let mut entry = zip.by_index(idx).map_err(|_| "archive_invalid")?;
+        // enclosed_name rejects absolute paths and ".." components.
+        let Some(relative) = entry.enclosed_name() else {
+            return Err("archive_invalid".to_string());
+        };
+        if entry.is_dir() || relative == Path::new("frproj.json") {
+            continue;
+        }
+        let target = dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|_| "archive_extract_failed")?;
+        }
+        let mut out = fs::File::create(&target).map_err(|_| "archive_extract_failed")?;
+        std::io::copy(&mut entry, &mut out).map_err(|_| "archive_extract_failed")?;
+    }
+    if !dir.join("recording.mp4").is_file() {
+        return Err("archive_missing_recording".to_string());
+    }
+    Ok(manifest)
+}
+
+// Unpacks a .frproj into a new session folder and migrates its files to the
+// current schema. Keeps the archived session id unless it is taken. Returns
+// the new session id.
+#[tauri::command]
+async fn import_session_archive(path: String) -> Result<String, AppError> {
+    let archive = PathBuf::from(&path);
+    if !archive.is_file() {
+        return Err("archive_not_found".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let staging = work_base_dir().join(format!(".import_{}", unix_now_ms()));
+        let unpacked = unpack_session_archive(&archive, &staging);
+        let manifest = match unpacked {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(err);
+            }
+        };
+        let mut session_id = manifest.session_id;
+        if session_dir(&session_id).is_ok() {
+            session_id = format!("{session_id}_{}", unix_now_ms());
+        }
+        match session_dir(&session_id) {
+            Err(err) if err == "session_not_found" && !session_id.starts_with('.') => {}
+            _ => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err("invalid_session_id".to_string());
+            }
+        }
+        let dir = work_base_dir().join(&session_id);
+        if fs::rename(&staging, &dir).is_err() {
+            let _ = fs::remove_dir_all(&staging);
+            return Err("archive_extract_failed".to_string());
+        }
+        let report = migrate_session(session_id.clone(), Some(false));
+        let failure = match report {
+            Ok(report) => report.files.into_iter().find_map(|file| {
+                if file.from_version > file.to_version {
+                    Some(format!("archive_newer_schema: {}", file.file))
+                } else {
+                    file.error.map(|err| format!("archive_invalid: {}: {err}", file.file))
+                }
+            }),
+            Err(err) => Some(String::from(err)),
+        };
+        if let Some(err) = failure {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(err);
+        }
+        Ok(session_id)
+    })
+    .await
+    .map_err(|_| "archive_join_failed".to_string())?
+    .map_err(AppError::from)
+}
+
+// Moves the session folder into target_dir (copy, verify, then delete the
+// original) and records the new location so session ids keep resolving.
+// Emits session_move_progress while copying; returns the new folder.
+#[tauri::command]
+async fn move_session(
+    app: tauri::AppHandle,
+    recording_state: State<'_, RecordingState>,
+    export_state: State<'_, ExportState>,
+    session_id: String,
+    target_dir: String,
+) -> Result<String, AppError> {
+    let src = session_dir(&session_id)?;
+    ensure_session_idle(&recording_state, &export_state, &session_id, &src)?;
+    let target_root = PathBuf::from(&target_dir);
+    if !target_root.is_absolute() {
+        return Err("invalid_target_dir".into());
+    }
+    let dst = target_root.join(&session_id);
+    if dst == src {
+        return Err("session_already_there".into());
+    }
+    if dst.exists() {
+        return Err("target_exists".into());
+    }
+    let total_bytes = dir_size(&src);
+    let moved = dst.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut copied = 0;
+        let mut last_emit = 0;
+        let mut on_progress = |copied_bytes: u64| {
+            if copied_bytes - last_emit >= 64 * 1024 * 1024 || copied_bytes == total_bytes {
+                last_emit = copied_bytes;
+                let _ = app.emit(
+                    "session_move_progress",
+                    SessionMoveProgress {
+                        session_id: session_id.clone(),
+                        copied_bytes,
+                        total_bytes,
+                    },
+                );
+            }
+        };
+        let copied_ok = copy_tree_with_progress(&src, &moved, &mut copied, &mut on_progress).is_ok();
+        if !copied_ok || !tree_copied(&src, &moved) {
+            let _ = fs::remove_dir_all(&moved);
+            return Err("session_copy_failed".to_string());
+        }
+        let mut locations = read_session_locations();
+        if moved.parent() == Some(work_base_dir().as_path()) {
+            locations.remove(&session_id);
+        } else {
+            locations.insert(session_id.clone(), moved.to_string_lossy().to_string());
+        }
+        let data = serde_json::to_string_pretty(&locations).map_err(|e| e.to_string())?;
+        write_file_atomic(&session_locations_path(), &data)?;
+        // The copy is verified and recorded; a leftover original only costs space.
+        let _ = fs::remove_dir_all(&src);
+        Ok(())
+    })
+    .await
+    .map_err(|_| "session_move_join_failed".to_string())??;
+    Ok(dst.to_string_lossy().to_string())
+}
+
+// Edits capture.json as plain JSON so fields this build doesn't know survive.
+fn update_capture_meta(
+    session_id: &str,
+    update: impl FnOnce(&mut serde_json::Map<String, serde_json::Value>),
+) -> Result<(), String> {
+    let path = session_dir(session_id)?.join("capture.json");
+    let mut meta: serde_json::Value = fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .ok_or("session_meta_not_found")?;
+    update(meta.as_object_mut().ok_or("session_meta_not_found")?);
+    let data = serde_json::to_string(&meta).map_err(|e| e.to_string())?;
+    write_file_atomic(&path, &data)?;
+    refresh_project_json(&path)
+}
+
+// Stores a display title in capture.json; an empty title clears it.
+#[tauri::command]
+fn rename_session(session_id: String, title: String) -> Result<(), AppError> {
+    let title = title.trim().replace(['\r', '\n'], " ");
+    update_capture_meta(&session_id, |obj| {
+        if title.is_empty() {
+            obj.remove("title");
+        } else {
+            obj.insert("title".to_string(), title.chars().take(120).collect::<String>().into());
+        }
+    })?;
+    Ok(())
+}
+
+// Favorites sort first in list_sessions and are never removed by retention.
+#[tauri::command]
+fn set_session_favorite(session_id: String, favorite: bool) -> Result<(), AppError> {
+    update_capture_meta(&session_id, |obj| {
+        obj.insert("favorite".to_string(), favorite.into());
+    })?;
+    Ok(())
+}
+
+// Trimmed, at most 20 tags of 32 characters, duplicates (ignoring case)
+// dropped. Returns the stored list.
+#[tauri::command]
+fn set_session_tags(session_id: String, tags: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter() {
+        let tag: String = tag.trim().replace(['\r', '\n'], " ").chars().take(32).collect();
+        if !tag.is_empty() && !cleaned.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            cleaned.push(tag);
+        }
+    }
+    cleaned.truncate(20);
+    update_capture_meta(&session_id, |obj| {
+        obj.insert("tags".to_string(), cleaned.clone().into());
+    })?;
+    Ok(cleaned)
+}
+
+#[derive(Serialize)]
+struct SessionSummary {
+    session_id: String,
+    dir: String,
+    title: Option<String>,
+    tags: Vec<String>,
+    started_at_ms: u64,
+    favorite: bool,
+}
+
+// Sessions in the work folder plus those moved elsewhere, in no order.
+fn session_summaries() -> Vec<SessionSummary> {
+    let mut dirs: Vec<(String, PathBuf)> = fs::read_dir(work_base_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+        .filter(|(id, _)| !id.starts_with('.'))
+        .collect();
+    for (id, dir) in read_session_locations() {
+        let dir = PathBuf::from(dir);
+        if dir.is_dir() && !dirs.iter().any(|(known, _)| *known == id) {
+            dirs.push((id, dir));
+        }
+    }
+    dirs.into_iter()
+        .filter_map(|(session_id, dir)| {
+            let meta: CaptureMeta = read_json_file(&dir.join("capture.json"))?;
+            Some(SessionSummary {
+                session_id,
+                dir: dir.to_string_lossy().to_string(),
+                title: meta.title,
+                tags: meta.tags,
+                started_at_ms: meta.started_at_ms,
+                favorite: meta.favorite,
+            })
+        })
+        .collect()
+}
+
+// Every whitespace-separated term must match the title, id or a tag (case
+// insensitive); "tag:name" only matches a tag exactly. Sorted like
+// list_sessions.
+#[tauri::command]
+fn search_sessions(query: String) -> Result<Vec<SessionSummary>, AppError> {
+    let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+    let mut sessions: Vec<SessionSummary> = session_summaries()
+        .into_iter()
+        .filter(|session| {
+            let title = session.title.as_deref().unwrap_or("").to_lowercase();
+            let id = session.session_id.to_lowercase();
+            let tags: Vec<String> = session.tags.iter().map(|t| t.to_lowercase()).collect();
+            terms.iter().all(|term| match term.strip_prefix("tag:") {
+                Some(tag) => tags.iter().any(|t| t == tag),
+                None => {
+                    title.contains(term.as_str())
+                        || id.contains(term.as_str())
+                        || tags.iter().any(|t| t.contains(term.as_str()))
+                }
+            })
+        })
+        .collect();
+    sort_sessions(&mut sessions);
+    Ok(sessions)
+}
+
+// Favorites first, then newest first.
+fn sort_sessions(sessions: &mut [SessionSummary]) {
+    sessions.sort_by_key(|session| (!session.favorite, std::cmp::Reverse(session.started_at_ms)));
+}
+
+#[tauri::command]
+fn list_sessions() -> Result<Vec<SessionSummary>, AppError> {
+    let mut sessions = session_summaries();
+    sort_sessions(&mut sessions);
+    Ok(sessions)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+        })
+        .sum()
+}
+
+#[derive(Serialize)]
+struct SessionUsage {
+    session_id: String,
+    title: Option<String>,
+    bytes: u64,
+    started_at_ms: u64,
+    favorite: bool,
+}
+
+#[derive(Serialize)]
+struct StorageUsage {
+    // Oldest first.
+    sessions: Vec<SessionUsage>,
+    sessions_bytes: u64,
+    exports_bytes: u64,
+    trash_bytes: u64,
+}
+
+fn is_session_folder(dir: &Path) -> bool {
+    dir.join("capture.json").is_file() || dir.join("recording.mp4").is_file()
+}
+
+fn session_usages() -> Vec<SessionUsage> {
+    let mut sessions: Vec<SessionUsage> = fs::read_dir(work_base_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        // Dot folders are imports still being unpacked.
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        // work_dir is user-chosen and may hold unrelated folders; only ones
+        // that look like a session are counted (and ever removed).
+        .filter(|entry| is_session_folder(&entry.path()))
+        .map(|entry| {
+            let dir = entry.path();
+            let meta: Option<CaptureMeta> = read_json_file(&dir.join("capture.json"));
+            // Sessions without capture.json fall back to the folder's mtime.
+            let modified_ms = fs::metadata(&dir)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|age| age.as_millis() as u64)
+                .unwrap_or(0);
+            SessionUsage {
+                session_id: entry.file_name().to_string_lossy().to_string(),
+                title: meta.as_ref().and_then(|meta| meta.title.clone()),
+                bytes: dir_size(&dir),
+                started_at_ms: meta.as_ref().map(|meta| meta.started_at_ms).unwrap_or(modified_ms),
+                favorite: meta.is_some_and(|meta| meta.favorite),
+            }
+        })
+        .collect();
+    sessions.sort_by_key(|session| session.started_at_ms);
+    sessions
+}
+
+#[tauri::command]
+fn get_storage_usage() -> Result<StorageUsage, AppError> {
+    let sessions = session_usages();
+    Ok(StorageUsage {
+        sessions_bytes: sessions.iter().map(|session| session.bytes).sum(),
+        sessions,
+        exports_bytes: dir_size(&export_dir_with_fallback()),
+        trash_bytes: dir_size(&trash_dir()),
+    })
+}
+
+#[tauri::command]
+fn get_retention_policy() -> Result<RetentionPolicy, AppError> {
+    Ok(load_settings().retention)
+}
+
+#[tauri::command]
+fn set_retention_policy(policy: RetentionPolicy) -> Result<RetentionPolicy, AppError> {
+    validate_retention_policy(&policy)?;
+    let mut settings = load_settings();
+    settings.retention = policy;
+    save_settings(&settings)?;
+    Ok(settings.retention)
+}
+
+fn validate_retention_policy(policy: &RetentionPolicy) -> Result<(), String> {
+    if policy.max_total_gb.is_some_and(|gb| !gb.is_finite() || gb <= 0.0) {
+        return Err("invalid_retention_policy".to_string());
+    }
+    Ok(())
+}
+
+// Trashes sessions past the age limit, then the oldest ones until the total
+// fits. Favorites and sessions with a persisted export job are kept. Returns
+// the removed session ids.
+fn apply_retention_policy() -> Vec<String> {
+    let policy = load_settings().retention;
+    if policy.max_age_days.is_none() && policy.max_total_gb.is_none() {
+        return Vec::new();
+    }
+    let queued: Vec<PathBuf> = fs::read_to_string(export_queue_path())
+        .ok()
+        .and_then(|data| serde_json::from_str::<Vec<ExportJob>>(&data).ok())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|job| PathBuf::from(&job.request.input_path).parent().map(Path::to_path_buf))
+        .collect();
+    let sessions = session_usages();
+    let mut total: u64 = sessions.iter().map(|session| session.bytes).sum();
+    let max_bytes = policy
+        .max_total_gb
+        .map(|gb| (gb * 1024.0 * 1024.0 * 1024.0) as u64)
+        .unwrap_or(u64::MAX);
+    let cutoff_ms = policy
+        .max_age_days
+        .map(|days| unix_now_ms().saturating_sub(days as u64 * 86_400_000))
+        .unwrap_or(0);
+    let mut removed = Vec::new();
+    for session in sessions.iter() {
+        if session.started_at_ms >= cutoff_ms && total <= max_bytes {
+            break;
+        }
+        let dir = work_base_dir().join(&session.session_id);
+        if session.favorite || queued.contains(&dir) {
+            continue;
+        }
+        if trash_session(&session.session_id, &dir).is_ok() {
+            total = total.saturating_sub(session.bytes);
+            removed.push(session.session_id.clone());
+        }
+    }
+    removed
+}
+
+#[tauri::command]
+fn list_themes() -> Result<Vec<ThemePack>, AppError> {
+    let mut themes = vec![builtin_theme()];
+    if let Ok(entries) = fs::read_dir(themes_dir()) {
+        let mut installed: Vec<ThemePack> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .filter_map(|entry| read_theme_pack(&entry.path()))
+            .collect();
+        installed.sort_by(|a, b| a.name.cmp(&b.name));
+        themes.extend(installed);
+    }
+    Ok(themes)
+}
+
+#[tauri::command]
+fn install_theme(path: String) -> Result<ThemePack, AppError> {
+    let source = PathBuf::from(&path);
+    let source_dir = if source.is_dir() {
+        source
+    } else {
+        source.parent().ok_or("invalid_theme_path")?.to_path_buf()
+    };
+    if !source_dir.join("theme.json").exists() {
+        return Err("theme_manifest_missing".into());
+    }
+    let data = fs::read_to_string(source_dir.join("theme.json"))
+        .map_err(|_| "theme_read_failed".to_string())?;
+    let _: ThemePack = serde_json::from_str(&data).map_err(|_| "theme_invalid".to_string())?;
+    let id = source_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .filter(|n| !n.is_empty() && *n != "default")
+        .ok_or("invalid_theme_id")?
+        .to_string();
+    let target = themes_dir().join(&id);
+    if target.exists() {
+        fs::remove_dir_all(&target).map_err(|_| "theme_replace_failed".to_string())?;
+    }
+    copy_dir(&source_dir, &target).map_err(|_| "theme_install_failed".to_string())?;
+    read_theme_pack(&target).ok_or_else(|| "theme_invalid".into())
+}
+
+#[tauri::command]
+fn apply_theme(output_path: String, theme_id: String) -> Result<EditState, AppError> {
+    let mut edit_state = load_edit_state(output_path.clone())?;
+    if theme_id.is_empty() || theme_id == "default" {
+        edit_state.theme = None;
+    } else {
+        if !valid_theme_id(&theme_id) {
+            return Err("invalid_theme_id".into());
+        }
+        if read_theme_pack(&themes_dir().join(&theme_id)).is_none() {
+            return Err("theme_not_found".into());
+        }
+        edit_state.theme = Some(theme_id);
+    }
+    edit_state.background_preset = 0;
+    save_edit_state(output_path, edit_state.clone())?;
+    Ok(edit_state)
+}
+
+#[tauri::command]
+fn ensure_preview(app: tauri::AppHandle, output_path: String) -> Result<String, AppError> {
+    let preview = preview_path(&output_path);
+    if preview.exists() {
+        return Ok(preview.to_string_lossy().to_string());
+    }
+    let bin = ffmpeg_binary_with_app_handle(&app);
+    let status = new_cmd(&bin)
+        .args([
+            "-y",
+            "-i",
+            &output_path,
+            "-vf",
+            "scale=1024:-2",
+            "-r",
+            "30",
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-pix_fmt",
+            "yuv420p",
+            "-an",
+            preview.to_string_lossy().as_ref(),
+        ])
+        .status()
+        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
+    if status.success() {
+        Ok(preview.to_string_lossy().to_string())
+    } else {
+        Err("preview_failed".into())
+    }
+}
+
+fn preview_profile_for(edit_state: &EditState, long_edge: u32) -> ExportProfile {
+    let long_edge = evenize(long_edge.max(64) as i32) as u32;
+    let short_edge = |ratio: f32| evenize((long_edge as f32 * ratio).round() as i32).max(2) as u32;
+    let (width, height) = match edit_state.aspect.as_str() {
+        "1:1" => (long_edge, long_edge),
+        "9:16" => (short_edge(9.0 / 16.0), long_edge),
+        _ => (long_edge, short_edge(9.0 / 16.0)),
+    };
+    ExportProfile {
+        format: "h264".to_string(),
+        width,
+        height,
+        fps: 30,
+        bitrate_kbps: 2500,
+        ..ExportProfile::default()
+    }
+}
+
+fn edit_state_hash(edit_state: &EditState, input_path: &str, extra: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(edit_state).unwrap_or_default().hash(&mut hasher);
+    if let Some(dir) = PathBuf::from(input_path).parent() {
+        for name in [
+            "clip_track.json",
+            "camera_track.json",
+            "zoom_track.json",
+            "crop_track.json",
+            "camera_framing_track.json",
+        ] {
+            fs::read_to_string(dir.join(name)).unwrap_or_default().hash(&mut hasher);
+        }
+    }
+    extra.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn filtered_range_graph(
+    app: &tauri::AppHandle,
+    input_path: &str,
+    has_camera: bool,
+    edit_state: &EditState,
+    profile: &ExportProfile,
+    (start_s, end_s): (f64, f64),
+) -> String {
+    let clip_select = load_clip_track(input_path).and_then(|t| build_clip_select_window(&t, start_s, end_s));
+    let camera_enable =
+        load_camera_track(input_path).and_then(|t| build_camera_enable_window(&t, start_s, end_s));
+    let zoom = probe_media(app, input_path)
+        .and_then(|probe| {
+            source_effects_for(input_path, edit_state, &probe, (start_s, end_s), (profile.width, profile.height))
+        });
+    let filter = build_export_filter(edit_state, profile, has_camera, camera_enable, clip_select, zoom);
+    let filter = append_camera_framing(filter, input_path);
+    append_keystrokes(filter, input_path, edit_state, profile, (start_s, end_s))
+}
+
+// Renders [start_s, end_s) of the input through the export filter graph at the
+// given profile, without audio. Used for editor-side previews.
+fn render_filtered_range(
+    app: &tauri::AppHandle,
+    input_path: &str,
+    camera_path: Option<&str>,
+    edit_state: &EditState,
+    profile: &ExportProfile,
+    (start_s, end_s): (f64, f64),
+    output: &PathBuf,
+) -> Result<(), String> {
+    if end_s <= start_s || start_s < 0.0 {
+        return Err("invalid_range".to_string());
+    }
+    let camera_path = camera_path.filter(|p| !p.is_empty() && PathBuf::from(p).exists());
+    let filter = filtered_range_graph(
+        app,
+        input_path,
+        camera_path.is_some(),
+        edit_state,
+        profile,
+        (start_s, end_s),
+    );
+    let start = format!("{:.3}", start_s);
+    let duration = format!("{:.3}", end_s - start_s);
+    let mut args = vec![
+        "-y".to_string(),
+        "-ss".to_string(),
+        start.clone(),
+        "-i".to_string(),
+        input_path.to_string(),
+    ];
+    if let Some(path) = camera_path {
+        args.extend(["-ss".to_string(), start, "-i".to_string(), path.to_string()]);
+    }
+    args.extend([
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[v]".to_string(),
+        "-t".to_string(),
+        duration,
+        "-r".to_string(),
+        profile.fps.to_string(),
+        "-c:v".to_string(),
+        "libx264".to_string(),
+        "-preset".to_string(),
+        "veryfast".to_string(),
+        "-pix_fmt".to_string(),
+        "yuv420p".to_string(),
+        "-b:v".to_string(),
+        format!("{}k", profile.bitrate_kbps.max(1)),
+        "-an".to_string(),
+        output.to_string_lossy().to_string(),
+    ]);
+    if let Some(parent) = output.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let bin = ffmpeg_binary_with_app_handle(app);
+    let result = new_cmd(&bin)
+        .args(args)
+        .stdin(Stdio::null())
+        .output();
+    remove_cursor_script(input_path, start_s);
+    let result = result.map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        let _ = fs::remove_file(output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let tail = stderr
+            .lines()
+            .rev()
+            .take(12)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(format!("render_failed:\n{tail}"))
+    }
+}
+
+// Inverse of output_time_for: where out_s of the edited timeline comes from in
+// the recording, and how much of that clip is left.
+fn recording_time_for(segments: &[ClipWindowSegment], out_s: f64) -> Option<(f64, f64)> {
+    let mut elapsed = 0.0;
+    for seg in segments.iter() {
+        let length = (seg.end_s - seg.start_s) / seg.speed;
+        if out_s < elapsed + length {
+            return Some((seg.start_s + (out_s - elapsed) * seg.speed, seg.end_s));
+        }
+        elapsed += length;
+    }
+    None
+}
+
+// One frame of the edited timeline through the full export look, written as
+// PNG or JPEG depending on the output extension.
+#[tauri::command]
+async fn export_frame(
+    app: tauri::AppHandle,
+    input_path: String,
+    edit_state: EditState,
+    time_s: f64,
+    output_path: String,
+    camera_path: Option<String>,
+    long_edge: Option<u32>,
+) -> Result<String, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        render_frame(&app, input_path, &edit_state, time_s, output_path, camera_path, long_edge)
+    })
+    .await
+    .map_err(|_| "export_frame_join_failed".to_string())?
+    .map_err(AppError::from)
+}
+
+fn render_frame(
+    app: &tauri::AppHandle,
+    input_path: String,
+    edit_state: &EditState,
+    time_s: f64,
+    output_path: String,
+    camera_path: Option<String>,
+    long_edge: Option<u32>,
+) -> Result<String, String> {
+    if !PathBuf::from(&input_path).is_file() {
+        return Err("input_not_found".to_string());
+    }
+    if !time_s.is_finite() || time_s < 0.0 {
+        return Err("invalid_time".to_string());
+    }
+    let (start_s, clip_end_s) = match load_clip_track(&input_path).filter(|t| !t.segments.is_empty()) {
+        Some(track) => recording_time_for(&clip_segments_window(&track, 0.0, f64::MAX), time_s)
+            .ok_or("time_out_of_range")?,
+        None => (time_s, f64::MAX),
+    };
+    let end_s = clip_end_s.min(start_s + 0.5);
+    let camera_path = camera_path.filter(|p| !p.is_empty() && PathBuf::from(p).exists());
+    let profile = preview_profile_for(edit_state, long_edge.unwrap_or(1920).min(7680));
+    let filter = filtered_range_graph(
+        app,
+        &input_path,
+        camera_path.is_some(),
+        edit_state,
+        &profile,
+        (start_s, end_s),
+    );
+    let start = format!("{:.3}", start_s);
+    let mut args = vec![
+        "-y".to_string(),
+        "-ss".to_string(),
+        start.clone(),
+        "-i".to_string(),
+        input_path.clone(),
+    ];
+    if let Some(path) = camera_path.as_ref() {
+        args.extend(["-ss".to_string(), start, "-i".to_string(), path.clone()]);
+    }
+    args.extend([
+        "-filter_complex".to_string(),
+        filter,
+        "-map".to_string(),
+        "[v]".to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ]);
+    let output = PathBuf::from(&output_path);
+    let ext = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png")
+        .to_ascii_lowercase();
+    if matches!(ext.as_str(), "jpg" | "jpeg") {
+        args.extend(["-q:v".to_string(), "2".to_string()]);
+    }
+    args.push(output_path.clone());
+    if let Some(parent) = output.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let status = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    remove_cursor_script(&input_path, start_s);
+    let status = status.map_err(|e| e.to_string())?;
+    if !status.success() || !output.exists() {
+        return Err("frame_export_failed".to_string());
+    }
+    Ok(output_path)
+}
+
+#[tauri::command]
+fn render_loop(
+    app: tauri::AppHandle,
+    input_path: String,
+    edit_state: EditState,
+    start_s: f64,
+    end_s: f64,
+    camera_path: Option<String>,
+) -> Result<String, AppError> {
+    let dir = PathBuf::from(&input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .join("loops");
+    let range_key = format!(
+        "loop_{}_{}",
+        (start_s * 1000.0).round() as i64,
+        (end_s * 1000.0).round() as i64
+    );
+    let hash = edit_state_hash(&edit_state, &input_path, camera_path.as_deref().unwrap_or(""));
+    let output = dir.join(format!("{range_key}_{hash}.mp4"));
+    if output.exists() {
+        return Ok(output.to_string_lossy().to_string());
+    }
+    // Drop renders of the same range made with an older edit state.
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(&format!("{range_key}_")) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+    let profile = preview_profile_for(&edit_state, 960);
+    render_filtered_range(
+        &app,
+        &input_path,
+        camera_path.as_deref(),
+        &edit_state,
+        &profile,
+        (start_s, end_s),
+        &output,
+    )?;
+    Ok(output.to_string_lossy().to_string())
+}
+
+#[derive(Serialize)]
+struct PreviewRender {
+    video_path: String,
+    frames: Vec<String>,
+}
+
+const PREVIEW_RANGE_MAX_S: f64 = 10.0;
+const PREVIEW_RANGE_LONG_EDGE: u32 = 480;
+
+// Low-resolution WYSIWYG render of a short range through the real export
+// graph. Results live in the temp dir, keyed by the edit state hash.
+#[tauri::command]
+fn render_preview_range(
+    app: tauri::AppHandle,
+    input_path: String,
+    edit_state: EditState,
+    start_s: f64,
+    end_s: f64,
+    camera_path: Option<String>,
+    frames: Option<bool>,
+) -> Result<PreviewRender, AppError> {
+    let start_s = start_s.max(0.0);
+    let end_s = end_s.min(start_s + PREVIEW_RANGE_MAX_S);
+    let long_edge = PREVIEW_RANGE_LONG_EDGE;
+    let hash = edit_state_hash(
+        &edit_state,
+        &input_path,
+        &format!(
+            "{}|{}|{}|{}",
+            camera_path.as_deref().unwrap_or(""),
+            long_edge,
+            (start_s * 1000.0).round() as i64,
+            (end_s * 1000.0).round() as i64
+        ),
+    );
+    let dir = env::temp_dir().join("flash-recorder-preview").join(&hash);
+    let video = dir.join("preview.mp4");
+    if !video.exists() {
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let profile = preview_profile_for(&edit_state, long_edge);
+        render_filtered_range(
+            &app,
+            &input_path,
+            camera_path.as_deref(),
+            &edit_state,
+            &profile,
+            (start_s, end_s),
+            &video,
+        )?;
+    }
+    let mut frame_paths = Vec::new();
+    if frames.unwrap_or(false) {
+        let pattern = dir.join("frame_%04d.jpg");
+        if !dir.join("frame_0001.jpg").exists() {
+            let status = new_cmd(&ffmpeg_binary_with_app_handle(&app))
+                .args([
+                    "-y".to_string(),
+                    "-i".to_string(),
+                    video.to_string_lossy().to_string(),
+                    "-vf".to_string(),
+                    "fps=5".to_string(),
+                    "-q:v".to_string(),
+                    "4".to_string(),
+                    pattern.to_string_lossy().to_string(),
+                ])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err("preview_frames_failed".into());
+            }
+        }
+        let mut names = fs::read_dir(&dir)
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with("frame_") && n.ends_with(".jpg"))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        frame_paths = names
+            .into_iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+    }
+    Ok(PreviewRender {
+        video_path: video.to_string_lossy().to_string(),
+        frames: frame_paths,
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScrubThumbnailIndex {
+    interval_s: f64,
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    rows: u32,
+    count: u32,
+    sheets: Vec<String>,
+}
+
+const SCRUB_TILE_WIDTH: u32 = 160;
+const SCRUB_GRID: u32 = 10;
+
+// One frame per second tiled into 10x10 jpeg sheets next to the recording,
+// with thumbs/index.json describing how to find frame n.
+#[tauri::command]
+fn generate_scrub_thumbnails(
+    app: tauri::AppHandle,
+    input_path: String,
+) -> Result<ScrubThumbnailIndex, AppError> {
+    let input = PathBuf::from(&input_path);
+    let dir = input.parent().ok_or("invalid_input_path")?.join("thumbs");
+    let index_path = dir.join("index.json");
+    let input_modified = fs::metadata(&input)
+        .and_then(|meta| meta.modified())
+        .map_err(|_| "input_not_found".to_string())?;
+    let index_fresh = fs::metadata(&index_path)
+        .and_then(|meta| meta.modified())
+        .map(|modified| modified >= input_modified)
+        .unwrap_or(false);
+    if index_fresh {
+        if let Ok(data) = fs::read_to_string(&index_path) {
+            if let Ok(index) = serde_json::from_str::<ScrubThumbnailIndex>(&data) {
+                return Ok(index);
+            }
+        }
+    }
+    let probe = probe_media(&app, &input_path).ok_or("probe_failed")?;
+    let (width, height) = match (probe.width, probe.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => (w, h),
+        _ => return Err("probe_failed".into()),
+    };
+    let tile_height = evenize(((SCRUB_TILE_WIDTH * height) as f32 / width as f32).round() as i32).max(2) as u32;
+    let count = (probe.duration_ms.unwrap_or(0) as f64 / 1000.0).ceil().max(1.0) as u32;
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let output = new_cmd(&ffmpeg_binary_with_app_handle(&app))
+        .args([
+            "-y".to_string(),
+            "-i".to_string(),
+            input_path.clone(),
+            "-an".to_string(),
+            "-vf".to_string(),
+            format!(
+                "fps=1,scale={SCRUB_TILE_WIDTH}:{tile_height},tile={SCRUB_GRID}x{SCRUB_GRID}"
+            ),
+            "-q:v".to_string(),
+            "5".to_string(),
+            dir.join("sprite_%03d.jpg").to_string_lossy().to_string(),
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err("thumbnail_generation_failed".into());
+    }
+    let mut sheets = fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str().map(|n| n.to_string()))
+        .filter(|name| name.starts_with("sprite_") && name.ends_with(".jpg"))
+        .collect::<Vec<_>>();
+    sheets.sort();
+    let index = ScrubThumbnailIndex {
+        interval_s: 1.0,
+        tile_width: SCRUB_TILE_WIDTH,
+        tile_height,
+        columns: SCRUB_GRID,
+        rows: SCRUB_GRID,
+        count,
+        sheets,
+    };
+    let data = serde_json::to_string(&index).map_err(|e| e.to_string())?;
+    fs::write(&index_path, data).map_err(|e| e.to_string())?;
+    Ok(index)
+}
+
+// Recordings with a long edge above this get a proxy right after stopping.
+const PROXY_AUTO_LONG_EDGE: u32 = 1920;
+const PROXY_HEIGHT: u32 = 720;
+
+#[derive(Serialize, Clone)]
+struct ProxyEvent {
+    source: String,
+    proxy: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EditorMedia {
+    path: String,
+    is_proxy: bool,
+}
+
+fn proxy_path_for(source: &PathBuf) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("recording");
+    source.with_file_name(format!("{stem}_proxy.mp4"))
+}
+
+fn fresh_proxy_for(source: &PathBuf) -> Option<PathBuf> {
+    let proxy = proxy_path_for(source);
+    let source_modified = fs::metadata(source).and_then(|m| m.modified()).ok()?;
+    let proxy_modified = fs::metadata(&proxy).and_then(|m| m.modified()).ok()?;
+    (proxy_modified >= source_modified).then_some(proxy)
+}
+
+// All-intra so every frame is a seek point; written under a temporary name
+// and renamed so the editor never picks up a half-written proxy.
+fn build_proxy(app: &tauri::AppHandle, source: &PathBuf) -> Result<PathBuf, String> {
+    if !source.exists() {
+        return Err("input_not_found".to_string());
+    }
+    if let Some(proxy) = fresh_proxy_for(source) {
+        return Ok(proxy);
+    }
+    let proxy = proxy_path_for(source);
+    let partial = proxy.with_extension("part.mp4");
+    let output = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args([
+            "-y".to_string(),
+            "-i".to_string(),
+            source.to_string_lossy().to_string(),
+            "-vf".to_string(),
+            format!("scale=-2:'min({PROXY_HEIGHT},ih)'"),
+            "-c:v".to_string(),
+            "libx264".to_string(),
+            "-preset".to_string(),
+            "ultrafast".to_string(),
+            "-crf".to_string(),
+            "30".to_string(),
+            "-g".to_string(),
+            "1".to_string(),
+            "-pix_fmt".to_string(),
+            "yuv420p".to_string(),
+            "-c:a".to_string(),
+            "aac".to_string(),
+            "-b:a".to_string(),
+            "96k".to_string(),
+            "-movflags".to_string(),
+            "+faststart".to_string(),
+            partial.to_string_lossy().to_string(),
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        return Err("proxy_failed".to_string());
+    }
+    fs::rename(&partial, &proxy).map_err(|e| e.to_string())?;
+    Ok(proxy)
+}
+
+fn emit_proxy_result(app: &tauri::AppHandle, source: &PathBuf, result: &Result<PathBuf, String>) {
+    let _ = app.emit(
+        "proxy_ready",
+        ProxyEvent {
+            source: source.to_string_lossy().to_string(),
+            proxy: result.as_ref().ok().map(|p| p.to_string_lossy().to_string()),
+            error: result.as_ref().err().cloned(),
+        },
+    );
+}
+
+fn schedule_auto_proxy(app: tauri::AppHandle, source: PathBuf) {
+    thread::spawn(move || {
+        let Some(probe) = probe_media(&app, &source.to_string_lossy()) else {
+            return;
+        };
+        let settings = load_settings();
+        let long_edge = probe.width.unwrap_or(0).max(probe.height.unwrap_or(0));
+        if !settings.features.auto_proxy || long_edge <= settings.defaults.auto_proxy_long_edge {
+            return;
+        }
+        let result = build_proxy(&app, &source);
+        emit_proxy_result(&app, &source, &result);
+    });
+}
+
+#[tauri::command]
+async fn generate_proxy(app: tauri::AppHandle, input_path: String) -> Result<String, AppError> {
+    let source = PathBuf::from(&input_path);
+    let result = async_runtime::spawn_blocking({
+        let app = app.clone();
+        let source = source.clone();
+        move || build_proxy(&app, &source)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    emit_proxy_result(&app, &source, &result);
+    Ok(result.map(|proxy| proxy.to_string_lossy().to_string())?)
+}
+
+#[derive(Serialize, Clone)]
+struct SilenceRange {
+    start_s: f64,
+    end_s: f64,
+}
+
+#[derive(Serialize)]
+struct SilenceReport {
+    ranges: Vec<SilenceRange>,
+    clip_track_path: Option<String>,
+}
+
+// Speech right next to a gap is kept so cuts don't clip word edges.
+const SILENCE_KEEP_PAD_S: f64 = 0.15;
+
+fn run_silencedetect(
+    app: &tauri::AppHandle,
+    input_path: &str,
+    threshold_db: f64,
+    min_duration_s: f64,
+) -> Result<Vec<SilenceRange>, String> {
+    let filter = format!("silencedetect=noise={threshold_db}dB:d={min_duration_s}");
+    let output = new_cmd(&ffmpeg_binary_with_app_handle(app))
+        .args([
+            "-hide_banner",
+            "-i",
+            input_path,
+            "-map",
+            "0:a:0",
+            "-af",
+            filter.as_str(),
+            "-f",
+            "null",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("ffmpeg_spawn_failed: {e}"))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err("silence_detect_failed".to_string());
+    }
+    let value_after = |line: &str, key: &str| -> Option<f64> {
+        let idx = line.find(key)?;
+        line[idx + key.len()..].split_whitespace().next()?.parse().ok()
+    };
+    let mut ranges = Vec::new();
+    let mut open: Option<f64> = None;
+    for line in stderr.lines() {
+        if let Some(start) = value_after(line, "silence_start:") {
+            open = Some(start.max(0.0));
+        } else if let Some(end) = value_after(line, "silence_end:") {
+            if let Some(start) = open.take() {
+                ranges.push(SilenceRange { start_s: start, end_s: end });
+            }
+        }
+    }
+    // Silence running into the end of the file never gets a silence_end.
+    if let Some(start) = open {
+        let end = parse_duration_ms(&stderr).map(|ms| ms as f64 / 1000.0).unwrap_or(start);
+        if end > start {
+            ranges.push(SilenceRange { start_s: start, end_s: end });
+        }
+    }
+    Ok(ranges)
+}
+
+// Removes the silent ranges (less a small pad) from the clip segments,
+// splitting segments that contain a gap and keeping their speed.
+fn cut_silence_from_clip(segments: &[ClipSegment], ranges: &[SilenceRange]) -> Vec<ClipSegment> {
+    let mut result: Vec<ClipSegment> = segments.to_vec();
+    for range in ranges.iter() {
+        let cut_start = range.start_s + SILENCE_KEEP_PAD_S;
+        let cut_end = range.end_s - SILENCE_KEEP_PAD_S;
+        if cut_end <= cut_start {
+            continue;
+        }
+        let mut next = Vec::with_capacity(result.len() + 1);
+        for seg in result.into_iter() {
+            if seg.end_s <= cut_start || seg.start_s >= cut_end {
+                next.push(seg);
+                continue;
+            }
+            if seg.start_s < cut_start {
+                next.push(ClipSegment { end_s: cut_start, ..seg.clone() });
+            }
+            if seg.end_s > cut_end {
+                next.push(ClipSegment { start_s: cut_end, ..seg.clone() });
+            }
+        }
+        result = next;
+    }
+    result
+}
+
+// Finds dead air with silencedetect. With apply, the gaps are cut out of
+// clip_track.json (created over the whole recording when missing).
+#[tauri::command]
+async fn detect_silence(
+    app: tauri::AppHandle,
+    input_path: String,
+    threshold_db: Option<f64>,
+    min_duration: Option<f64>,
+    apply: Option<bool>,
+) -> Result<SilenceReport, AppError> {
+    let threshold_db = threshold_db.unwrap_or(-40.0).clamp(-90.0, 0.0);
+    let min_duration_s = min_duration.unwrap_or(1.0).clamp(0.1, 60.0);
+    let ranges = async_runtime::spawn_blocking({
+        let app = app.clone();
+        let input_path = input_path.clone();
+        move || run_silencedetect(&app, &input_path, threshold_db, min_duration_s)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    if !apply.unwrap_or(false) || ranges.is_empty() {
+        return Ok(SilenceReport { ranges, clip_track_path: None });
+    }
+    let dir = PathBuf::from(&input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    let path = dir.join("clip_track.json");
+    let segments = match load_clip_track(&input_path).filter(|t| !t.segments.is_empty()) {
+        Some(track) => track.segments,
+        None => {
+            let duration_ms = get_media_duration_ms(&app, &input_path).unwrap_or(0);
+            if duration_ms == 0 {
+                return Err("duration_unknown".into());
+            }
+            vec![ClipSegment { start_s: 0.0, end_s: duration_ms as f64 / 1000.0, speed: None }]
+        }
+    };
+    let track = ClipTrack { segments: cut_silence_from_clip(&segments, &ranges) };
+    let value = to_versioned_value(&track, CLIP_TRACK_MIGRATIONS)
+        .map_err(|_| "track_serialize_failed")?;
+    save_track_file(
+        &path,
+        &serde_json::to_string(&value).map_err(|_| "track_serialize_failed")?,
+    )?;
+    Ok(SilenceReport {
+        ranges,
+        clip_track_path: Some(path.to_string_lossy().to_string()),
+    })
+}
+
+// Copies an external .srt or .vtt into the session as subtitles.srt, which
+// burn-in and soft subtitle export read from.
+#[tauri::command]
+fn attach_subtitles(input_path: String, subtitle_path: String) -> Result<String, AppError> {
+    let data = fs::read_to_string(&subtitle_path).map_err(|_| "subtitle_read_failed")?;
+    let cues = parse_srt(&data);
+    if cues.is_empty() {
+        return Err("subtitle_parse_failed".into());
+    }
+    let dir = PathBuf::from(&input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    let path = dir.join("subtitles.srt");
+    fs::write(&path, write_srt(&cues)).map_err(|_| "subtitle_write_failed")?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Writes the session subtitles, re-timed to the edit, to output_path as SRT
+// or (by extension) VTT.
+#[tauri::command]
+fn export_subtitles(input_path: String, output_path: String) -> Result<String, AppError> {
+    let cues = output_subtitle_cues(&input_path, (0.0, f64::MAX));
+    if cues.is_empty() {
+        return Err("subtitles_missing".into());
+    }
+    let output = PathBuf::from(&output_path);
+    let data = match output.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("vtt") => write_vtt(&cues),
+        _ => write_srt(&cues),
+    };
+    fs::write(&output, data).map_err(|_| "subtitle_write_failed")?;
+    Ok(output_path)
+}
+
+// Resolves which file the editor should load for a track of the session that
+// input_path belongs to: the proxy when one is up to date, else the original.
+#[tauri::command]
+fn resolve_editor_media(input_path: String, track: Option<String>) -> Result<EditorMedia, AppError> {
+    let input = PathBuf::from(&input_path);
+    let source = match track.as_deref() {
+        Some("camera") => input.with_file_name("camera.mp4"),
+        Some("screen") => input.with_file_name("recording.mp4"),
+        _ => input,
+    };
+    if !source.exists() {
+        return Err("input_not_found".into());
+    }
+    Ok(match fresh_proxy_for(&source) {
+        Some(proxy) => EditorMedia {
+            path: proxy.to_string_lossy().to_string(),
+            is_proxy: true,
+        },
+        None => EditorMedia {
+            path: source.to_string_lossy().to_string(),
+            is_proxy: false,
+        },
+    })
+}
+
+// Single frame as PNG, cached under frames/ next to the input. Used for poster
+// frames and for picking zoom anchors and blur regions in the editor.
+#[tauri::command]
+fn get_frame(
+    app: tauri::AppHandle,
+    input_path: String,
+    time_s: f64,
+    max_width: Option<u32>,
+) -> Result<String, AppError> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err("input_not_found".into());
+    }
+    let time_s = time_s.max(0.0);
+    let max_width = max_width.unwrap_or(0);
+    let dir = input.parent().ok_or("invalid_input_path")?.join("frames");
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("frame");
+    let output = dir.join(format!(
+        "{stem}_{}_{max_width}.png",
+        (time_s * 1000.0).round() as u64
+    ));
+    if output.exists() {
+        return Ok(output.to_string_lossy().to_string());
+    }
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let mut args = vec![
+        "-y".to_string(),
+        "-ss".to_string(),
+        format!("{time_s:.3}"),
+        "-i".to_string(),
+        input_path.clone(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ];
+    if max_width > 0 {
+        args.extend([
+            "-vf".to_string(),
+            format!("scale='min({max_width},iw)':-2"),
+        ]);
+    }
+    args.push(output.to_string_lossy().to_string());
+    let status = new_cmd(&ffmpeg_binary_with_app_handle(&app))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| e.to_string())?;
+    if !status.success() || !output.exists() {
+        return Err("frame_extract_failed".into());
+    }
+    Ok(output.to_string_lossy().to_string())
+}
+
+const ZOOM_MAX_SCALE: f32 = 4.0;
+// Clicks closer together than this share one zoom window.
+const ZOOM_CLICK_GAP_S: f64 = 3.0;
+const ZOOM_HOLD_S: f64 = 1.5;
+
+fn zoom_track_path(input_path: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join("zoom_track.json"))
+}
+
+fn load_zoom_track(input_path: &str) -> Option<ZoomTrack> {
+    let path = zoom_track_path(input_path).ok()?;
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_zoom_track(input_path: &str, track: &mut ZoomTrack) -> Result<PathBuf, String> {
+    track
+        .windows
+        .sort_by(|a, b| a.start_s.partial_cmp(&b.start_s).unwrap_or(std::cmp::Ordering::Equal));
+    track.frames = compute_zoom_frames(track);
+    let path = zoom_track_path(input_path)?;
+    let data = serde_json::to_string(track).map_err(|_| "track_serialize_failed")?;
+    save_track_file(&path, &data)?;
+    Ok(path)
+}
+
+// Ramp lengths for a window, shortened so in and out never overlap.
+fn zoom_ramps(window: &ZoomWindow, settings: &ZoomSettings) -> (f64, f64) {
+    let half = (window.end_s - window.start_s) / 2.0;
+    (
+        settings.ramp_in_s.min(half).max(0.001),
+        settings.ramp_out_s.min(half).max(0.001),
+    )
+}
+
+fn zoom_ramp(window: &ZoomWindow, settings: &ZoomSettings, time_s: f64) -> f64 {
+    let (ramp_in, ramp_out) = zoom_ramps(window, settings);
+    ((time_s - window.start_s) / ramp_in)
+        .min((window.end_s - time_s) / ramp_out)
+        .clamp(0.0, 1.0)
+}
+
+fn zoom_center_at(window: &ZoomWindow, time_s: f64) -> (f32, f32) {
+    let anchors = &window.anchors;
+    let Some(first) = anchors.first() else {
+        return (0.5, 0.5);
+    };
+    if time_s <= first.time_s {
+        return (first.x, first.y);
+    }
+    for pair in anchors.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if time_s <= b.time_s {
+            let span = (b.time_s - a.time_s).max(1e-6);
+            let k = ((time_s - a.time_s) / span) as f32;
+            return (a.x + (b.x - a.x) * k, a.y + (b.y - a.y) * k);
+        }
+    }
+    let last = anchors.last().unwrap_or(first);
+    (last.x, last.y)
 }
 
-#[tauri::command]
-fn exclude_window_from_capture(app: tauri::AppHandle, label: String) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        use windows_sys::Win32::Foundation::HWND;
-        use windows_sys::Win32::UI::WindowsAndMessaging::{
-            SetWindowDisplayAffinity, WDA_EXCLUDEFROMCAPTURE,
-        };
+const ZOOM_PAN_MAX_POINTS: f64 = 64.0;
+const ZOOM_PAN_MAX_TAU_S: f64 = 0.8;
 
-        let window = app.get_webview_window(&label).ok_or("window_not_found")?;
-        let hwnd = window.hwnd().map_err(|_| "hwnd_unavailable")?;
-        let hwnd: HWND = hwnd.0 as HWND;
-        let result = unsafe { SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE) };
-        if result == 0 {
-            return Err("exclude_from_capture_failed".into());
-        }
-        return Ok(());
+// Resamples the anchor path and runs a forward and a backward low-pass over
+// it, which removes the jitter of raw cursor anchors without adding lag. The
+// point count is capped so the zoompan expressions stay small.
+fn smoothed_zoom_anchors(window: &ZoomWindow, settings: &ZoomSettings) -> Vec<ZoomAnchor> {
+    let tau = settings.pan_smoothing.clamp(0.0, 1.0) as f64 * ZOOM_PAN_MAX_TAU_S;
+    if window.anchors.len() < 2 || tau <= 0.0 {
+        return window.anchors.clone();
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        let _ = (app, label);
-        Ok(())
+    let span = window.end_s - window.start_s;
+    let step = (settings.sample_ms.max(10) as f64 / 1000.0).max(span / ZOOM_PAN_MAX_POINTS);
+    let mut points = Vec::new();
+    let mut t = window.start_s;
+    while t <= window.end_s + 1e-9 {
+        let (x, y) = zoom_center_at(window, t);
+        points.push(ZoomAnchor { time_s: t, x, y });
+        t += step;
+    }
+    let alpha = (1.0 - (-step / tau).exp()) as f32;
+    for i in 1..points.len() {
+        let prev = (points[i - 1].x, points[i - 1].y);
+        points[i].x = prev.0 + alpha * (points[i].x - prev.0);
+        points[i].y = prev.1 + alpha * (points[i].y - prev.1);
     }
+    for i in (0..points.len().saturating_sub(1)).rev() {
+        let next = (points[i + 1].x, points[i + 1].y);
+        points[i].x = next.0 + alpha * (points[i].x - next.0);
+        points[i].y = next.1 + alpha * (points[i].y - next.1);
+    }
+    points
 }
 
-#[tauri::command]
-fn start_recording(
-    app: tauri::AppHandle,
-    state: State<RecordingState>,
-    preview_state: State<PreviewState>,
-    request: StartRecordingRequest,
-) -> Result<StartRecordingResponse, String> {
-    let mut guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
-    if guard.is_some() {
-        return Err("recording_already_running".into());
+fn compute_zoom_frames(track: &ZoomTrack) -> Vec<ZoomFrame> {
+    let step = track.settings.sample_ms.max(10) as f64 / 1000.0;
+    let mut frames = Vec::new();
+    for window in track.windows.iter() {
+        let smoothed = ZoomWindow {
+            anchors: smoothed_zoom_anchors(window, &track.settings),
+            ..window.clone()
+        };
+        let mut t = window.start_s;
+        while t <= window.end_s + 1e-9 {
+            let (x, y) = zoom_center_at(&smoothed, t);
+            frames.push(ZoomFrame {
+                time_s: t,
+                scale: 1.0 + (window.scale - 1.0) * zoom_ramp(window, &track.settings, t) as f32,
+                x,
+                y,
+            });
+            t += step;
+        }
     }
+    frames
+}
 
-    let session_id = SystemTime::now()
+fn new_zoom_window_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_millis()
-        .to_string();
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("zoom_{now}_{}", COUNTER.fetch_add(1, Ordering::Relaxed))
+}
 
-    let base_dir = work_base_dir();
-    let output_dir = base_dir.join(&session_id);
-    fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
-    let log_error = |message: String| {
-        write_error_log(&output_dir, &message);
-        message
+fn read_cursor_events(dir: &PathBuf) -> Vec<CursorEventRecord> {
+    let Ok(path) = cursor_path_for_dir(dir) else {
+        return Vec::new();
     };
-    let output_path = output_dir.join("recording.mp4");
-    let camera_path = output_dir.join("camera.mp4");
-    let log_path = output_dir.join("ffmpeg.log");
-    let cursor_path = output_dir.join("cursor.jsonl");
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CursorEventRecord>(line).ok())
+        .collect()
+}
 
-    let fps = if request.fps == 0 { 60 } else { request.fps };
-    let resolution_value = parse_resolution_value(&request.resolution);
-    let bitrate_kbps = bitrate_for_resolution(resolution_value);
+fn capture_size_for_dir(dir: &PathBuf) -> (f32, f32) {
+    fs::read_to_string(dir.join("capture.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<CaptureMeta>(&data).ok())
+        .map(|meta| (meta.rect.width.max(1) as f32, meta.rect.height.max(1) as f32))
+        .unwrap_or((1920.0, 1080.0))
+}
 
-    let capture_mode = request
-        .capture_mode
-        .as_deref()
-        .unwrap_or("screen")
-        .to_string();
-    let screen_rect = {
-        #[cfg(target_os = "windows")]
-        {
-            use windows_sys::Win32::UI::WindowsAndMessaging::{
-                GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
-                SM_YVIRTUALSCREEN,
+// Groups clicks into windows and follows the cursor inside each one, adding
+// an anchor only once it has moved far enough to be worth a pan.
+fn derive_zoom_windows(
+    events: &[CursorEventRecord],
+    capture_size: (f32, f32),
+    duration_s: f64,
+    settings: &ZoomSettings,
+) -> Vec<ZoomWindow> {
+    let clicks: Vec<&CursorEventRecord> = events.iter().filter(|e| e.kind == "down").collect();
+    let mut groups: Vec<(f64, f64)> = Vec::new();
+    for click in clicks.iter() {
+        let t = click.offset_ms as f64 / 1000.0;
+        match groups.last_mut() {
+            Some(last) if t - last.1 <= ZOOM_CLICK_GAP_S => last.1 = t,
+            _ => groups.push((t, t)),
+        }
+    }
+    let limit = if duration_s > 0.0 { duration_s } else { f64::MAX };
+    let mut windows = Vec::new();
+    for (first, last) in groups {
+        let start_s = (first - settings.ramp_in_s).max(0.0);
+        let end_s = (last + ZOOM_HOLD_S + settings.ramp_out_s).min(limit);
+        if end_s <= start_s {
+            continue;
+        }
+        let mut anchors: Vec<ZoomAnchor> = Vec::new();
+        for event in events.iter() {
+            let t = event.offset_ms as f64 / 1000.0;
+            if t < start_s || t > end_s {
+                continue;
+            }
+            let far_enough = match anchors.last() {
+                None => true,
+                Some(prev) => {
+                    let dx = (event.axn - prev.x) * capture_size.0;
+                    let dy = (event.ayn - prev.y) * capture_size.1;
+                    (dx * dx + dy * dy).sqrt() >= settings.follow_threshold_px
+                }
             };
-            let x = unsafe { GetSystemMetrics(SM_XVIRTUALSCREEN) };
-            let y = unsafe { GetSystemMetrics(SM_YVIRTUALSCREEN) };
-            let width = evenize(unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) }.max(2));
-            let height = evenize(unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) }.max(2));
-            Rect {
-                x,
-                y,
-                width,
-                height,
+            if far_enough || event.kind == "down" {
+                anchors.push(ZoomAnchor {
+                    time_s: t,
+                    x: event.axn.clamp(0.0, 1.0),
+                    y: event.ayn.clamp(0.0, 1.0),
+                });
             }
         }
-        #[cfg(not(target_os = "windows"))]
-        {
-            Rect {
-                x: 0,
-                y: 0,
-                width: 1920,
-                height: 1080,
+        windows.push(ZoomWindow {
+            id: new_zoom_window_id(),
+            start_s,
+            end_s,
+            scale: settings.max_zoom,
+            manual: false,
+            anchors,
+        });
+    }
+    windows
+}
+
+fn zoom_windows_overlap(track: &ZoomTrack, start_s: f64, end_s: f64, skip_id: Option<&str>) -> bool {
+    track
+        .windows
+        .iter()
+        .filter(|w| Some(w.id.as_str()) != skip_id)
+        .any(|w| start_s < w.end_s && w.start_s < end_s)
+}
+
+// Hand-placed windows may go up to ZOOM_MAX_SCALE; settings.max_zoom only
+// caps the derived ones.
+fn validate_zoom_window(window: &mut ZoomWindow) -> Result<(), String> {
+    if window.end_s <= window.start_s || window.start_s < 0.0 {
+        return Err("invalid_zoom_range".to_string());
+    }
+    window.scale = window.scale.clamp(1.0, ZOOM_MAX_SCALE);
+    window
+        .anchors
+        .retain(|a| a.time_s >= window.start_s && a.time_s <= window.end_s);
+    for anchor in window.anchors.iter_mut() {
+        anchor.x = anchor.x.clamp(0.0, 1.0);
+        anchor.y = anchor.y.clamp(0.0, 1.0);
+    }
+    window
+        .anchors
+        .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(())
+}
+
+// zoompan stage for the source video: one linear ramp per window and a
+// piecewise-linear pan between anchors. offset_s maps the input timestamps of
+// a seeked render back onto the recording timeline.
+fn derive_zoom_override(
+    input_path: &str,
+    size: (u32, u32),
+    fps: u32,
+    (offset_s, end_s): (f64, f64),
+    crop: Option<&CropView>,
+    output_size: (u32, u32),
+) -> Option<String> {
+    let track = load_zoom_track(input_path)?;
+    let windows: Vec<&ZoomWindow> = track
+        .windows
+        .iter()
+        .filter(|w| w.end_s > offset_s && w.start_s < end_s && w.scale > 1.0)
+        .collect();
+    if windows.is_empty() || size.0 == 0 || size.1 == 0 {
+        return None;
+    }
+    let t = format!("(it+{offset_s})");
+    let mut zoom = "1".to_string();
+    let mut cx = "0.5".to_string();
+    let mut cy = "0.5".to_string();
+    for w in windows.iter().rev() {
+        let (ramp_in, ramp_out) = zoom_ramps(w, &track.settings);
+        let cond = format!("between({t},{},{})", w.start_s, w.end_s);
+        zoom = format!(
+            "if({cond},1+({}-1)*clip(min(({t}-{s})/{ramp_in},({e}-{t})/{ramp_out}),0,1),{zoom})",
+            w.scale,
+            s = w.start_s,
+            e = w.end_s
+        );
+        let anchors = smoothed_zoom_anchors(w, &track.settings);
+        let pan = |pick: fn(&ZoomAnchor) -> f32| -> String {
+            let Some(first) = anchors.first() else {
+                return "0.5".to_string();
+            };
+            let last = anchors.last().unwrap_or(first);
+            let mut expr = format!("{}", pick(last));
+            for pair in anchors.windows(2).rev() {
+                let (a, b) = (&pair[0], &pair[1]);
+                let span = (b.time_s - a.time_s).max(1e-6);
+                expr = format!(
+                    "if(lt({t},{bt}),{av}+({bv}-{av})*({t}-{at})/{span},{expr})",
+                    bt = b.time_s,
+                    at = a.time_s,
+                    av = pick(a),
+                    bv = pick(b)
+                );
             }
-        }
+            format!("if(lt({t},{}),{},{expr})", first.time_s, pick(first))
+        };
+        let (x, y) = match crop {
+            Some(crop) => crop.map_expr(&t, &pan(|a| a.x), &pan(|a| a.y)),
+            None => (pan(|a| a.x), pan(|a| a.y)),
+        };
+        cx = format!("if({cond},{x},{cx})");
+        cy = format!("if({cond},{y},{cy})");
+    }
+    // zoompan crops on whole pixels, which shows as stair-stepping during slow
+    // pans; cropping from an upscaled copy gives it sub-pixel positions.
+    let factor = match track.settings.quality.as_str() {
+        "fast" => 1,
+        "max" => 3,
+        _ => 2,
+    }
+    .min(supersample_max_edge(output_size) / size.0.max(size.1).max(1))
+    .max(1);
+    let supersample = if factor > 1 {
+        format!(
+            "scale={}:{}:flags=lanczos,",
+            size.0 * factor,
+            size.1 * factor
+        )
+    } else {
+        String::new()
     };
-    let mut region_rect: Option<Rect> = None;
-    let mut args = vec![
-        "-y".into(),
-        "-thread_queue_size".into(),
-        "512".into(),
-        "-rtbufsize".into(),
-        "256M".into(),
-        "-f".into(),
-        "gdigrab".into(),
-        "-framerate".into(),
-        fps.to_string(),
-    ];
+    // zoompan stamps frames from its own counter, so feed it a constant rate.
+    Some(format!(
+        "fps={fps},{supersample}zoompan=z='{zoom}':x='clip(({cx})*iw-iw/zoom/2,0,iw-iw/zoom)':y='clip(({cy})*ih-ih/zoom/2,0,ih-ih/zoom)':d=1:s={w}x{h}:fps={fps}",
+        w = size.0,
+        h = size.1
+    ))
+}
 
-    if capture_mode == "window" {
-        let window_title = request
-            .window_title
-            .clone()
-            .ok_or("window_title_required")?;
-        args.extend(["-i".into(), format!("title={window_title}")]);
-    } else if capture_mode == "region" {
-        let mut region = request.region.clone().ok_or("region_required")?;
-        if region.width <= 0 || region.height <= 0 {
-            return Err("invalid_region".into());
-        }
-        if region.x % 2 != 0 {
-            region.x += 1;
-            region.width -= 1;
-        }
-        if region.y % 2 != 0 {
-            region.y += 1;
-            region.height -= 1;
-        }
-        if region.width % 2 != 0 {
-            region.width -= 1;
-        }
-        if region.height % 2 != 0 {
-            region.height -= 1;
-        }
-        if region.width <= 0 || region.height <= 0 {
-            return Err("invalid_region".into());
-        }
-        region_rect = Some(Rect {
-            x: region.x,
-            y: region.y,
-            width: region.width,
-            height: region.height,
-        });
-        args.extend([
-            "-offset_x".into(),
-            region.x.to_string(),
-            "-offset_y".into(),
-            region.y.to_string(),
-            "-video_size".into(),
-            format!("{}x{}", region.width, region.height),
-            "-i".into(),
-            "desktop".into(),
-        ]);
-    } else {
-        args.extend([
-            "-offset_x".into(),
-            screen_rect.x.to_string(),
-            "-offset_y".into(),
-            screen_rect.y.to_string(),
-            "-video_size".into(),
-            format!("{}x{}", screen_rect.width, screen_rect.height),
-            "-i".into(),
-            "desktop".into(),
-        ]);
-    }
+// Largest supersampled edge; keeps 4K sources from upscaling past 8K.
+const ZOOM_SUPERSAMPLE_MAX_EDGE: u32 = 8192;
 
-    let mut input_index: usize = 1;
-    let mut camera_index: Option<usize> = None;
-    let mut audio_index: Option<usize> = None;
+// Past twice the output size the extra detail is thrown away by the final
+// scale, so supersampling stops there.
+fn supersample_max_edge(output_size: (u32, u32)) -> u32 {
+    (output_size.0.max(output_size.1) * 2).clamp(1, ZOOM_SUPERSAMPLE_MAX_EDGE)
+}
 
-    let camera_device = request.camera_device.unwrap_or_else(|| "auto".into());
-    let mut selected_camera: Option<String> = None;
-    if camera_device == "auto" || camera_device == "default" {
-        let devices = list_video_devices_internal(&app).map_err(log_error)?;
-        selected_camera = devices.into_iter().next();
-    } else if camera_device != "off"
-        && camera_device != "none"
-        && camera_device != "no-camera"
-        && !camera_device.trim().is_empty()
+// eq and an optional .cube LUT for the screen capture.
+fn screen_color_stage(edit_state: &EditState) -> Option<String> {
+    let brightness = edit_state.screen_brightness.clamp(-1.0, 1.0);
+    let contrast = edit_state.screen_contrast.clamp(0.0, 3.0);
+    let saturation = edit_state.screen_saturation.clamp(0.0, 3.0);
+    let gamma = edit_state.screen_gamma.clamp(0.1, 3.0);
+    let mut stages = Vec::new();
+    if brightness.abs() > 0.001
+        || [contrast, saturation, gamma].iter().any(|v| (v - 1.0).abs() > 0.001)
     {
-        selected_camera = Some(camera_device.clone());
+        stages.push(format!(
+            "eq=brightness={brightness:.3}:contrast={contrast:.3}:saturation={saturation:.3}:gamma={gamma:.3}"
+        ));
+    }
+    if let Some(lut) = edit_state
+        .screen_lut
+        .as_deref()
+        .filter(|path| path.to_ascii_lowercase().ends_with(".cube") && PathBuf::from(path).is_file())
+    {
+        stages.push(format!("lut3d=file='{}'", filter_escape_path(lut)));
     }
+    (!stages.is_empty()).then(|| stages.join(","))
+}
 
-    if let Some(camera_name) = selected_camera.as_ref() {
-        args.extend([
-            "-thread_queue_size".into(),
-            "512".into(),
-            "-f".into(),
-            "dshow".into(),
-            "-i".into(),
-            format!("video={}", camera_name),
-        ]);
-        camera_index = Some(input_index);
-        input_index += 1;
+// Effects applied to the raw recording before it is framed. The crop comes
+// first so it always cuts the same band of the capture (a taskbar, say);
+// click ripples, zoom and cursor then work inside the crop rectangle, with
+// their capture coordinates mapped through CropView. Ripples go before the
+// zoom so it magnifies them with everything else, and the synthetic cursor
+// after it so it is drawn at full resolution.
+// Returns a graph fragment reading [0:v] and ending in [src].
+fn source_effects_for(
+    input_path: &str,
+    edit_state: &EditState,
+    probe: &MediaProbe,
+    range: (f64, f64),
+    output_size: (u32, u32),
+) -> Option<String> {
+    let fps = probe.fps.map(|f| f.round() as u32).filter(|f| *f > 0).unwrap_or(30);
+    let view = crop_view_for(input_path, probe);
+    let size = match view.as_ref() {
+        Some(view) => (view.crop_w as u32, view.crop_h as u32),
+        None => (probe.width?, probe.height?),
+    };
+    let pre = match (view.as_ref().map(|view| view.crop_filter(range.0)), screen_color_stage(edit_state)) {
+        (Some(crop), Some(color)) => Some(format!("{crop},{color}")),
+        (crop, color) => crop.or(color),
+    };
+    let mut graph = Vec::new();
+    let mut input = "0:v";
+    if let Some(pre) = pre {
+        graph.push(format!("[0:v]{pre}[cropped]"));
+        input = "cropped";
+    }
+    if let Some(ripple) = click_ripple_stage(input_path, edit_state, fps, range, view.as_ref(), input) {
+        graph.push(format!("{ripple}[rippled]"));
+        input = "rippled";
     }
+    if let Some(zoom) = derive_zoom_override(input_path, size, fps, range, view.as_ref(), output_size) {
+        graph.push(format!("[{input}]{zoom}[zoomed]"));
+        input = "zoomed";
+    }
+    if let Some(cursor) =
+        synthetic_cursor_stage(input_path, edit_state, size, fps, range, view.as_ref(), input)
+    {
+        graph.push(format!("{cursor}[cursored]"));
+        input = "cursored";
+    }
+    if graph.is_empty() {
+        return None;
+    }
+    graph.push(format!("[{input}]null[src]"));
+    Some(graph.join(";"))
+}
 
-    let mic_device = request.mic_device.unwrap_or_else(|| "auto".into());
-    let mut selected_device: Option<String> = None;
-    if mic_device == "auto" || mic_device == "default" {
-        let devices = list_audio_devices_internal(&app).map_err(log_error)?;
-        selected_device = devices.into_iter().next();
-    } else if mic_device != "mute" && !mic_device.trim().is_empty() {
-        selected_device = Some(mic_device.clone());
+fn crop_track_path(input_path: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join("crop_track.json"))
+}
+
+fn load_crop_track(input_path: &str) -> Option<CropTrack> {
+    let path = crop_track_path(input_path).ok()?;
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn validate_crop_track(track: &mut CropTrack) -> Result<(), String> {
+    let rect = &mut track.rect;
+    rect.width = rect.width.clamp(0.0, 1.0);
+    rect.height = rect.height.clamp(0.0, 1.0);
+    if rect.width < 0.05 || rect.height < 0.05 {
+        return Err("invalid_crop_rect".to_string());
+    }
+    rect.x = rect.x.clamp(0.0, 1.0 - rect.width);
+    rect.y = rect.y.clamp(0.0, 1.0 - rect.height);
+    let (max_x, max_y) = (1.0 - rect.width, 1.0 - rect.height);
+    track.keyframes.retain(|k| k.time_s.is_finite() && k.time_s >= 0.0);
+    for keyframe in track.keyframes.iter_mut() {
+        keyframe.x = keyframe.x.clamp(0.0, max_x);
+        keyframe.y = keyframe.y.clamp(0.0, max_y);
+    }
+    track
+        .keyframes
+        .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(())
+}
+
+// The crop rectangle of a recording. Keyframed positions are interpolated
+// linearly on the recording timeline, like the zoom pan.
+struct CropView {
+    track: CropTrack,
+    crop_w: i32,
+    crop_h: i32,
+    // Crop size as fractions of the capture.
+    frac_w: f64,
+    frac_h: f64,
+}
+
+fn crop_view_for(input_path: &str, probe: &MediaProbe) -> Option<CropView> {
+    let track = load_crop_track(input_path)?;
+    let (width, height) = (probe.width? as f32, probe.height? as f32);
+    let crop_w = evenize((track.rect.width * width).round() as i32).max(2);
+    let crop_h = evenize((track.rect.height * height).round() as i32).max(2);
+    if crop_w >= width as i32 && crop_h >= height as i32 {
+        return None;
+    }
+    Some(CropView {
+        track,
+        crop_w,
+        crop_h,
+        frac_w: crop_w as f64 / width as f64,
+        frac_h: crop_h as f64 / height as f64,
+    })
+}
+
+impl CropView {
+    // Top-left corner at time_s, as fractions of the capture.
+    fn origin_at(&self, time_s: f64) -> (f64, f64) {
+        let keyframes = &self.track.keyframes;
+        let (x, y) = match keyframes.iter().position(|k| k.time_s > time_s) {
+            None => keyframes
+                .last()
+                .map_or((self.track.rect.x, self.track.rect.y), |k| (k.x, k.y)),
+            Some(0) => (keyframes[0].x, keyframes[0].y),
+            Some(idx) => {
+                let (a, b) = (&keyframes[idx - 1], &keyframes[idx]);
+                let k = ((time_s - a.time_s) / (b.time_s - a.time_s).max(1e-6)) as f32;
+                (a.x + (b.x - a.x) * k, a.y + (b.y - a.y) * k)
+            }
+        };
+        (
+            (x as f64).clamp(0.0, 1.0 - self.frac_w),
+            (y as f64).clamp(0.0, 1.0 - self.frac_h),
+        )
+    }
+
+    // origin_at as an ffmpeg expression of the recording time t.
+    fn origin_expr(&self, pick: fn(&CropKeyframe) -> f32, base: f32, frac: f64, t: &str) -> String {
+        let keyframes = &self.track.keyframes;
+        let Some(first) = keyframes.first() else {
+            return format!("{}", (base as f64).clamp(0.0, 1.0 - frac));
+        };
+        let last = keyframes.last().unwrap_or(first);
+        let mut expr = format!("{}", pick(last));
+        for pair in keyframes.windows(2).rev() {
+            let (a, b) = (&pair[0], &pair[1]);
+            let span = (b.time_s - a.time_s).max(1e-6);
+            expr = format!(
+                "if(lt({t},{bt}),{av}+({bv}-{av})*({t}-{at})/{span},{expr})",
+                bt = b.time_s,
+                at = a.time_s,
+                av = pick(a),
+                bv = pick(b)
+            );
+        }
+        format!("clip(if(lt({t},{}),{},{expr}),0,{})", first.time_s, pick(first), 1.0 - frac)
     }
 
-    if let Some(device_name) = selected_device.as_ref() {
-        args.extend([
-            "-thread_queue_size".into(),
-            "512".into(),
-            "-f".into(),
-            "dshow".into(),
-            "-i".into(),
-            format!("audio={}", device_name),
-        ]);
-        audio_index = Some(input_index);
-    } else {
-        args.push("-an".into());
+    // A capture-relative point mapped into the crop rectangle at time_s.
+    fn map_point(&self, time_s: f64, x: f64, y: f64) -> (f64, f64) {
+        let (ox, oy) = self.origin_at(time_s);
+        ((x - ox) / self.frac_w, (y - oy) / self.frac_h)
     }
 
-    let preview_url = if camera_index.is_some() {
-        Some("webrtc://local".to_string())
-    } else {
-        None
-    };
+    // Same as map_point for expressions: t is the recording time.
+    fn map_expr(&self, t: &str, x: &str, y: &str) -> (String, String) {
+        let ox = self.origin_expr(|k| k.x, self.track.rect.x, self.frac_w, t);
+        let oy = self.origin_expr(|k| k.y, self.track.rect.y, self.frac_h, t);
+        (
+            format!("(({x})-({ox}))/{}", self.frac_w),
+            format!("(({y})-({oy}))/{}", self.frac_h),
+        )
+    }
 
-    if preview_url.is_some() {
-        {
-            let mut preview_guard = preview_state
-                .inner
-                .lock()
-                .map_err(|_| "preview_state_lock_failed")?;
-            if let Some(existing) = preview_guard.take() {
-                async_runtime::block_on(stop_preview_session(existing));
-            }
-        }
-        let session = async_runtime::block_on(create_preview_session()).map_err(log_error)?;
-        let mut preview_guard = preview_state
-            .inner
-            .lock()
-            .map_err(|_| "preview_state_lock_failed")?;
-        *preview_guard = Some(session);
+    fn crop_filter(&self, offset_s: f64) -> String {
+        let t = format!("(t+{offset_s})");
+        format!(
+            "crop=w={w}:h={h}:x='({x})*iw':y='({y})*ih'",
+            w = self.crop_w,
+            h = self.crop_h,
+            x = self.origin_expr(|k| k.x, self.track.rect.x, self.frac_w, &t),
+            y = self.origin_expr(|k| k.y, self.track.rect.y, self.frac_h, &t)
+        )
     }
+}
 
-    if let Some(camera_input) = camera_index {
-        let filter = format!(
-            "[{camera_input}:v]crop='min(iw,ih)':'min(iw,ih)',hflip,split=2[cam_preview][cam_avatar];[cam_preview]fps=20,scale=240:240:force_original_aspect_ratio=increase,crop=240:240,format=yuv420p[preview];[cam_avatar]fps=30,scale=240:240:force_original_aspect_ratio=increase,crop=240:240,format=yuv420p[avatar]"
-        );
-        args.extend([
-            "-filter_complex".into(),
-            filter,
-            "-map".into(),
-            "0:v".into(),
-        ]);
-        if let Some(audio_input) = audio_index {
-            args.push("-map".into());
-            args.push(format!("{audio_input}:a"));
+const CURSOR_MAX_TAU_S: f64 = 0.25;
+
+// Zoom scale and normalized center at time_s, matching the zoompan expression
+// built by derive_zoom_override.
+fn zoom_view_at(track: &ZoomTrack, smoothed: &[ZoomWindow], time_s: f64) -> (f64, f64, f64) {
+    for (window, path) in track.windows.iter().zip(smoothed.iter()) {
+        if window.scale > 1.0 && time_s >= window.start_s && time_s <= window.end_s {
+            let zoom = 1.0 + (window.scale as f64 - 1.0) * zoom_ramp(window, &track.settings, time_s);
+            let (x, y) = zoom_center_at(path, time_s);
+            return (zoom, x as f64, y as f64);
         }
     }
+    (1.0, 0.5, 0.5)
+}
 
-    let bitrate_value = format!("{}k", bitrate_kbps.max(1));
-    match request.format.as_str() {
-        "h265" | "hevc" => {
-            args.extend([
-                "-c:v".into(),
-                "libx265".into(),
-                "-preset".into(),
-                "fast".into(),
-                "-b:v".into(),
-                bitrate_value.clone(),
-            ]);
+// Cursor path resampled at the output frame rate over [offset_s, end_s] and
+// low-passed forward and backward like the zoom pan.
+fn smoothed_cursor_path(
+    events: &[CursorEventRecord],
+    fps: u32,
+    smoothing: f32,
+    (offset_s, end_s): (f64, f64),
+) -> Vec<(f64, f64, f64)> {
+    let points: Vec<(f64, f64, f64)> = events
+        .iter()
+        .map(|e| (e.offset_ms as f64 / 1000.0, e.axn as f64, e.ayn as f64))
+        .collect();
+    let Some(first) = points.first().copied() else {
+        return Vec::new();
+    };
+    let end_s = end_s.min(points.last().map(|p| p.0).unwrap_or(end_s) + 1.0);
+    let step = 1.0 / fps.max(1) as f64;
+    let mut samples = Vec::new();
+    let mut index = 0;
+    let mut t = offset_s;
+    while t <= end_s {
+        while index + 1 < points.len() && points[index + 1].0 <= t {
+            index += 1;
         }
-        _ => {
-            args.extend([
-                "-c:v".into(),
-                "libx264".into(),
-                "-preset".into(),
-                "fast".into(),
-                "-pix_fmt".into(),
-                "yuv420p".into(),
-                "-b:v".into(),
-                bitrate_value.clone(),
-            ]);
+        let (x, y) = if t <= first.0 {
+            (first.1, first.2)
+        } else if index + 1 < points.len() {
+            let (a, b) = (points[index], points[index + 1]);
+            let k = ((t - a.0) / (b.0 - a.0).max(1e-6)).clamp(0.0, 1.0);
+            (a.1 + (b.1 - a.1) * k, a.2 + (b.2 - a.2) * k)
+        } else {
+            (points[index].1, points[index].2)
+        };
+        samples.push((t, x, y));
+        t += step;
+    }
+    let tau = smoothing.clamp(0.0, 1.0) as f64 * CURSOR_MAX_TAU_S;
+    if tau > 0.0 {
+        let alpha = 1.0 - (-step / tau).exp();
+        for i in 1..samples.len() {
+            let prev = samples[i - 1];
+            samples[i].1 = prev.1 + alpha * (samples[i].1 - prev.1);
+            samples[i].2 = prev.2 + alpha * (samples[i].2 - prev.2);
+        }
+        for i in (0..samples.len().saturating_sub(1)).rev() {
+            let next = samples[i + 1];
+            samples[i].1 = next.1 + alpha * (samples[i].1 - next.1);
+            samples[i].2 = next.2 + alpha * (samples[i].2 - next.2);
         }
     }
+    samples
+}
 
-    if selected_device.is_some() {
-        args.extend(["-c:a".into(), "aac".into(), "-b:a".into(), "160k".into()]);
+// The sendcmd script of the cursor stage for a range; renders remove it once
+// their ffmpeg has finished.
+fn cursor_script_path(input_path: &str, start_s: f64) -> Option<PathBuf> {
+    let dir = PathBuf::from(input_path).parent()?.to_path_buf();
+    Some(dir.join(format!("cursor_cmds_{}.txt", (start_s * 1000.0) as u64)))
+}
+
+fn remove_cursor_script(input_path: &str, start_s: f64) {
+    if let Some(path) = cursor_script_path(input_path, start_s) {
+        let _ = fs::remove_file(path);
     }
+}
 
-    args.push(output_path.to_string_lossy().to_string());
-    if camera_index.is_some() {
-        args.extend([
-            "-map".into(),
-            "[avatar]".into(),
-            "-c:v".into(),
-            "libx264".into(),
-            "-preset".into(),
-            "veryfast".into(),
-                "-crf".into(),
-                "23".into(),
-            "-pix_fmt".into(),
-            "yuv420p".into(),
-            camera_path.to_string_lossy().to_string(),
-        ]);
+// Redraws the pointer after the zoom so it stays sharp at any scale. Only for
+// captures recorded with the cursor hidden; otherwise it is already in the
+// video. Per-frame positions are mapped through the zoom in Rust and fed to
+// the overlay with a sendcmd script next to the recording, which keeps the
+// graph itself small.
+fn synthetic_cursor_stage(
+    input_path: &str,
+    edit_state: &EditState,
+    size: (u32, u32),
+    fps: u32,
+    range: (f64, f64),
+    crop: Option<&CropView>,
+    input: &str,
+) -> Option<String> {
+    if !edit_state.synthetic_cursor {
+        return None;
     }
-    if preview_url.is_some() {
-        args.extend([
-            "-map".into(),
-            "[preview]".into(),
-            "-c:v".into(),
-            "libx264".into(),
-            "-preset".into(),
-            "ultrafast".into(),
-            "-tune".into(),
-            "zerolatency".into(),
-            "-pix_fmt".into(),
-            "yuv420p".into(),
-            "-profile:v".into(),
-            "baseline".into(),
-            "-g".into(),
-            "30".into(),
-            "-keyint_min".into(),
-            "30".into(),
-            "-bf".into(),
-            "0".into(),
-            "-f".into(),
-            "rtp".into(),
-            format!("rtp://127.0.0.1:{PREVIEW_RTP_PORT}?pkt_size=1200"),
-        ]);
+    let (width, height) = (size.0 as f64, size.1 as f64);
+    let dir = PathBuf::from(input_path).parent()?.to_path_buf();
+    let meta: CaptureMeta = read_json_file(&dir.join("capture.json"))?;
+    if !meta.cursor_hidden {
+        return None;
+    }
+    let events = read_cursor_events(&dir);
+    let samples = smoothed_cursor_path(&events, fps, edit_state.cursor_smoothing, range);
+    if samples.is_empty() {
+        return None;
+    }
+    let track = load_zoom_track(input_path).unwrap_or_default();
+    let smoothed: Vec<ZoomWindow> = track
+        .windows
+        .iter()
+        .map(|w| ZoomWindow {
+            anchors: smoothed_zoom_anchors(w, &track.settings),
+            ..w.clone()
+        })
+        .collect();
+    let mut script = String::new();
+    for (t, x, y) in samples.iter() {
+        let (zoom, cx, cy) = zoom_view_at(&track, &smoothed, *t);
+        let ((x, y), (cx, cy)) = match crop {
+            Some(crop) => (crop.map_point(*t, *x, *y), crop.map_point(*t, cx, cy)),
+            None => ((*x, *y), (cx, cy)),
+        };
+        let x0 = (cx * width - width / zoom / 2.0).clamp(0.0, width - width / zoom);
+        let y0 = (cy * height - height / zoom / 2.0).clamp(0.0, height - height / zoom);
+        script.push_str(&format!(
+            "{:.3} overlay@fcursor x {:.0}, overlay@fcursor y {:.0};\n",
+            t - range.0,
+            (x * width - x0) * zoom,
+            (y * height - y0) * zoom
+        ));
     }
+    let script_path = cursor_script_path(input_path, range.0)?;
+    fs::write(&script_path, script).ok()?;
+    let size = match edit_state.cursor_size {
+        0 => 32,
+        size => size.clamp(12, 256) as i32,
+    };
+    let image = edit_state
+        .cursor_image
+        .as_deref()
+        .filter(|path| PathBuf::from(path).is_file());
+    let source = match image {
+        Some(path) => format!(
+            "movie='{}',loop=loop=-1:size=1:start=0,setpts=N/({fps}*TB),scale=-2:{size},format=rgba",
+            filter_escape_path(path)
+        ),
+        None => {
+            // Plain arrow: a white triangle with a dark outline, tip at 0,0.
+            let w = evenize((size as f32 * 0.72).round() as i32);
+            let h = evenize(size);
+            let edge = (size as f32 / 16.0).max(1.5);
+            let fill = format!(
+                "255*gte(X,{edge})*lte(X+{e2},Y)*lte(Y,{h}-0.42*X-{e2})",
+                e2 = edge * 1.5
+            );
+            format!(
+                "color=c=black@0:s={w}x{h}:r={fps},format=rgba,geq=r='{fill}':g='{fill}':b='{fill}':a='255*lte(X,Y)*lte(Y,{h}-0.42*X)'"
+            )
+        }
+    };
+    Some(format!(
+        "{source}[cursorimg];[{input}]sendcmd=f='{script}'[cursorbase];[cursorbase][cursorimg]overlay@fcursor=x=-{size}:y=-{size}:shortest=1",
+        script = filter_escape_path(&script_path.to_string_lossy())
+    ))
+}
 
-    let rect = if capture_mode == "region" {
-        region_rect.ok_or("region_required")?
+// Expanding, fading ring at every click. Positions and phase are flat sums
+// over the clicks (at most one is active at a time) rather than nested ifs,
+// so long recordings don't produce deeply nested expressions.
+fn click_ripple_stage(
+    input_path: &str,
+    edit_state: &EditState,
+    fps: u32,
+    (offset_s, end_s): (f64, f64),
+    crop: Option<&CropView>,
+    input: &str,
+) -> Option<String> {
+    if !edit_state.click_effect {
+        return None;
+    }
+    let duration_s = match edit_state.click_effect_duration_ms {
+        0 => 0.45,
+        ms => (ms as f64 / 1000.0).clamp(0.1, 3.0),
+    };
+    let radius = match edit_state.click_effect_size {
+        0 => 48,
+        size => size.clamp(8, 400) as i32,
+    };
+    let color = if edit_state.click_effect_color.trim().is_empty() {
+        "#ffffff"
     } else {
-        screen_rect.clone()
+        edit_state.click_effect_color.as_str()
     };
-    let started_at_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_millis() as u64;
-    let meta = CaptureMeta { mode: capture_mode.clone(), rect: rect.clone(), started_at_ms };
-    let _ = fs::write(output_dir.join("capture.json"), serde_json::to_string(&meta).unwrap_or_default());
+    let (r, g, b) = parse_hex_color(color);
+    let dir = PathBuf::from(input_path).parent()?.to_path_buf();
+    let mut clicks: Vec<(f64, f32, f32)> = Vec::new();
+    for event in read_cursor_events(&dir).iter().filter(|e| e.kind == "down") {
+        let t = event.offset_ms as f64 / 1000.0;
+        if t + duration_s < offset_s || t > end_s {
+            continue;
+        }
+        if clicks.last().is_some_and(|last| t < last.0 + duration_s) {
+            continue;
+        }
+        let (x, y) = (event.axn.clamp(0.0, 1.0) as f64, event.ayn.clamp(0.0, 1.0) as f64);
+        // A crop that moves during the short ripple is ignored.
+        let (x, y) = crop.map_or((x, y), |crop| crop.map_point(t, x, y));
+        clicks.push((t, x as f32, y as f32));
+    }
+    if clicks.is_empty() {
+        return None;
+    }
+    let phase = |var: &str| -> String {
+        let t = format!("({var}+{offset_s})");
+        let terms = clicks
+            .iter()
+            .map(|(c, _, _)| {
+                format!("between({t},{c},{e})*(({t}-{c})/{duration_s}+1)", e = c + duration_s)
+            })
+            .collect::<Vec<_>>()
+            .join("+");
+        format!("({terms}-1)")
+    };
+    let coord = |pick: fn(&(f64, f32, f32)) -> f32, scale: &str| -> String {
+        let t = format!("(t+{offset_s})");
+        clicks
+            .iter()
+            .map(|click| {
+                format!(
+                    "between({t},{c},{e})*{v}*{scale}",
+                    c = click.0,
+                    e = click.0 + duration_s,
+                    v = pick(click)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("+")
+    };
+    let thickness = (radius / 8).max(2);
+    let canvas = evenize(radius * 2 + thickness * 2 + 4);
+    let center = canvas / 2;
+    Some(format!(
+        "color=c=black@0:s={canvas}x{canvas}:r={fps},format=rgba,geq=r='{r}':g='{g}':b='{b}':a='st(0,{p});st(1,hypot(X-{center},Y-{center}));if(gte(ld(0),0)*lt(abs(ld(1)-{radius}*(0.3+0.7*ld(0))),{thickness}),255*(1-ld(0)),0)'[ripple];[{input}][ripple]overlay=x='{x}-{center}':y='{y}-{center}':enable='gte({pt},0)':shortest=1",
+        p = phase("T"),
+        pt = phase("t"),
+        x = coord(|c| c.1, "main_w"),
+        y = coord(|c| c.2, "main_h"),
+    ))
+}
 
-    let log_file = fs::File::create(&log_path).map_err(|e| log_error(e.to_string()))?;
+#[tauri::command]
+fn ensure_zoom_track(app: tauri::AppHandle, input_path: String) -> Result<String, AppError> {
+    let path = zoom_track_path(&input_path)?;
+    if path.exists() {
+        return Ok(path.to_string_lossy().to_string());
+    }
+    let settings = ZoomSettings::default();
+    let mut track = ZoomTrack {
+        windows: auto_zoom_windows(&app, &input_path, &settings)?,
+        settings,
+        frames: Vec::new(),
+    };
+    let path = write_zoom_track(&input_path, &mut track)?;
+    Ok(path.to_string_lossy().to_string())
+}
 
-    let bin = ffmpeg_binary_with_app_handle(&app);
-    let child = new_cmd(&bin)
-        .args(args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::null())
-        .stderr(Stdio::from(log_file))
-        .spawn()
-        .map_err(|e| log_error(format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin)))?;
+fn auto_zoom_windows(
+    app: &tauri::AppHandle,
+    input_path: &str,
+    settings: &ZoomSettings,
+) -> Result<Vec<ZoomWindow>, String> {
+    let dir = zoom_track_path(input_path)?
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    let duration_s = get_media_duration_ms(app, input_path).unwrap_or(0) as f64 / 1000.0;
+    let events = read_cursor_events(&dir);
+    Ok(derive_zoom_windows(
+        &events,
+        capture_size_for_dir(&dir),
+        duration_s,
+        settings,
+    ))
+}
 
-    let stop_flag = Arc::new(AtomicBool::new(false));
-    {
-        let started = Instant::now();
-        let stop_flag_clone = stop_flag.clone();
-        let cursor_path_clone = cursor_path.clone();
-        let rect_clone = rect.clone();
-        thread::spawn(move || {
-            #[cfg(target_os = "windows")]
-            {
-                use std::io::BufWriter;
-                use windows_sys::Win32::Foundation::POINT;
-                use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON};
-                use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
-                let file = fs::File::create(&cursor_path_clone);
-                if file.is_err() {
-                    return;
-                }
-                let mut writer = BufWriter::new(file.unwrap());
-                let mut last_btn = false;
-                let mut last_axn = -1f32;
-                let mut last_ayn = -1f32;
-                loop {
-                    if stop_flag_clone.load(Ordering::Relaxed) {
-                        break;
-                    }
-                    let mut pt = POINT { x: 0, y: 0 };
-                    let ok = unsafe { GetCursorPos(&mut pt as *mut POINT) };
-                    if ok == 0 {
-                        thread::sleep(Duration::from_millis(30));
-                        continue;
-                    }
-                    let rel_x = (pt.x - rect_clone.x) as f64;
-                    let rel_y = (pt.y - rect_clone.y) as f64;
-                    let axn = (rel_x / (rect_clone.width as f64)).clamp(0.0, 1.0) as f32;
-                    let ayn = (rel_y / (rect_clone.height as f64)).clamp(0.0, 1.0) as f32;
-                    let btn = unsafe { GetAsyncKeyState(VK_LBUTTON as i32) } < 0;
-                    let offset_ms = started.elapsed().as_millis() as u64;
-                    let mut wrote_move = false;
-                    if (axn - last_axn).abs() > 0.0001 || (ayn - last_ayn).abs() > 0.0001 {
-                        let rec = CursorEventRecord { kind: "move".into(), offset_ms, axn, ayn };
-                        if let Ok(line) = serde_json::to_string(&rec) {
-                            let _ = writeln!(writer, "{line}");
-                            wrote_move = true;
-                        }
-                        last_axn = axn;
-                        last_ayn = ayn;
-                    }
-                    if btn && !last_btn {
-                        let rec = CursorEventRecord { kind: "down".into(), offset_ms, axn, ayn };
-                        if let Ok(line) = serde_json::to_string(&rec) {
-                            let _ = writeln!(writer, "{line}");
-                            wrote_move = true;
-                        }
-                    } else if !btn && last_btn {
-                        let rec = CursorEventRecord { kind: "up".into(), offset_ms, axn, ayn };
-                        if let Ok(line) = serde_json::to_string(&rec) {
-                            let _ = writeln!(writer, "{line}");
-                            wrote_move = true;
-                        }
-                    }
-                    last_btn = btn;
-                    if !wrote_move {
-                        thread::sleep(Duration::from_millis(30));
-                    } else {
-                        thread::sleep(Duration::from_millis(10));
-                    }
-                }
-            }
-        });
+fn sanitize_zoom_settings(settings: ZoomSettings) -> ZoomSettings {
+    ZoomSettings {
+        max_zoom: settings.max_zoom.clamp(1.0, ZOOM_MAX_SCALE),
+        ramp_in_s: settings.ramp_in_s.clamp(0.0, 5.0),
+        ramp_out_s: settings.ramp_out_s.clamp(0.0, 5.0),
+        follow_threshold_px: settings.follow_threshold_px.clamp(0.0, 2000.0),
+        sample_ms: settings.sample_ms.clamp(10, 1000),
+        pan_smoothing: settings.pan_smoothing.clamp(0.0, 1.0),
+        quality: if matches!(settings.quality.as_str(), "fast" | "max") {
+            settings.quality
+        } else {
+            "high".to_string()
+        },
     }
+}
 
-    *guard = Some(RecordingSession {
-        id: session_id.clone(),
-        started_at: Instant::now(),
-        child,
-        cursor_stop: stop_flag,
-    });
+#[tauri::command]
+fn get_zoom_settings(input_path: String) -> Result<ZoomSettings, AppError> {
+    Ok(load_zoom_track(&input_path)
+        .map(|track| track.settings)
+        .unwrap_or_default())
+}
 
-    Ok(StartRecordingResponse {
-        session_id,
-        output_path: output_path.to_string_lossy().to_string(),
-        log_path: log_path.to_string_lossy().to_string(),
-        preview_url,
-        camera_path: camera_index.map(|_| camera_path.to_string_lossy().to_string()),
-    })
+#[tauri::command]
+fn set_zoom_settings(input_path: String, settings: ZoomSettings) -> Result<ZoomTrack, AppError> {
+    let mut track = load_zoom_track(&input_path).unwrap_or_default();
+    track.settings = sanitize_zoom_settings(settings);
+    write_zoom_track(&input_path, &mut track)?;
+    Ok(track)
 }
 
+// Rebuilds the click-derived windows with the current settings. Hand-placed
+// windows survive unless keep_manual is false; auto windows overlapping them
+// are dropped.
 #[tauri::command]
-async fn webrtc_create_answer(
-    preview_state: State<'_, PreviewState>,
-    offer_sdp: String,
-) -> Result<String, String> {
-    let peer = {
-        let guard = preview_state
-            .inner
-            .lock()
-            .map_err(|_| "preview_state_lock_failed")?;
-        guard
-            .as_ref()
-            .map(|session| session.peer.clone())
-            .ok_or("preview_not_ready")?
+fn regenerate_zoom_track(
+    app: tauri::AppHandle,
+    input_path: String,
+    keep_manual: Option<bool>,
+) -> Result<ZoomTrack, AppError> {
+    let mut track = load_zoom_track(&input_path).unwrap_or_default();
+    let manual: Vec<ZoomWindow> = if keep_manual.unwrap_or(true) {
+        track.windows.iter().filter(|w| w.manual).cloned().collect()
+    } else {
+        Vec::new()
+    };
+    let mut windows = auto_zoom_windows(&app, &input_path, &track.settings)?;
+    windows.retain(|auto| {
+        !manual
+            .iter()
+            .any(|m| auto.start_s < m.end_s && m.start_s < auto.end_s)
+    });
+    windows.extend(manual);
+    track.windows = windows;
+    write_zoom_track(&input_path, &mut track)?;
+    Ok(track)
+}
+
+#[tauri::command]
+fn add_zoom_region(
+    input_path: String,
+    start_s: f64,
+    end_s: f64,
+    scale: Option<f32>,
+    anchors: Option<Vec<ZoomAnchor>>,
+) -> Result<ZoomTrack, AppError> {
+    let mut track = load_zoom_track(&input_path).unwrap_or_default();
+    let mut window = ZoomWindow {
+        id: new_zoom_window_id(),
+        start_s,
+        end_s,
+        scale: scale.unwrap_or(track.settings.max_zoom),
+        manual: true,
+        anchors: anchors.unwrap_or_default(),
     };
-    let offer = RTCSessionDescription::offer(offer_sdp).map_err(|e| e.to_string())?;
-    peer.set_remote_description(offer)
-        .await
-        .map_err(|e| e.to_string())?;
-    let answer = peer.create_answer(None).await.map_err(|e| e.to_string())?;
-    let mut gather = peer.gathering_complete_promise().await;
-    peer.set_local_description(answer)
-        .await
-        .map_err(|e| e.to_string())?;
-    let _ = gather.recv().await;
-    let local = peer
-        .local_description()
-        .await
-        .ok_or("missing_local_description")?;
-    Ok(local.sdp)
+    validate_zoom_window(&mut window)?;
+    if zoom_windows_overlap(&track, window.start_s, window.end_s, None) {
+        return Err("zoom_region_overlap".into());
+    }
+    track.windows.push(window);
+    write_zoom_track(&input_path, &mut track)?;
+    Ok(track)
+}
+
+#[derive(Deserialize)]
+struct ZoomRegionPatch {
+    #[serde(default)]
+    start_s: Option<f64>,
+    #[serde(default)]
+    end_s: Option<f64>,
+    #[serde(default)]
+    scale: Option<f32>,
+    #[serde(default)]
+    anchors: Option<Vec<ZoomAnchor>>,
 }
 
 #[tauri::command]
-fn stop_recording(
-    state: State<RecordingState>,
-    preview_state: State<PreviewState>,
-) -> Result<StopRecordingResponse, String> {
-    let mut guard = state.inner.lock().map_err(|_| "state_lock_failed")?;
-    let mut session = guard.take().ok_or("no_active_recording")?;
-    session.cursor_stop.store(true, Ordering::Relaxed);
-    let duration_ms = session.started_at.elapsed().as_millis() as u64;
-    let session_id = session.id.clone();
-    if let Some(mut stdin) = session.child.stdin.take() {
-        let _ = stdin.write_all(b"q");
-        let _ = stdin.flush();
+fn update_zoom_region(
+    input_path: String,
+    id: String,
+    patch: ZoomRegionPatch,
+) -> Result<ZoomTrack, AppError> {
+    let mut track = load_zoom_track(&input_path).ok_or("zoom_track_missing")?;
+    let index = track
+        .windows
+        .iter()
+        .position(|w| w.id == id)
+        .ok_or("zoom_region_not_found")?;
+    let mut window = track.windows[index].clone();
+    if let Some(start_s) = patch.start_s {
+        window.start_s = start_s;
     }
-    let mut exited = false;
-    for _ in 0..20 {
-        if let Ok(Some(_)) = session.child.try_wait() {
-            exited = true;
-            break;
-        }
-        thread::sleep(Duration::from_millis(200));
+    if let Some(end_s) = patch.end_s {
+        window.end_s = end_s;
     }
-    if !exited {
-        let _ = session.child.kill();
-        let _ = session.child.wait();
+    if let Some(scale) = patch.scale {
+        window.scale = scale;
     }
-    if let Ok(mut preview_guard) = preview_state.inner.lock() {
-        if let Some(preview_session) = preview_guard.take() {
-            async_runtime::block_on(stop_preview_session(preview_session));
-        }
+    if let Some(anchors) = patch.anchors {
+        window.anchors = anchors;
     }
-    Ok(StopRecordingResponse {
-        session_id,
-        duration_ms,
-    })
+    window.manual = true;
+    validate_zoom_window(&mut window)?;
+    if zoom_windows_overlap(&track, window.start_s, window.end_s, Some(&id)) {
+        return Err("zoom_region_overlap".into());
+    }
+    track.windows[index] = window;
+    write_zoom_track(&input_path, &mut track)?;
+    Ok(track)
 }
 
 #[tauri::command]
-fn list_audio_devices(app: tauri::AppHandle) -> Result<Vec<String>, String> {
-    list_audio_devices_internal(&app)
+fn remove_zoom_region(input_path: String, id: String) -> Result<ZoomTrack, AppError> {
+    let mut track = load_zoom_track(&input_path).ok_or("zoom_track_missing")?;
+    let before = track.windows.len();
+    track.windows.retain(|w| w.id != id);
+    if track.windows.len() == before {
+        return Err("zoom_region_not_found".into());
+    }
+    write_zoom_track(&input_path, &mut track)?;
+    Ok(track)
 }
 
-fn list_audio_devices_internal(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
-    let bin = ffmpeg_binary_with_app_handle(app);
-    let (stderr_output, stdout_output) = new_cmd(&bin)
-        .args(["-list_devices", "true", "-f", "dshow", "-i", "dummy"])
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .and_then(|mut child| {
-            let mut stderr_bytes = Vec::new();
-            if let Some(mut stderr_reader) = child.stderr.take() {
-                let _ = stderr_reader.read_to_end(&mut stderr_bytes);
-            }
-            let mut stdout_bytes = Vec::new();
-            if let Some(mut stdout_reader) = child.stdout.take() {
-                let _ = stdout_reader.read_to_end(&mut stdout_bytes);
-            }
-            let _ = child.wait();
-            let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
-            let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
-            Ok((stderr, stdout))
-        })
-        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
+#[tauri::command]
+fn get_crop_track(input_path: String) -> Result<Option<CropTrack>, AppError> {
+    Ok(load_crop_track(&input_path))
+}
 
-    let combined = format!("{stderr_output}\n{stdout_output}");
-    Ok(parse_dshow_audio_devices(&combined))
+#[tauri::command]
+fn save_crop_track(input_path: String, mut track: CropTrack) -> Result<CropTrack, AppError> {
+    validate_crop_track(&mut track)?;
+    let path = crop_track_path(&input_path)?;
+    let data = serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?;
+    save_track_file(&path, &data)?;
+    Ok(track)
 }
 
 #[tauri::command]
-fn list_video_devices(app: tauri::AppHandle) -> Result<Vec<String>, String> {
-    list_video_devices_internal(&app)
+fn clear_crop_track(input_path: String) -> Result<(), AppError> {
+    let path = crop_track_path(&input_path)?;
+    if let Ok(current) = fs::read_to_string(&path) {
+        push_track_history(&path, "undo", &current)?;
+        clear_track_history(&path, "redo");
+        fs::remove_file(&path).map_err(|_| "track_write_failed")?;
+        refresh_project_json(&path)?;
+    }
+    Ok(())
 }
 
+// Skin-region framing: finds the largest skin-toned region in camera.mp4 and
+// writes camera_framing_track.json, which export uses to crop the bubble to it.
 #[tauri::command]
-fn list_windows() -> Result<Vec<String>, String> {
-    #[cfg(target_os = "windows")]
-    {
-        use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
-        use windows_sys::Win32::UI::WindowsAndMessaging::{
-            EnumWindows, GetWindowTextLengthW, GetWindowTextW, IsWindowVisible,
-        };
+async fn analyze_camera_framing(
+    app: tauri::AppHandle,
+    input_path: String,
+    camera_path: Option<String>,
+) -> Result<CameraFramingTrack, AppError> {
+    let camera_path = match camera_path.filter(|p| !p.is_empty()) {
+        Some(path) => path,
+        None => PathBuf::from(&input_path)
+            .parent()
+            .ok_or("invalid_input_path")?
+            .join("camera.mp4")
+            .to_string_lossy()
+            .to_string(),
+    };
+    if !PathBuf::from(&camera_path).is_file() {
+        return Err("camera_not_found".into());
+    }
+    let samples = async_runtime::spawn_blocking({
+        let app = app.clone();
+        move || scan_skin_regions(&app, &camera_path)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    if samples.is_empty() {
+        return Err("no_skin_region_found".into());
+    }
+    let mut cx: Vec<f32> = samples.iter().map(|s| s.cx).collect();
+    let mut cy: Vec<f32> = samples.iter().map(|s| s.cy).collect();
+    let mut size: Vec<f32> = samples.iter().map(|s| s.size).collect();
+    let track = CameraFramingTrack {
+        frame: Some(CameraFramingSample {
+            time_s: 0.0,
+            cx: median_f32(&mut cx),
+            cy: median_f32(&mut cy),
+            size: median_f32(&mut size),
+        }),
+        samples,
+    };
+    let path = camera_framing_path(&input_path)?;
+    let data = serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?;
+    save_track_file(&path, &data)?;
+    Ok(track)
+}
 
-        unsafe extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
-            if IsWindowVisible(hwnd) == 0 {
-                return 1;
-            }
-            let length = GetWindowTextLengthW(hwnd);
-            if length == 0 {
-                return 1;
-            }
-            let mut buffer = vec![0u16; (length + 1) as usize];
-            let written = GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
-            if written <= 0 {
-                return 1;
-            }
-            let title = String::from_utf16_lossy(&buffer[..written as usize]);
-            let trimmed = title.trim();
-            if trimmed.is_empty() {
-                return 1;
-            }
-            let titles = unsafe { &mut *(lparam as *mut Vec<String>) };
-            if !titles.iter().any(|item| item == trimmed) {
-                titles.push(trimmed.to_string());
-            }
-            1
-        }
+#[tauri::command]
+fn get_camera_framing(input_path: String) -> Result<Option<CameraFramingTrack>, AppError> {
+    Ok(load_camera_framing(&input_path))
+}
 
-        let mut titles: Vec<String> = Vec::new();
-        let result = unsafe {
-            EnumWindows(Some(enum_windows_proc), &mut titles as *mut _ as LPARAM)
-        };
-        if result == 0 {
-            return Err("list_windows_failed".into());
+#[tauri::command]
+fn clear_camera_framing(input_path: String) -> Result<(), AppError> {
+    let path = camera_framing_path(&input_path)?;
+    if let Ok(current) = fs::read_to_string(&path) {
+        push_track_history(&path, "undo", &current)?;
+        clear_track_history(&path, "redo");
+        fs::remove_file(&path).map_err(|_| "track_write_failed")?;
+        refresh_project_json(&path)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_audio_track(input_path: String) -> Result<AudioTrack, AppError> {
+    Ok(load_audio_track(&input_path).unwrap_or_default())
+}
+
+#[tauri::command]
+fn ensure_marker_track(input_path: String) -> Result<MarkerTrack, AppError> {
+    let path = marker_track_path(&input_path)?;
+    if let Ok(data) = fs::read_to_string(&path) {
+        return serde_json::from_str(&data).map_err(|_| "track_parse_failed".into());
+    }
+    let track = MarkerTrack::default();
+    let data = serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?;
+    write_file_atomic(&path, &data)?;
+    Ok(track)
+}
+
+#[tauri::command]
+fn save_marker_track(input_path: String, mut track: MarkerTrack) -> Result<MarkerTrack, AppError> {
+    track.markers.retain(|m| m.time_s.is_finite() && m.time_s >= 0.0);
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    for (idx, marker) in track.markers.iter_mut().enumerate() {
+        marker.label = marker.label.trim().replace(['\r', '\n'], " ");
+        if marker.id.trim().is_empty() {
+            marker.id = format!("m{stamp}_{idx}");
         }
-        if titles.is_empty() {
-            return Ok(Vec::new());
+        if !marker.color.starts_with('#') || marker.color.len() != 7 {
+            marker.color = marker_color_for(&marker.label).to_string();
         }
-        titles.sort();
-        return Ok(titles);
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        Ok(Vec::new())
+    track
+        .markers
+        .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal));
+    let path = marker_track_path(&input_path)?;
+    let data = serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?;
+    save_track_file(&path, &data)?;
+    Ok(track)
+}
+
+#[tauri::command]
+fn get_chapter_track(input_path: String) -> Result<ChapterTrack, AppError> {
+    Ok(load_chapter_track(&input_path).unwrap_or_default())
+}
+
+#[tauri::command]
+fn save_chapter_track(input_path: String, mut track: ChapterTrack) -> Result<ChapterTrack, AppError> {
+    track
+        .chapters
+        .retain(|c| c.time_s.is_finite() && c.time_s >= 0.0 && !c.name.trim().is_empty());
+    for chapter in track.chapters.iter_mut() {
+        chapter.name = chapter.name.trim().replace(['\r', '\n'], " ");
     }
+    track
+        .chapters
+        .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal));
+    let path = chapter_track_path(&input_path)?;
+    let data = serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?;
+    save_track_file(&path, &data)?;
+    Ok(track)
 }
 
-fn list_video_devices_internal(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
-    let bin = ffmpeg_binary_with_app_handle(app);
-    let (stderr_output, stdout_output) = new_cmd(&bin)
-        .args(["-list_devices", "true", "-f", "dshow", "-i", "dummy"])
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .and_then(|mut child| {
-            let mut stderr_bytes = Vec::new();
-            if let Some(mut stderr_reader) = child.stderr.take() {
-                let _ = stderr_reader.read_to_end(&mut stderr_bytes);
-            }
-            let mut stdout_bytes = Vec::new();
-            if let Some(mut stdout_reader) = child.stdout.take() {
-                let _ = stdout_reader.read_to_end(&mut stdout_bytes);
-            }
-            let _ = child.wait();
-            let stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
-            let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
-            Ok((stderr, stdout))
-        })
-        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
+#[tauri::command]
+fn save_audio_track(input_path: String, mut track: AudioTrack) -> Result<AudioTrack, AppError> {
+    track.fade_in_s = track.fade_in_s.clamp(0.0, 30.0);
+    track.fade_out_s = track.fade_out_s.clamp(0.0, 30.0);
+    track.keyframes.retain(|k| k.time_s.is_finite() && k.time_s >= 0.0);
+    for keyframe in track.keyframes.iter_mut() {
+        keyframe.gain = keyframe.gain.clamp(0.0, 4.0);
+    }
+    track
+        .keyframes
+        .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal));
+    let path = audio_track_path(&input_path)?;
+    let data = serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?;
+    save_track_file(&path, &data)?;
+    Ok(track)
+}
 
-    let combined = format!("{stderr_output}\n{stdout_output}");
-    Ok(parse_dshow_video_devices(&combined))
+const TRACK_HISTORY_LIMIT: usize = 30;
+
+// <session>/history/<track stem>/<stack>, holding one file per version named
+// by save time so the newest sorts last.
+fn track_history_dir(path: &Path, stack: &str) -> Result<PathBuf, String> {
+    let dir = path.parent().ok_or("invalid_input_path")?;
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("invalid_input_path")?;
+    Ok(dir.join("history").join(stem).join(stack))
 }
 
-fn parse_dshow_audio_devices(stderr: &str) -> Vec<String> {
-    let mut devices = Vec::new();
-    let mut in_audio = false;
-    for line in stderr.lines() {
-        if line.contains("DirectShow audio devices") {
-            in_audio = true;
-            continue;
-        }
-        if line.contains("DirectShow video devices") {
-            in_audio = false;
-            continue;
-        }
-        if !in_audio && !line.contains("(audio)") {
-            continue;
-        }
-        if line.contains("(none)") {
-            continue;
-        }
-        if let Some(start) = line.find('"') {
-            let rest = &line[start + 1..];
-            if let Some(end) = rest.find('"') {
-                let name = rest[..end].trim();
-                if !name.is_empty() && !devices.iter().any(|item| item == name) {
-                    devices.push(name.to_string());
-                }
-            }
-        }
+fn track_history_entries(path: &Path, stack: &str) -> Vec<PathBuf> {
+    let Ok(dir) = track_history_dir(path, stack) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    entries.retain(|p| p.extension().and_then(|e| e.to_str()) == Some("json"));
+    entries.sort();
+    entries
+}
+
+fn push_track_history(path: &Path, stack: &str, data: &str) -> Result<(), String> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = track_history_dir(path, stack)?;
+    fs::create_dir_all(&dir).map_err(|_| "track_write_failed")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let name = format!("{now:016}_{:04}.json", COUNTER.fetch_add(1, Ordering::Relaxed) % 10_000);
+    fs::write(dir.join(name), data).map_err(|_| "track_write_failed")?;
+    let entries = track_history_entries(path, stack);
+    for old in entries.iter().take(entries.len().saturating_sub(TRACK_HISTORY_LIMIT)) {
+        let _ = fs::remove_file(old);
     }
-    devices
+    Ok(())
 }
 
-fn parse_dshow_video_devices(stderr: &str) -> Vec<String> {
-    let mut devices = Vec::new();
-    let mut in_video = false;
-    for line in stderr.lines() {
-        if line.contains("DirectShow video devices") {
-            in_video = true;
-            continue;
-        }
-        if line.contains("DirectShow audio devices") {
-            in_video = false;
-            continue;
-        }
-        if !in_video && !line.contains("(video)") {
-            continue;
-        }
-        if line.contains("(none)") {
-            continue;
-        }
-        if let Some(start) = line.find('"') {
-            let rest = &line[start + 1..];
-            if let Some(end) = rest.find('"') {
-                let name = rest[..end].trim();
-                if !name.is_empty() && !devices.iter().any(|item| item == name) {
-                    devices.push(name.to_string());
-                }
-            }
+fn pop_track_history(path: &Path, stack: &str) -> Option<String> {
+    let newest = track_history_entries(path, stack).pop()?;
+    let data = fs::read_to_string(&newest).ok()?;
+    let _ = fs::remove_file(&newest);
+    Some(data)
+}
+
+fn clear_track_history(path: &Path, stack: &str) {
+    for entry in track_history_entries(path, stack) {
+        let _ = fs::remove_file(entry);
+    }
+}
+
+// Temp file plus rename, so a crash mid-save never leaves a torn track.
+fn write_file_atomic(path: &Path, data: &str) -> Result<(), String> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, data).map_err(|_| "track_write_failed")?;
+    fs::rename(&tmp, path).map_err(|_| {
+        let _ = fs::remove_file(&tmp);
+        "track_write_failed".to_string()
+    })
+}
+
+// Saves a track, keeping the version it replaces on the undo stack. A new
+// save invalidates anything that was undone.
+fn save_track_file(path: &Path, data: &str) -> Result<(), String> {
+    if let Ok(current) = fs::read_to_string(path) {
+        if current == data {
+            return Ok(());
         }
+        push_track_history(path, "undo", &current)?;
     }
-    devices
+    clear_track_history(path, "redo");
+    write_file_atomic(path, data)?;
+    refresh_project_json(path)
 }
 
-#[tauri::command]
-fn save_edit_state(output_path: String, edit_state: EditState) -> Result<(), String> {
-    let path = edit_state_path(&output_path);
-    let serialized = serde_json::to_string_pretty(&edit_state).map_err(|e| e.to_string())?;
-    fs::write(path, serialized).map_err(|e| e.to_string())?;
-    Ok(())
+#[derive(Serialize)]
+struct TrackHistoryState {
+    path: String,
+    data: Option<serde_json::Value>,
+    undo_depth: usize,
+    redo_depth: usize,
 }
 
-#[tauri::command]
-fn load_edit_state(output_path: String) -> Result<EditState, String> {
-    let path = edit_state_path(&output_path);
-    if !path.exists() {
-        return Ok(EditState::default());
+fn track_file_for(input_path: &str, track: &str) -> Result<PathBuf, String> {
+    if !matches!(track, "clip" | "camera" | "zoom" | "crop" | "audio" | "chapters" | "marker") {
+        return Err("unknown_track".to_string());
     }
-    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    serde_json::from_str(&data).map_err(|e| e.to_string())
+    let dir = PathBuf::from(input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    Ok(dir.join(format!("{track}_track.json")))
 }
 
-#[tauri::command]
-fn ensure_preview(app: tauri::AppHandle, output_path: String) -> Result<String, String> {
-    let preview = preview_path(&output_path);
-    if preview.exists() {
-        return Ok(preview.to_string_lossy().to_string());
-    }
-    let bin = ffmpeg_binary_with_app_handle(&app);
-    let status = new_cmd(&bin)
-        .args([
-            "-y",
-            "-i",
-            &output_path,
-            "-vf",
-            "scale=1024:-2",
-            "-r",
-            "30",
-            "-c:v",
-            "libx264",
-            "-preset",
-            "veryfast",
-            "-pix_fmt",
-            "yuv420p",
-            "-an",
-            preview.to_string_lossy().as_ref(),
-        ])
-        .status()
-        .map_err(|e| format!("ffmpeg_not_found: {} (bin={})", e.to_string(), bin))?;
-    if status.success() {
-        Ok(preview.to_string_lossy().to_string())
-    } else {
-        Err("preview_failed".to_string())
+// Moves the current version onto `to` and restores the newest from `from`.
+fn step_track_history(input_path: &str, track: &str, from: &str, to: &str) -> Result<TrackHistoryState, String> {
+    let path = track_file_for(input_path, track)?;
+    let restored = pop_track_history(&path, from).ok_or_else(|| format!("nothing_to_{from}"))?;
+    if let Ok(current) = fs::read_to_string(&path) {
+        push_track_history(&path, to, &current)?;
     }
+    write_file_atomic(&path, &restored)?;
+    refresh_project_json(&path)?;
+    Ok(TrackHistoryState {
+        path: path.to_string_lossy().to_string(),
+        data: serde_json::from_str(&restored).ok(),
+        undo_depth: track_history_entries(&path, "undo").len(),
+        redo_depth: track_history_entries(&path, "redo").len(),
+    })
+}
+
+#[tauri::command]
+fn undo_track(input_path: String, track: String) -> Result<TrackHistoryState, AppError> {
+    Ok(step_track_history(&input_path, &track, "undo", "redo")?)
+}
+
+#[tauri::command]
+fn redo_track(input_path: String, track: String) -> Result<TrackHistoryState, AppError> {
+    Ok(step_track_history(&input_path, &track, "redo", "undo")?)
 }
 
 fn cursor_path_for_dir(dir: &PathBuf) -> Result<PathBuf, String> {
@@ -2470,7 +12492,7 @@ fn cursor_path_for_dir(dir: &PathBuf) -> Result<PathBuf, String> {
 }
 
 #[tauri::command]
-fn ensure_clip_track(app: tauri::AppHandle, input_path: String) -> Result<String, String> {
+fn ensure_clip_track(app: tauri::AppHandle, input_path: String) -> Result<String, AppError> {
     let dir = PathBuf::from(&input_path)
         .parent()
         .ok_or("invalid_input_path")?
@@ -2485,13 +12507,16 @@ fn ensure_clip_track(app: tauri::AppHandle, input_path: String) -> Result<String
         segments.push(ClipSegment { start_s: 0.0, end_s: (duration_ms as f64) / 1000.0, speed: None });
     }
     let track = ClipTrack { segments };
-    fs::write(&path, serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?)
+    let value = to_versioned_value(&track, CLIP_TRACK_MIGRATIONS)
+        .map_err(|_| "track_serialize_failed")?;
+    fs::write(&path, serde_json::to_string(&value).map_err(|_| "track_serialize_failed")?)
         .map_err(|_| "track_write_failed")?;
+    refresh_project_json(&path)?;
     Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn ensure_cursor_track(input_path: String) -> Result<String, String> {
+fn ensure_cursor_track(input_path: String) -> Result<String, AppError> {
     let dir = PathBuf::from(&input_path)
         .parent()
         .ok_or("invalid_input_path")?
@@ -2501,18 +12526,19 @@ fn ensure_cursor_track(input_path: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn save_clip_track(input_path: String, track_json: String) -> Result<String, String> {
+fn save_clip_track(input_path: String, track_json: String) -> Result<String, AppError> {
     let dir = PathBuf::from(&input_path)
         .parent()
         .ok_or("invalid_input_path")?
         .to_path_buf();
     let path = dir.join("clip_track.json");
-    fs::write(&path, track_json).map_err(|_| "track_write_failed".to_string())?;
+    let track_json = stamp_track_json(track_json, CLIP_TRACK_MIGRATIONS);
+    save_track_file(&path, &track_json)?;
     Ok(path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn ensure_camera_track(app: tauri::AppHandle, input_path: String) -> Result<String, String> {
+fn ensure_camera_track(app: tauri::AppHandle, input_path: String) -> Result<String, AppError> {
     let dir = PathBuf::from(&input_path)
         .parent()
         .ok_or("invalid_input_path")?
@@ -2537,70 +12563,200 @@ fn ensure_camera_track(app: tauri::AppHandle, input_path: String) -> Result<Stri
         Vec::new()
     };
     let track = CameraTrack { segments };
-    fs::write(&path, serde_json::to_string(&track).map_err(|_| "track_serialize_failed")?)
+    let value = to_versioned_value(&track, CAMERA_TRACK_MIGRATIONS)
+        .map_err(|_| "track_serialize_failed")?;
+    fs::write(&path, serde_json::to_string(&value).map_err(|_| "track_serialize_failed")?)
         .map_err(|_| "track_write_failed")?;
+    refresh_project_json(&path)?;
     Ok(path.to_string_lossy().to_string())
 }
 
+// Brings footage recorded elsewhere into a session as its recording.mp4, so
+// the editor and export treat it like a capture without cursor data.
 #[tauri::command]
-fn load_click_markers(input_path: String) -> Result<Vec<f64>, String> {
-    let dir = PathBuf::from(&input_path)
-        .parent()
-        .ok_or("invalid_input_path")?
-        .to_path_buf();
-    let cursor_path = {
-        let direct = dir.join("cursor.jsonl");
-        if direct.exists() {
-            direct
-        } else {
-            let mut found: Option<PathBuf> = None;
-            if let Ok(entries) = fs::read_dir(&dir) {
-                for entry in entries.flatten() {
-                    let p = entry.path();
-                    if p
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .map(|n| n.ends_with("cursor.jsonl"))
-                        .unwrap_or(false)
-                    {
-                        found = Some(p);
-                        break;
-                    }
-                }
-            }
-            found.ok_or("cursor_events_missing")?
+async fn import_media(
+    app: tauri::AppHandle,
+    session_id: String,
+    path: String,
+) -> Result<ImportMediaResponse, AppError> {
+    tauri::async_runtime::spawn_blocking(move || import_media_into_session(app, session_id, path))
+        .await
+        .map_err(|_| "import_join_failed".to_string())?
+        .map_err(AppError::from)
+}
+
+fn import_media_into_session(
+    app: tauri::AppHandle,
+    session_id: String,
+    path: String,
+) -> Result<ImportMediaResponse, String> {
+    let source = PathBuf::from(&path);
+    if !source.is_file() {
+        return Err("media_not_found".to_string());
+    }
+    let ext = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    if !matches!(ext.as_str(), "mp4" | "mov" | "m4v") {
+        return Err("unsupported_media".to_string());
+    }
+    let probe = probe_media(&app, &path).ok_or("media_probe_failed")?;
+    let (width, height) = match (probe.has_video, probe.width, probe.height) {
+        (true, Some(width), Some(height)) => (width, height),
+        _ => return Err("unsupported_media".to_string()),
+    };
+    let dir = match session_dir(&session_id) {
+        Err(err) if err == "session_not_found" => {
+            let dir = work_base_dir().join(&session_id);
+            fs::create_dir_all(&dir).map_err(|_| "session_create_failed".to_string())?;
+            dir
         }
+        other => other?,
     };
-    let data = fs::read_to_string(&cursor_path).map_err(|_| "cursor_read_failed")?;
-    let mut times_s: Vec<f64> = Vec::new();
-    for line in data.lines() {
-        if let Ok(rec) = serde_json::from_str::<CursorEventRecord>(line) {
-            if rec.kind == "down" {
-                times_s.push((rec.offset_ms as f64) / 1000.0);
+    let output_path = dir.join("recording.mp4");
+    if output_path.exists() {
+        return Err("session_not_empty".to_string());
+    }
+    // A hard link avoids duplicating large files; copy across volumes.
+    if fs::hard_link(&source, &output_path).is_err() {
+        fs::copy(&source, &output_path).map_err(|_| "media_copy_failed".to_string())?;
+    }
+    let started_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_millis() as u64;
+    let meta = CaptureMeta {
+        mode: "import".to_string(),
+        rect: Rect { x: 0, y: 0, width: width as i32, height: height as i32 },
+        started_at_ms,
+        speed_factor: None,
+        cursor_hidden: false,
+        title: None,
+        favorite: false,
+        tags: Vec::new(),
+    };
+    let _ = fs::write(dir.join("capture.json"), serde_json::to_string(&meta).unwrap_or_default());
+    let output = output_path.to_string_lossy().to_string();
+    ensure_clip_track(app.clone(), output.clone())?;
+    ensure_zoom_track(app, output.clone())?;
+    Ok(ImportMediaResponse {
+        session_id,
+        output_path: output,
+        probe,
+    })
+}
+
+// Two clicks closer than this in time and distance count as a double click.
+const DOUBLE_CLICK_S: f64 = 0.4;
+const DOUBLE_CLICK_PX: f32 = 6.0;
+// A press that travels further than this before release is a drag.
+const DRAG_MIN_PX: f32 = 8.0;
+
+// Positions are normalized to the capture; end_* is where the button was
+// released, which differs from the press only for drags.
+#[derive(Serialize, Clone)]
+struct ClickMarker {
+    kind: String,
+    start_s: f64,
+    end_s: f64,
+    x: f32,
+    y: f32,
+    end_x: f32,
+    end_y: f32,
+}
+
+fn click_markers_from_events(events: &[CursorEventRecord], capture_size: (f32, f32)) -> Vec<ClickMarker> {
+    let distance_px = |ax: f32, ay: f32, bx: f32, by: f32| {
+        ((ax - bx) * capture_size.0).hypot((ay - by) * capture_size.1)
+    };
+    let mut markers: Vec<ClickMarker> = Vec::new();
+    let mut pressed: Option<&CursorEventRecord> = None;
+    for event in events.iter() {
+        match event.kind.as_str() {
+            "down" => pressed = Some(event),
+            "up" => {
+                let Some(down) = pressed.take() else {
+                    continue;
+                };
+                let start_s = down.offset_ms as f64 / 1000.0;
+                let end_s = (event.offset_ms as f64 / 1000.0).max(start_s);
+                let drag = distance_px(down.axn, down.ayn, event.axn, event.ayn) > DRAG_MIN_PX;
+                if !drag {
+                    if let Some(last) = markers.last_mut().filter(|m| {
+                        m.kind == "click"
+                            && start_s - m.end_s <= DOUBLE_CLICK_S
+                            && distance_px(m.x, m.y, down.axn, down.ayn) <= DOUBLE_CLICK_PX
+                    }) {
+                        last.kind = "double_click".to_string();
+                        last.end_s = end_s;
+                        continue;
+                    }
+                }
+                markers.push(ClickMarker {
+                    kind: if drag { "drag" } else { "click" }.to_string(),
+                    start_s,
+                    end_s,
+                    x: down.axn,
+                    y: down.ayn,
+                    end_x: event.axn,
+                    end_y: event.ayn,
+                });
             }
+            _ => {}
         }
     }
-    Ok(times_s)
+    // A press still held when recording stopped.
+    if let Some(down) = pressed {
+        let start_s = down.offset_ms as f64 / 1000.0;
+        markers.push(ClickMarker {
+            kind: "click".to_string(),
+            start_s,
+            end_s: start_s,
+            x: down.axn,
+            y: down.ayn,
+            end_x: down.axn,
+            end_y: down.ayn,
+        });
+    }
+    markers
+}
+
+#[tauri::command]
+fn load_click_markers(input_path: String) -> Result<Vec<ClickMarker>, AppError> {
+    let dir = PathBuf::from(&input_path)
+        .parent()
+        .ok_or("invalid_input_path")?
+        .to_path_buf();
+    let cursor_path = cursor_path_for_dir(&dir)?;
+    let data = fs::read_to_string(&cursor_path).map_err(|_| "cursor_read_failed")?;
+    let events: Vec<CursorEventRecord> = data
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CursorEventRecord>(line).ok())
+        .collect();
+    Ok(click_markers_from_events(&events, capture_size_for_dir(&dir)))
 }
 #[tauri::command]
-fn save_camera_track(input_path: String, track_json: String) -> Result<String, String> {
+fn save_camera_track(input_path: String, track_json: String) -> Result<String, AppError> {
     let dir = PathBuf::from(&input_path)
         .parent()
         .ok_or("invalid_input_path")?
         .to_path_buf();
     let path = dir.join("camera_track.json");
-    fs::write(&path, track_json).map_err(|_| "track_write_failed".to_string())?;
+    let track_json = stamp_track_json(track_json, CAMERA_TRACK_MIGRATIONS);
+    save_track_file(&path, &track_json)?;
     Ok(path.to_string_lossy().to_string())
 }
 #[tauri::command]
-fn get_export_dir() -> Result<String, String> {
+fn get_export_dir() -> Result<String, AppError> {
     Ok(export_dir_with_fallback()
         .to_string_lossy()
         .to_string())
 }
 
 #[tauri::command]
-fn open_path(path: String) -> Result<(), String> {
+fn open_path(path: String) -> Result<(), AppError> {
     #[cfg(target_os = "windows")]
     {
         let mut target = {
@@ -2616,71 +12772,622 @@ fn open_path(path: String) -> Result<(), String> {
     #[cfg(not(target_os = "windows"))]
     {
         let _ = path;
-        Err("unsupported_platform".to_string())
+        Err("unsupported_platform".into())
+    }
+}
+#[derive(Serialize)]
+struct ExportEstimate {
+    output_path: String,
+    duration_ms: u64,
+    estimated_bytes: u64,
+    free_bytes: Option<u64>,
+    warning: Option<String>,
+    // True when the export will be a stream-copy remux of the recording,
+    // which takes seconds rather than a full encode.
+    remux: bool,
+}
+
+// Output duration of the request, honouring start_s/end_s.
+fn export_duration_ms(app: &tauri::AppHandle, request: &ExportRequest) -> u64 {
+    let total_s = effective_duration_ms(app, &request.input_path) as f64 / 1000.0;
+    let (start_s, end_s) = export_output_range(request);
+    ((end_s.min(total_s) - start_s).max(0.0) * 1000.0).round() as u64
+}
+
+fn effective_duration_ms(app: &tauri::AppHandle, input_path: &str) -> u64 {
+    let media_ms = get_media_duration_ms(app, input_path).unwrap_or(0);
+    let Some(track) = load_clip_track(input_path).filter(|t| !t.segments.is_empty()) else {
+        return media_ms;
+    };
+    let media_s = if media_ms > 0 { media_ms as f64 / 1000.0 } else { f64::MAX };
+    let total_s: f64 = clip_segments_window(&track, 0.0, media_s)
+        .iter()
+        .map(|seg| (seg.end_s - seg.start_s) / seg.speed)
+        .sum();
+    (total_s * 1000.0).round() as u64
+}
+
+#[cfg(target_os = "windows")]
+fn free_disk_bytes(path: &PathBuf) -> Option<u64> {
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let mut probe = path.clone();
+    while !probe.exists() {
+        probe = probe.parent()?.to_path_buf();
+    }
+    let wide: Vec<u16> = probe
+        .to_string_lossy()
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available: u64 = 0;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        None
+    } else {
+        Some(available)
     }
 }
+
+#[cfg(not(target_os = "windows"))]
+fn free_disk_bytes(path: &PathBuf) -> Option<u64> {
+    let _ = path;
+    None
+}
+
+#[tauri::command]
+fn estimate_export(app: tauri::AppHandle, request: ExportRequest) -> Result<ExportEstimate, AppError> {
+    let request = apply_export_preset(request)?;
+    let output_path = normalize_export_output_path(&request);
+    let duration_ms = export_duration_ms(&app, &request);
+    let total_kbps = request.profile.bitrate_kbps.max(1) as u64 + EXPORT_AUDIO_KBPS;
+    let remux_range = trim_only_range(&app, &ExportRequest {
+        output_path: output_path.clone(),
+        ..request.clone()
+    });
+    // ~3% container overhead on top of the nominal bitrates. CRF output size
+    // depends on the content, so bitrate_kbps stands in as a guess. A remux
+    // is the matching share of the recording file.
+    let estimated_bytes = match (remux_range, request.profile.rate_control.as_ref()) {
+        (Some((start_s, end_s)), _) => {
+            let source_bytes = fs::metadata(&request.input_path).map(|m| m.len()).unwrap_or(0);
+            let source_s = get_media_duration_ms(&app, &request.input_path).unwrap_or(0) as f64 / 1000.0;
+            if source_s > 0.0 {
+                (source_bytes as f64 * ((end_s - start_s) / source_s).min(1.0)) as u64
+            } else {
+                source_bytes
+            }
+        }
+        (None, Some(RateControl::TargetSize { size_mb })) => (size_mb.max(0.0) * 1_000_000.0) as u64,
+        (None, _) => total_kbps * 1000 / 8 * duration_ms / 1000 * 103 / 100,
+    };
+    let free_bytes = PathBuf::from(&output_path)
+        .parent()
+        .and_then(|dir| free_disk_bytes(&dir.to_path_buf()));
+    let warning = match free_bytes {
+        Some(free) if free < estimated_bytes => Some("insufficient_disk_space".to_string()),
+        Some(free) if free < estimated_bytes.saturating_mul(2) => Some("low_disk_space".to_string()),
+        _ if duration_ms == 0 => Some("duration_unknown".to_string()),
+        _ => None,
+    };
+    Ok(ExportEstimate {
+        output_path,
+        duration_ms,
+        estimated_bytes,
+        free_bytes,
+        warning,
+        remux: remux_range.is_some(),
+    })
+}
+
+fn profile_aspect(profile: &ExportProfile) -> Option<&'static str> {
+    if profile.width == 0 || profile.height == 0 {
+        return None;
+    }
+    Some(match profile.width.cmp(&profile.height) {
+        std::cmp::Ordering::Greater => "16:9",
+        std::cmp::Ordering::Equal => "1:1",
+        std::cmp::Ordering::Less => "9:16",
+    })
+}
+
+// Flags the export pipeline depends on, which extra args may not override.
+const RESERVED_FFMPEG_FLAGS: &[&str] = &[
+    "-i",
+    "-y",
+    "-n",
+    "-filter_complex",
+    "-filter_complex_script",
+    "-lavfi",
+    "-filter",
+    "-filter_script",
+    "-vf",
+    "-af",
+    "-map",
+    "-progress",
+    "-stats",
+    "-nostats",
+    "-f",
+];
+
+// Flags that take no value; every other flag takes exactly one.
+const VALUELESS_FFMPEG_FLAGS: &[&str] = &[
+    "-re",
+    "-noautorotate",
+    "-an",
+    "-vn",
+    "-sn",
+    "-dn",
+    "-shortest",
+    "-copyts",
+    "-start_at_zero",
+    "-copytb",
+    "-xerror",
+];
+
+// Args go to ffmpeg directly, not through a shell, so there is nothing to
+// quote. The list must be strict flag/value pairs, so no stray token can
+// become an extra output; reserved flags, empty tokens and NULs are rejected.
+fn validate_extra_args(args: &[String]) -> Result<(), String> {
+    if args.iter().any(|arg| arg.is_empty() || arg.contains('\0')) {
+        return Err("extra_args_invalid: empty or NUL argument".to_string());
+    }
+    let mut tokens = args.iter();
+    while let Some(arg) = tokens.next() {
+        let is_flag = arg.len() > 1
+            && arg.starts_with('-')
+            && !arg[1..].starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        if !is_flag {
+            return Err(format!("extra_args_invalid: expected a flag, got {arg}"));
+        }
+        let flag = arg.split(':').next().unwrap_or(arg);
+        if RESERVED_FFMPEG_FLAGS.contains(&flag) {
+            return Err(format!("extra_args_reserved: {arg}"));
+        }
+        if !VALUELESS_FFMPEG_FLAGS.contains(&flag) && tokens.next().is_none() {
+            return Err(format!("extra_args_invalid: {arg} needs a value"));
+        }
+    }
+    Ok(())
+}
+
+// The layout saved for the profile's aspect, or the request's layout
+// re-targeted to it.
+fn edit_state_for_profile(request: &ExportRequest, profile: &ExportProfile) -> EditState {
+    let edit_state = request.edit_state.clone();
+    let Some(aspect) = profile_aspect(profile).filter(|aspect| *aspect != edit_state.aspect) else {
+        return edit_state;
+    };
+    aspect_edit_state_path(&request.input_path, aspect)
+        .ok()
+        .filter(|path| path.exists())
+        .and_then(|path| read_edit_state(&path).ok())
+        .unwrap_or(EditState {
+            aspect: aspect.to_string(),
+            ..edit_state
+        })
+}
+
+#[derive(Serialize, Clone)]
+struct ExportPreset {
+    name: String,
+    label: String,
+    profile: ExportProfile,
+}
+
+// Platform targets, following each platform's upload recommendations.
+fn builtin_export_presets() -> Vec<ExportPreset> {
+    let preset = |name: &str, label: &str, (width, height): (u32, u32), fps: u32, bitrate_kbps: u32, lufs: f32| {
+        ExportPreset {
+            name: name.to_string(),
+            label: label.to_string(),
+            profile: ExportProfile {
+                format: "h264".to_string(),
+                width,
+                height,
+                fps,
+                bitrate_kbps,
+                loudness_lufs: Some(lufs),
+                true_peak_db: Some(-1.0),
+                ..ExportProfile::default()
+            },
+        }
+    };
+    vec![
+        preset("youtube_1080p", "YouTube 1080p", (1920, 1080), 60, 12_000, -14.0),
+        preset("youtube_4k", "YouTube 4K", (3840, 2160), 60, 53_000, -14.0),
+        preset("tiktok", "TikTok", (1080, 1920), 30, 8_000, -14.0),
+        preset("reels", "Instagram Reels", (1080, 1920), 30, 8_000, -14.0),
+        preset("x", "X / Twitter", (1280, 720), 30, 5_000, -16.0),
+    ]
+}
+
+// Replaces the profile with the named preset's, keeping the encoder choice.
+fn apply_export_preset(request: ExportRequest) -> Result<ExportRequest, String> {
+    let default_preset = load_settings().defaults.export_preset.filter(|_| request.profile.width == 0);
+    let Some(name) = request.preset.as_deref().or(default_preset.as_deref()) else {
+        return Ok(request);
+    };
+    let preset = builtin_export_presets()
+        .into_iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| format!("unknown_export_preset: {name}"))?;
+    let profile = ExportProfile {
+        encoder: request.profile.encoder.clone(),
+        ..preset.profile
+    };
+    Ok(ExportRequest {
+        edit_state: edit_state_for_profile(&request, &profile),
+        profile,
+        ..request
+    })
+}
+
+#[tauri::command]
+fn list_export_presets() -> Vec<ExportPreset> {
+    builtin_export_presets()
+}
+
+// One request of a batch, written next to the base output with a size
+// suffix so the files don't collide.
+fn batch_request(request: &ExportRequest, profile: ExportProfile) -> ExportRequest {
+    let edit_state = edit_state_for_profile(request, &profile);
+    // Same-size encodes in different codecs would otherwise share a name.
+    let format_suffix = match profile.format.as_str() {
+        "" | "h264" => String::new(),
+        format => format!("_{format}"),
+    };
+    let base = PathBuf::from(normalize_video_output_path(request));
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("export");
+    let ext = base.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+    let output_path = base
+        .with_file_name(format!("{stem}_{}x{}{}.{ext}", profile.width, profile.height, format_suffix))
+        .to_string_lossy()
+        .to_string();
+    ExportRequest {
+        output_path,
+        edit_state,
+        profile,
+        ..request.clone()
+    }
+}
+
+// Marker labels become part of a file name: ASCII letters, digits and dashes
+// only, at most 40 characters.
+fn file_name_slug(label: &str) -> String {
+    let slug: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    slug.chars().take(40).collect()
+}
+
+// Splits the request into one request per clip segment, each limited to the
+// segment's range on the edited timeline and named <stem>_<nn>[_<marker>].
+fn split_segment_requests(request: &ExportRequest) -> Result<Vec<ExportRequest>, String> {
+    let segments = load_clip_track(&request.input_path)
+        .map(|track| clip_segments_window(&track, 0.0, f64::MAX))
+        .unwrap_or_default();
+    let markers = marker_track_path(&request.input_path)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str::<MarkerTrack>(&data).ok())
+        .unwrap_or_default()
+        .markers;
+    let base = PathBuf::from(normalize_video_output_path(request));
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("export");
+    let ext = base.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+    let (range_start, range_end) = export_output_range(request);
+    let mut requests = Vec::new();
+    let mut out_s = 0.0;
+    for seg in segments.iter() {
+        let seg_out = (out_s, out_s + (seg.end_s - seg.start_s) / seg.speed);
+        out_s = seg_out.1;
+        let (start_s, end_s) = (seg_out.0.max(range_start), seg_out.1.min(range_end));
+        if end_s - start_s < 0.05 {
+            continue;
+        }
+        let label = markers
+            .iter()
+            .find(|m| m.time_s >= seg.start_s && m.time_s < seg.end_s)
+            .map(|m| file_name_slug(&m.label))
+            .filter(|slug| !slug.is_empty())
+            .map(|slug| format!("_{slug}"))
+            .unwrap_or_default();
+        let output_path = base
+            .with_file_name(format!("{stem}_{:02}{label}.{ext}", requests.len() + 1))
+            .to_string_lossy()
+            .to_string();
+        requests.push(ExportRequest {
+            output_path,
+            start_s: Some(start_s),
+            end_s: Some(end_s),
+            split_segments: false,
+            ..request.clone()
+        });
+    }
+    if requests.is_empty() {
+        return Err("no_clip_segments".to_string());
+    }
+    Ok(requests)
+}
+
+// With profiles set, the request is exported once per profile and the jobs
+// share a group id; request.profile is ignored. split_segments groups the
+// per-segment jobs the same way.
 #[tauri::command]
 fn start_export(
     app: tauri::AppHandle,
     state: State<ExportState>,
     request: ExportRequest,
-) -> Result<ExportStartResponse, String> {
+    profiles: Option<Vec<ExportProfile>>,
+) -> Result<ExportStartResponse, AppError> {
+    let request = apply_export_preset(request)?;
     let job_id = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|e| e.to_string())?
         .as_millis()
         .to_string();
-    let normalized_output = normalize_export_output_path(&request);
-    let status = ExportStatus {
-        job_id: job_id.clone(),
-        state: "queued".to_string(),
-        progress: 0.0,
-        error: None,
-        output_path: Some(normalized_output.clone()),
+    let (start_s, end_s) = export_output_range(&request);
+    if !start_s.is_finite() || start_s < 0.0 || end_s <= start_s {
+        return Err("invalid_range".into());
+    }
+    validate_extra_args(&request.extra_input_args)?;
+    validate_extra_args(&request.extra_output_args)?;
+    check_denoise_model(&app, &request)?;
+    if request.image_sequence.is_some() && !request.extra_outputs.is_empty() {
+        return Err("invalid_image_sequence".into());
+    }
+    let split = request.split_segments;
+    let requests = if split { split_segment_requests(&request)? } else { vec![request] };
+    let (group_id, requests) = match profiles.filter(|profiles| !profiles.is_empty()) {
+        Some(profiles) => (
+            Some(job_id.clone()),
+            requests
+                .iter()
+                .flat_map(|request| profiles.iter().map(|profile| batch_request(request, profile.clone())))
+                .collect::<Vec<_>>(),
+        ),
+        None => (split.then(|| job_id.clone()), requests),
     };
+    let requests = merge_shared_renders(requests);
+    let mut job_ids = Vec::new();
+    let mut output_paths = Vec::new();
+    let mut statuses = Vec::new();
     {
         let mut guard = state.inner.lock().map_err(|_| "export_state_lock_failed")?;
-        guard.statuses.insert(job_id.clone(), status.clone());
-        guard.queue.push_back(ExportJob {
-            job_id: job_id.clone(),
-            request: ExportRequest {
-                output_path: normalized_output,
-                ..request
-            },
-        });
+        for (idx, request) in requests.into_iter().enumerate() {
+            let id = if group_id.is_some() { format!("{job_id}_{idx}") } else { job_id.clone() };
+            let normalized_output = normalize_export_output_path(&request);
+            let status = ExportStatus {
+                job_id: id.clone(),
+                state: "queued".to_string(),
+                progress: 0.0,
+                error: None,
+                output_path: Some(normalized_output.clone()),
+                group_id: group_id.clone(),
+                fps: None,
+                speed: None,
+                eta_s: None,
+                poster_path: None,
+            };
+            guard.statuses.insert(id.clone(), status.clone());
+            enqueue_export_job(
+                &mut guard,
+                ExportJob {
+                    job_id: id.clone(),
+                    group_id: group_id.clone(),
+                    request: ExportRequest {
+                        output_path: normalized_output,
+                        ..request
+                    },
+                    priority: 0,
+                },
+            );
+            job_ids.push(id);
+            statuses.push(status);
+        }
+        for job in guard.queue.iter().filter(|job| job_ids.contains(&job.job_id)) {
+            output_paths.extend(export_outputs(&job.request).into_iter().map(|r| r.output_path));
+        }
+        persist_export_queue(&guard);
+    }
+    for status in statuses.iter() {
+        emit_export_status(&app, status);
     }
-    emit_export_status(&app, &status);
     ensure_export_worker(app, state.inner.clone());
-    Ok(ExportStartResponse { job_id })
+    Ok(ExportStartResponse {
+        job_id: job_ids[0].clone(),
+        group_id,
+        job_ids,
+        output_paths,
+    })
+}
+
+#[tauri::command]
+fn list_encoder_presets(bitrate_kbps: Option<u32>) -> Result<Vec<EncoderPresetInfo>, AppError> {
+    let builtin = builtin_encoder_presets();
+    let user = read_user_encoder_presets();
+    let bitrate_kbps = bitrate_kbps.unwrap_or(8000);
+    Ok(encoder_presets()
+        .into_iter()
+        .map(|preset| {
+            let default = builtin.iter().find(|b| b.name == preset.name);
+            let overridden = match default {
+                Some(b) => serde_json::to_value(b).ok() != serde_json::to_value(&preset).ok(),
+                None => user.iter().any(|u| u.name == preset.name),
+            };
+            EncoderPresetInfo {
+                args: encoder_preset_args(&preset, bitrate_args(bitrate_kbps)),
+                builtin: default.is_some(),
+                overridden,
+                preset,
+            }
+        })
+        .collect())
 }
 
 #[tauri::command]
 fn get_export_status(
     state: State<ExportState>,
     job_id: String,
-) -> Result<ExportStatus, String> {
+) -> Result<ExportStatus, AppError> {
     let guard = state.inner.lock().map_err(|_| "export_state_lock_failed")?;
     guard
         .statuses
         .get(&job_id)
         .cloned()
-        .ok_or_else(|| "export_not_found".to_string())
+        .ok_or_else(|| "export_not_found".into())
+}
+
+// Everything ffmpeg printed for the job, one block per ffmpeg run (segmented
+// exports have one per part).
+#[tauri::command]
+fn get_export_log(job_id: String) -> Result<String, AppError> {
+    let path = export_log_path(&job_id).ok_or("invalid_job_id")?;
+    fs::read_to_string(path).map_err(|_| "export_log_not_found".into())
+}
+
+// Running and queued jobs first, then finished ones newest first.
+#[tauri::command]
+fn list_export_jobs(
+    state: State<ExportState>,
+    limit: Option<usize>,
+) -> Result<Vec<ExportHistoryEntry>, AppError> {
+    let mut entries = Vec::new();
+    {
+        let guard = state.inner.lock().map_err(|_| "export_state_lock_failed")?;
+        for job in guard.current.iter().chain(guard.queue.iter()) {
+            let status = guard.statuses.get(&job.job_id);
+            entries.push(ExportHistoryEntry {
+                job_id: job.job_id.clone(),
+                group_id: job.group_id.clone(),
+                input_path: job.request.input_path.clone(),
+                output_path: job.request.output_path.clone(),
+                state: status.map(|s| s.state.clone()).unwrap_or_else(|| "queued".to_string()),
+                error: None,
+                started_at_ms: guard.started_at_ms.get(&job.job_id).copied(),
+                finished_at_ms: None,
+                elapsed_ms: None,
+                duration_ms: None,
+            });
+        }
+    }
+    entries.extend(read_export_history().into_iter().rev());
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
+    Ok(entries)
+}
+
+// Changes a queued job's priority and re-slots it. A running job can't be
+// reprioritized.
+#[tauri::command]
+fn set_export_priority(
+    state: State<ExportState>,
+    job_id: String,
+    priority: i32,
+) -> Result<(), AppError> {
+    let mut guard = state.inner.lock().map_err(|_| "export_state_lock_failed")?;
+    let index = guard
+        .queue
+        .iter()
+        .position(|job| job.job_id == job_id)
+        .ok_or("export_not_queued")?;
+    let Some(mut job) = guard.queue.remove(index) else {
+        return Err("export_not_queued".into());
+    };
+    job.priority = priority;
+    enqueue_export_job(&mut guard, job);
+    persist_export_queue(&guard);
+    Ok(())
+}
+
+// Moves a queued job to `index` (0 = next to run). Its priority is clamped to
+// its new neighbours' so the queue stays ordered for later inserts.
+#[tauri::command]
+fn move_export_job(state: State<ExportState>, job_id: String, index: usize) -> Result<(), AppError> {
+    let mut guard = state.inner.lock().map_err(|_| "export_state_lock_failed")?;
+    let from = guard
+        .queue
+        .iter()
+        .position(|job| job.job_id == job_id)
+        .ok_or("export_not_queued")?;
+    let Some(mut job) = guard.queue.remove(from) else {
+        return Err("export_not_queued".into());
+    };
+    let index = index.min(guard.queue.len());
+    if let Some(next) = guard.queue.get(index) {
+        job.priority = job.priority.max(next.priority);
+    }
+    if let Some(prev) = index.checked_sub(1).and_then(|i| guard.queue.get(i)) {
+        job.priority = job.priority.min(prev.priority);
+    }
+    guard.queue.insert(index, job);
+    persist_export_queue(&guard);
+    Ok(())
 }
 
 #[tauri::command]
-fn cancel_export(state: State<ExportState>, job_id: String) -> Result<(), String> {
+fn cancel_export(
+    app: tauri::AppHandle,
+    state: State<ExportState>,
+    job_id: String,
+) -> Result<(), AppError> {
     let mut guard = state.inner.lock().map_err(|_| "export_state_lock_failed")?;
+    // A queued job is finished here; waiting for the worker to pop it could
+    // take as long as the export in front of it.
+    if let Some(index) = guard.queue.iter().position(|job| job.job_id == job_id) {
+        let job = guard.queue.remove(index).ok_or("export_not_found")?;
+        let status = guard.statuses.get_mut(&job_id).map(|status| {
+            status.state = "cancelled".to_string();
+            status.error = None;
+            status.clone()
+        });
+        persist_export_queue(&guard);
+        drop(guard);
+        if let Some(status) = status {
+            emit_export_status(&app, &status);
+        }
+        append_export_history(ExportHistoryEntry {
+            job_id: job.job_id.clone(),
+            group_id: job.group_id.clone(),
+            input_path: job.request.input_path.clone(),
+            output_path: job.request.output_path.clone(),
+            state: "cancelled".to_string(),
+            error: None,
+            started_at_ms: None,
+            finished_at_ms: Some(unix_now_ms()),
+            elapsed_ms: None,
+            duration_ms: None,
+        });
+        return Ok(());
+    }
     guard.cancellations.insert(job_id.clone(), true);
-    if let Some(status) = guard.statuses.get_mut(&job_id) {
-        status.state = "cancelled".to_string();
+    // try_lock: a locked child is already being reaped after exiting. The
+    // worker writes the final "cancelled" status and removes the partial file.
+    for child in guard.children.get(&job_id).into_iter().flatten() {
+        if let Ok(mut child) = child.try_lock() {
+            let _ = child.kill();
+        }
     }
+    persist_export_queue(&guard);
     Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    maybe_migrate_old_recordings();
+    install_crash_handler();
+    let _ = fs::create_dir_all(work_base_dir());
     let _ = fs::create_dir_all(export_dir_with_fallback());
+    init_encoder_presets();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_autostart::init(
@@ -2693,27 +13400,141 @@ pub fn run() {
         .manage(RecordingState::new())
         .manage(PreviewState::new())
         .manage(ExportState::new())
+        .setup(|app| {
+            if let Ok(dir) = app.path().resolve("mockups", BaseDirectory::Resource) {
+                let _ = MOCKUP_DIR.set(dir);
+            }
+            let handle = app.handle().clone();
+            thread::spawn(move || {
+                let _ = HARDWARE_ENCODERS.set(detect_hardware_encoders(&handle));
+            });
+            spawn_system_event_watcher(app.handle().clone());
+            setup_tray(app)?;
+            spawn_update_checker(app.handle().clone());
+            thread::spawn(|| {
+                let days = load_settings().retention.trash_purge_days.unwrap_or(TRASH_PURGE_DAYS);
+                let _ = empty_trash(Some(days));
+                apply_retention_policy();
+            });
+            thread::spawn(|| {
+                complete_crash_reports();
+                upload_pending_crash_reports();
+            });
+            restore_export_queue(app.handle(), &app.state::<ExportState>().inner);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             start_recording,
+            continue_recording,
+            import_media,
+            quick_trim,
+            get_hardware_encoders,
+            get_ffmpeg_info,
+            check_for_updates,
+            list_crash_reports,
+            get_crash_report,
+            delete_crash_report,
+            upload_crash_report,
+            export_frame,
             stop_recording,
+            describe_error,
+            pause_recording,
+            resume_recording,
+            list_recording_presets,
+            get_default_recording_request,
+            save_recording_preset,
+            delete_recording_preset,
+            apply_recording_preset,
+            run_capture_diagnostics,
+            export_app_state,
+            import_app_state,
             webrtc_create_answer,
+            set_preview_audio_muted,
+            set_preview_quality,
+            start_camera_preview,
+            stop_camera_preview,
+            get_preview_settings,
+            get_settings,
+            update_settings,
+            set_preview_settings,
             list_audio_devices,
             list_video_devices,
             list_windows,
             exclude_window_from_capture,
             save_edit_state,
             load_edit_state,
+            save_edit_state_for_aspect,
+            load_edit_state_for_aspect,
+            list_themes,
+            install_theme,
+            apply_theme,
+            migrate_session,
+            import_legacy_recordings,
+            delete_session,
+            rename_session,
+            export_session_archive,
+            import_session_archive,
+            move_session,
+            list_trash,
+            restore_session,
+            empty_trash,
+            set_session_favorite,
+            set_session_tags,
+            list_sessions,
+            search_sessions,
+            get_storage_usage,
+            get_retention_policy,
+            set_retention_policy,
             ensure_preview,
+            render_loop,
+            render_preview_range,
+            generate_scrub_thumbnails,
+            generate_proxy,
+            resolve_editor_media,
+            get_frame,
             ensure_cursor_track,
             ensure_clip_track,
             save_clip_track,
+            detect_silence,
+            attach_subtitles,
+            export_subtitles,
             ensure_camera_track,
+            ensure_zoom_track,
+            add_zoom_region,
+            update_zoom_region,
+            remove_zoom_region,
+            get_zoom_settings,
+            set_zoom_settings,
+            regenerate_zoom_track,
+            get_crop_track,
+            save_crop_track,
+            clear_crop_track,
+            get_audio_track,
+            save_audio_track,
+            ensure_marker_track,
+            save_marker_track,
+            analyze_camera_framing,
+            get_camera_framing,
+            clear_camera_framing,
+            get_chapter_track,
+            save_chapter_track,
+            undo_track,
+            redo_track,
+            load_project,
+            save_project,
             save_camera_track,
             load_click_markers,
             get_export_dir,
             open_path,
             start_export,
+            estimate_export,
+            list_encoder_presets,
+            list_export_presets,
             get_export_status,
+            list_export_jobs,
+            get_export_log,
+            set_export_priority,
+            move_export_job,
             cancel_export
         ])
         .run(tauri::generate_context!())
@@ -1,5 +1,6 @@
 import { useEffect, useMemo, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import { getCurrentWindow, PhysicalSize } from "@tauri-apps/api/window";
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
@@ -10,6 +11,7 @@ import { FiCamera, FiFolder, FiMic, FiMonitor, FiPlay, FiSettings, FiVideo } fro
 import { Button } from "@heroui/react";
 import "./App.css";
 import { SelectMenu, type SelectOption } from "./components/SelectMenu";
+import { describeError } from "./errors";
 
 type CaptureMode = "screen" | "window" | "region";
 
@@ -29,6 +31,13 @@ type AppSettings = {
 
 type UpdateInfo = Awaited<ReturnType<typeof check>>;
 
+type RecordingStarted = {
+  output_path: string;
+  log_path: string;
+  preview_url?: string | null;
+  camera_path?: string | null;
+};
+
 type RegionSelectionPayload = {
   id: number;
   status: "selected" | "cancel";
@@ -77,7 +86,7 @@ function RegionPicker() {
       await appWindow.setAlwaysOnTop(true);
       await appWindow.setFullscreen(true);
     };
-    apply().catch((error) => setErrorMessage(String(error)));
+    apply().catch((error) => setErrorMessage(describeError(error)));
     document.documentElement.style.background = "transparent";
     document.body.style.background = "transparent";
     return () => {
@@ -93,7 +102,7 @@ function RegionPicker() {
         localStorage.setItem(REGION_SELECTION_KEY, JSON.stringify(payload));
         getCurrentWindow()
           .close()
-          .catch((error) => setErrorMessage(String(error)));
+          .catch((error) => setErrorMessage(describeError(error)));
       }
     };
     window.addEventListener("keydown", handleKey);
@@ -235,7 +244,7 @@ function MainApp() {
   useEffect(() => {
     invoke<string[]>("list_audio_devices")
       .then((devices) => setAudioDevices(devices))
-      .catch((error) => setErrorMessage(String(error)));
+      .catch((error) => setErrorMessage(describeError(error)));
   }, []);
   useEffect(() => {
     invoke<string>("get_export_dir")
@@ -246,7 +255,7 @@ function MainApp() {
   useEffect(() => {
     invoke<string[]>("list_video_devices")
       .then((devices) => setVideoDevices(devices))
-      .catch((error) => setErrorMessage(String(error)));
+      .catch((error) => setErrorMessage(describeError(error)));
   }, []);
 
   useEffect(() => {
@@ -289,7 +298,7 @@ function MainApp() {
           }
           return { ok: true, message: "" };
         } catch (error) {
-          return { ok: false, message: String(error) };
+          return { ok: false, message: describeError(error) };
         }
       }
       return { ok: false, message: "更新源不可用" };
@@ -321,7 +330,7 @@ function MainApp() {
         setUpdateStatus("idle");
         return;
       }
-      const raw = String(error);
+      const raw = describeError(error);
       if (raw.includes("valid release JSON")) {
         setUpdateStatus("error");
         setUpdateMessage("更新源不可用，请确认 Release 已上传 latest.json 与 .sig");
@@ -344,7 +353,7 @@ function MainApp() {
       await relaunch();
     } catch (error) {
       setUpdateStatus("error");
-      setUpdateMessage(String(error));
+      setUpdateMessage(describeError(error));
     }
   };
 
@@ -385,7 +394,7 @@ function MainApp() {
       }
       updateSetting({ autostart: target });
     } catch (error) {
-      setErrorMessage(String(error));
+      setErrorMessage(describeError(error));
     } finally {
       setAutostartLoading(false);
     }
@@ -417,7 +426,7 @@ function MainApp() {
         updateSetting({ exportDir: selected });
       }
     } catch (error) {
-      setErrorMessage(String(error));
+      setErrorMessage(describeError(error));
     }
   };
 
@@ -479,12 +488,7 @@ function MainApp() {
   }) => {
     setErrorMessage("");
     try {
-      const response = await invoke<{
-        output_path: string;
-        log_path: string;
-        preview_url?: string | null;
-        camera_path?: string | null;
-      }>("start_recording", {
+      const response = await invoke<RecordingStarted>("start_recording", {
         request: {
           resolution: resolutionValue(settings.resolution),
           fps: settings.fps,
@@ -496,34 +500,79 @@ function MainApp() {
           region: options?.region ?? null,
         },
       });
-      const startedAt = Date.now();
-      localStorage.setItem("recordingActive", "1");
-      localStorage.setItem("recordingStart", startedAt.toString());
       localStorage.setItem("selectedCamera", camera);
       localStorage.setItem("selectedMic", mic);
-      localStorage.setItem("recordingOutputPath", response.output_path);
-      localStorage.setItem("recordingLogPath", response.log_path);
-      if (response.preview_url) {
-        localStorage.setItem("recordingPreviewUrl", response.preview_url);
-      } else {
-        localStorage.removeItem("recordingPreviewUrl");
-      }
-      if (response.camera_path) {
-        localStorage.setItem("recordingCameraPath", response.camera_path);
-      } else {
-        localStorage.removeItem("recordingCameraPath");
-      }
-      localStorage.removeItem("recordingFinished");
-      setOutputPath(response.output_path);
-      setLogPath(response.log_path);
-      setIsRecording(true);
-      await openMiniWindow();
-      await getCurrentWindow().hide();
+      await showRecordingStarted(response);
     } catch (error) {
-      setErrorMessage(String(error));
+      setErrorMessage(describeError(error));
+    }
+  };
+
+  // Shared by the record button and a recording started from the tray.
+  const showRecordingStarted = async (response: RecordingStarted) => {
+    const startedAt = Date.now();
+    localStorage.setItem("recordingActive", "1");
+    localStorage.setItem("recordingStart", startedAt.toString());
+    localStorage.removeItem("recordingPausedAt");
+    localStorage.setItem("recordingOutputPath", response.output_path);
+    localStorage.setItem("recordingLogPath", response.log_path);
+    if (response.preview_url) {
+      localStorage.setItem("recordingPreviewUrl", response.preview_url);
+    } else {
+      localStorage.removeItem("recordingPreviewUrl");
+    }
+    if (response.camera_path) {
+      localStorage.setItem("recordingCameraPath", response.camera_path);
+    } else {
+      localStorage.removeItem("recordingCameraPath");
     }
+    localStorage.removeItem("recordingFinished");
+    setOutputPath(response.output_path);
+    setLogPath(response.log_path);
+    setIsRecording(true);
+    await openMiniWindow();
+    await getCurrentWindow().hide();
   };
 
+  // The tray drives the same start/stop flow as the windows; mirror it here.
+  useEffect(() => {
+    const showRecordingEnded = async () => {
+      localStorage.removeItem("recordingActive");
+      localStorage.removeItem("recordingStart");
+      localStorage.removeItem("recordingPausedAt");
+      localStorage.removeItem("selectedCamera");
+      localStorage.removeItem("selectedMic");
+      localStorage.removeItem("recordingPreviewUrl");
+      setIsRecording(false);
+      const miniWindow = await WebviewWindow.getByLabel("mini");
+      if (miniWindow) {
+        await miniWindow.close();
+      }
+      const appWindow = getCurrentWindow();
+      await appWindow.show();
+      await appWindow.setFocus();
+      openEditWindow().catch((error) => setErrorMessage(describeError(error)));
+    };
+    const unlisteners = [
+      listen<RecordingStarted>("tray_recording_started", (event) => {
+        showRecordingStarted(event.payload).catch((error) => setErrorMessage(describeError(error)));
+      }),
+      listen<string>("tray_recording_stopped", showRecordingEnded),
+      // The recorder could not be restarted after sleep or a lock; the
+      // backend has already saved what was captured.
+      listen<{ message?: string }>("recording_resume_failed", async (event) => {
+        await showRecordingEnded();
+        setErrorMessage(event.payload?.message ?? String(event.payload));
+      }),
+      listen<{ message?: string }>("tray_action_failed", (event) => {
+        setErrorMessage(event.payload?.message ?? String(event.payload));
+      }),
+    ];
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((stop) => stop()));
+    };
+  }, []);
+
   const handleToggleRecord = async () => {
     if (isRecording) {
       return;
@@ -546,7 +595,7 @@ function MainApp() {
       setWindowOptions(windows);
       setWindowPickerOpen(true);
     } catch (error) {
-      setErrorMessage(String(error));
+      setErrorMessage(describeError(error));
     }
   };
 
@@ -583,7 +632,7 @@ function MainApp() {
       updateAwaitingRegion(false);
       await appWindow.show();
       await appWindow.setFocus();
-      setErrorMessage(String(error));
+      setErrorMessage(describeError(error));
     }
   };
 
@@ -599,7 +648,7 @@ function MainApp() {
         localStorage.removeItem("recordingFinished");
         const appWindow = getCurrentWindow();
         appWindow.show().then(() => appWindow.setFocus()).catch(() => null);
-        openEditWindow().catch((error) => setErrorMessage(String(error)));
+        openEditWindow().catch((error) => setErrorMessage(describeError(error)));
       }
       if (event.key === REGION_SELECTION_KEY && event.newValue) {
         if (!awaitingRegionRef.current) {
@@ -629,7 +678,7 @@ function MainApp() {
       return;
     }
     startRecording({ captureMode: "region", region: pendingRegion }).catch((error) =>
-      setErrorMessage(String(error))
+      setErrorMessage(describeError(error))
     );
     setPendingRegion(null);
   }, [pendingRegion, startRecording]);
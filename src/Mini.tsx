@@ -1,11 +1,13 @@
 import { useEffect, useMemo, useRef, useState } from "react";
 import ReactDOM from "react-dom/client";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { getCurrentWindow, LogicalPosition, LogicalSize } from "@tauri-apps/api/window";
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
-import { FiSquare } from "react-icons/fi";
+import { FiSquare, FiVolume2, FiVolumeX } from "react-icons/fi";
 import { Button } from "@heroui/react";
 import "./App.css";
+import { describeError } from "./errors";
 
 function Mini() {
   const [isRecording, setIsRecording] = useState(
@@ -19,6 +21,7 @@ function Mini() {
   const [previewUrl, setPreviewUrl] = useState(
     () => localStorage.getItem("recordingPreviewUrl") ?? ""
   );
+  const [monitorAudio, setMonitorAudio] = useState(false);
   const videoRef = useRef<HTMLVideoElement | null>(null);
   const peerRef = useRef<RTCPeerConnection | null>(null);
 
@@ -40,13 +43,17 @@ function Mini() {
 
   useEffect(() => {
     invoke("exclude_window_from_capture", { label: "mini" }).catch((error) =>
-      setErrorMessage(String(error))
+      setErrorMessage(describeError(error))
     );
   }, []);
 
   useEffect(() => {
-    const startedAt = Number(localStorage.getItem("recordingStart") ?? 0);
     const tick = () => {
+      // Frozen while paused; resuming moves recordingStart past the pause.
+      if (localStorage.getItem("recordingPausedAt")) {
+        return;
+      }
+      const startedAt = Number(localStorage.getItem("recordingStart") ?? 0);
       if (!startedAt) {
         setSeconds((current) => current + 1);
         return;
@@ -58,6 +65,25 @@ function Mini() {
     return () => window.clearInterval(intervalId);
   }, []);
 
+  useEffect(() => {
+    const unlisteners = [
+      listen("tray_recording_paused", () => {
+        localStorage.setItem("recordingPausedAt", Date.now().toString());
+      }),
+      listen("tray_recording_resumed", () => {
+        const pausedAt = Number(localStorage.getItem("recordingPausedAt") ?? 0);
+        const startedAt = Number(localStorage.getItem("recordingStart") ?? 0);
+        if (pausedAt && startedAt) {
+          localStorage.setItem("recordingStart", (startedAt + Date.now() - pausedAt).toString());
+        }
+        localStorage.removeItem("recordingPausedAt");
+      }),
+    ];
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((stop) => stop()));
+    };
+  }, []);
+
   useEffect(() => {
     const handleStorage = (event: StorageEvent) => {
       if (event.key === "recordingActive") {
@@ -110,6 +136,7 @@ function Mini() {
     const pc = new RTCPeerConnection();
     peerRef.current = pc;
     pc.addTransceiver("video", { direction: "recvonly" });
+    pc.addTransceiver("audio", { direction: "recvonly" });
     pc.ontrack = (event) => {
       if (!active || !videoRef.current) {
         return;
@@ -147,7 +174,7 @@ function Mini() {
       }
       await pc.setRemoteDescription({ type: "answer", sdp: answerSdp });
     };
-    start().catch((error) => setErrorMessage(String(error)));
+    start().catch((error) => setErrorMessage(describeError(error)));
     return () => {
       active = false;
       if (peerRef.current) {
@@ -165,6 +192,7 @@ function Mini() {
       await invoke("stop_recording");
       localStorage.removeItem("recordingActive");
       localStorage.removeItem("recordingStart");
+      localStorage.removeItem("recordingPausedAt");
       localStorage.removeItem("selectedCamera");
       localStorage.removeItem("selectedMic");
       localStorage.removeItem("recordingPreviewUrl");
@@ -180,7 +208,7 @@ function Mini() {
         await miniWindow.close();
       }
     } catch (error) {
-      setErrorMessage(String(error));
+      setErrorMessage(describeError(error));
     }
   };
 
@@ -195,7 +223,7 @@ function Mini() {
                 className="h-full w-full object-cover"
                 src={isWebRtcPreview ? undefined : previewSrc}
                 autoPlay
-                muted
+                muted={!monitorAudio}
                 playsInline
               />
             ) : (
@@ -207,6 +235,16 @@ function Mini() {
             <div className="text-[12px] font-semibold text-white">{timerText}</div>
           </div>
           <div className="flex-1" />
+          {previewUrl && isRecording && isWebRtcPreview ? (
+            <button
+              className="flex h-6 w-6 items-center justify-center rounded-md text-slate-300 hover:text-white cursor-pointer"
+              type="button"
+              onClick={() => setMonitorAudio((current) => !current)}
+              aria-label={monitorAudio ? "静音监听" : "监听麦克风"}
+            >
+              {monitorAudio ? <FiVolume2 size={14} /> : <FiVolumeX size={14} />}
+            </button>
+          ) : null}
           <Button
             className="flex h-12 w-12 items-center justify-center rounded-full border border-red-400/40 bg-red-500/15 text-red-200 transition hover:bg-red-500/25 disabled:opacity-60 cursor-pointer"
             type="button"
@@ -8,6 +8,7 @@ import { Toaster, toast } from "react-hot-toast";
 import "./App.css";
 
 import { SelectMenu, type SelectOption } from "./components/SelectMenu";
+import { describeError } from "./errors";
 import { motion } from "framer-motion";
 import { Button } from "@heroui/react";
 import TimelineUI from "./components/TimelineUI";
@@ -47,6 +48,11 @@ type ExportStatus = {
   progress: number;
   error?: string | null;
   output_path?: string;
+  group_id?: string | null;
+  fps?: number | null;
+  speed?: number | null;
+  eta_s?: number | null;
+  poster_path?: string | null;
 };
 
 const aspectOptions: SelectOption[] = [
@@ -809,7 +815,7 @@ const EditPage = () => {
       }
       await invoke("open_path", { path: target });
     } catch (error) {
-      toast.error(String(error).split("\n")[0].slice(0, 140));
+      toast.error(describeError(error).split("\n")[0].slice(0, 140));
     }
   };
 
@@ -881,9 +887,9 @@ const EditPage = () => {
         job_id: "",
         state: "failed",
         progress: 0,
-        error: String(error),
+        error: describeError(error),
       });
-      toast.error(String(error).split("\n")[0].slice(0, 140));
+      toast.error(describeError(error).split("\n")[0].slice(0, 140));
     }
   };
   const togglePreviewPlayback = () => {
@@ -0,0 +1,6 @@
+// Commands reject with { code, params, message }; plugins and JS still throw
+// strings or Error objects.
+export const describeError = (error: unknown): string => {
+  const message = (error as { message?: unknown } | null)?.message;
+  return typeof message === "string" ? message : String(error);
+};